@@ -3,7 +3,12 @@ use ratatui::layout::Layout;
 use ratatui::style::Style;
 use ratatui::Frame;
 
-use std::{any::Any, fmt::Debug};
+use std::{
+    any::Any,
+    fmt::Debug,
+    io,
+    process::{Command, ExitStatus},
+};
 
 #[derive(Debug, Clone)]
 pub struct AppInfo {
@@ -18,6 +23,20 @@ pub trait App: Any + Debug {
     fn update(&mut self, msg: &Self::Msg);
     fn info(&self) -> AppInfo;
     fn generate_msg(&self, key_event: KeyEvent) -> Option<Self::Msg>;
+    fn tick(&mut self) {}
+
+    /// Whether the app handles a key that otherwise has a global binding,
+    /// like Tab while it completes input.
+    fn captures_key(&self, _key: &KeyEvent) -> bool {
+        false
+    }
+
+    /// A program the app wants to run in the terminal. The TUI is suspended
+    /// while it runs and `resume` is called once it exits.
+    fn take_command(&mut self) -> Option<Command> {
+        None
+    }
+    fn resume(&mut self, _status: io::Result<ExitStatus>) {}
 }
 
 pub trait AppMessage: Any {}
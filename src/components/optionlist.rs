@@ -75,6 +75,14 @@ impl OptionListState {
         self.selected.remove(&self.highlighted.unwrap());
     }
 
+    pub fn set_selected(&mut self, index: usize, selected: bool) {
+        if selected {
+            self.selected.insert(index);
+        } else {
+            self.selected.remove(&index);
+        }
+    }
+
     pub fn is_selected(&self, index: usize) -> bool {
         return self.selected.contains(&index);
     }
@@ -119,9 +127,9 @@ impl StatefulWidget for OptionList {
             .enumerate()
             .map(|(index, item)| {
                 if state.is_selected(index) {
-                    format!("[ ] {:<3}", item)
+                    format!("{} {:<3}", self.selected_marker, item)
                 } else {
-                    format!("[*] {:<3}", item)
+                    format!("{} {:<3}", self.unselected_marker, item)
                 }
             })
             .collect();
@@ -0,0 +1,575 @@
+use chrono::{Local, NaiveDate};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use zip::{write::SimpleFileOptions, ZipArchive, ZipWriter};
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, BufReader, Read, Write},
+    os::unix::fs::{symlink, MetadataExt, OpenOptionsExt, PermissionsExt},
+    path::{Component, Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use super::batch::BatchReport;
+use super::listing::{EntryKind, FileEntry};
+use super::vfs::FileSystem;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Tar,
+    TarGz,
+    TarZst,
+    Zip,
+}
+
+impl ArchiveFormat {
+    pub fn detect(path: &Path) -> Option<ArchiveFormat> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveFormat::TarGz)
+        } else if name.ends_with(".tar.zst") || name.ends_with(".tzst") {
+            Some(ArchiveFormat::TarZst)
+        } else if name.ends_with(".tar") {
+            Some(ArchiveFormat::Tar)
+        } else if name.ends_with(".zip") {
+            Some(ArchiveFormat::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+/// An archive file browsed as a read-only directory tree. Members are
+/// indexed once on open; reading them streams through the archive again.
+#[derive(Debug)]
+pub struct Archive {
+    pub path: PathBuf,
+    format: ArchiveFormat,
+    /// Members by their path inside the archive, including directories the
+    /// archive only implies through the paths of their contents.
+    members: BTreeMap<PathBuf, FileEntry>,
+}
+
+impl Archive {
+    pub fn open(path: &Path) -> io::Result<Archive> {
+        let format = ArchiveFormat::detect(path)
+            .ok_or_else(|| io::Error::other(format!("{} is not an archive", path.display())))?;
+        let mut archive = Archive {
+            path: path.to_path_buf(),
+            format,
+            members: BTreeMap::new(),
+        };
+
+        match format {
+            ArchiveFormat::Zip => archive.index_zip()?,
+            _ => archive.index_tar()?,
+        }
+
+        let implied: Vec<PathBuf> = archive
+            .members
+            .keys()
+            .flat_map(|inner| inner.ancestors().skip(1))
+            .filter(|ancestor| !ancestor.as_os_str().is_empty())
+            .map(Path::to_path_buf)
+            .collect();
+        for dir in implied {
+            let path = archive.path.join(&dir);
+            archive
+                .members
+                .entry(dir)
+                .or_insert_with(|| member(path, EntryKind::Dir, 0, 0o755, None));
+        }
+
+        Ok(archive)
+    }
+
+    fn reader(&self) -> io::Result<Box<dyn Read>> {
+        let file = BufReader::new(fs::File::open(&self.path)?);
+        Ok(match self.format {
+            ArchiveFormat::TarGz => Box::new(GzDecoder::new(file)),
+            ArchiveFormat::TarZst => Box::new(zstd::Decoder::with_buffer(file)?),
+            _ => Box::new(file),
+        })
+    }
+
+    fn index_tar(&mut self) -> io::Result<()> {
+        let mut tar = tar::Archive::new(self.reader()?);
+        for entry in tar.entries()? {
+            let entry = entry?;
+            let Some(inner) = sanitize(&entry.path()?) else {
+                continue;
+            };
+
+            let header = entry.header();
+            let entry_type = header.entry_type();
+            let kind = if entry_type.is_dir() {
+                EntryKind::Dir
+            } else if entry_type.is_symlink() {
+                EntryKind::Symlink
+            } else if entry_type.is_file() {
+                EntryKind::File
+            } else {
+                EntryKind::Other
+            };
+            let modified = header
+                .mtime()
+                .ok()
+                .map(|time| UNIX_EPOCH + Duration::from_secs(time));
+
+            let mut entry_member = member(
+                self.path.join(&inner),
+                kind,
+                entry.size(),
+                header.mode().unwrap_or(0o644),
+                modified,
+            );
+            entry_member.uid = header.uid().unwrap_or_default() as u32;
+            entry_member.gid = header.gid().unwrap_or_default() as u32;
+            entry_member.link_target = entry.link_name()?.map(|target| target.into_owned());
+            self.members.insert(inner, entry_member);
+        }
+        Ok(())
+    }
+
+    fn index_zip(&mut self) -> io::Result<()> {
+        let mut zip = ZipArchive::new(fs::File::open(&self.path)?)?;
+        for index in 0..zip.len() {
+            let mut file = zip.by_index(index)?;
+            let Some(inner) = file.enclosed_name().as_deref().and_then(sanitize) else {
+                continue;
+            };
+
+            let kind = if file.is_dir() {
+                EntryKind::Dir
+            } else if file.is_symlink() {
+                EntryKind::Symlink
+            } else {
+                EntryKind::File
+            };
+            let default_mode = if kind == EntryKind::Dir { 0o755 } else { 0o644 };
+            let modified = file.last_modified().and_then(|time| {
+                NaiveDate::from_ymd_opt(time.year().into(), time.month().into(), time.day().into())?
+                    .and_hms_opt(
+                        time.hour().into(),
+                        time.minute().into(),
+                        time.second().into(),
+                    )?
+                    .and_local_timezone(Local)
+                    .single()
+                    .map(SystemTime::from)
+            });
+
+            let mut entry_member = member(
+                self.path.join(&inner),
+                kind,
+                file.size(),
+                file.unix_mode().unwrap_or(default_mode),
+                modified,
+            );
+            // Zip stores the target of a symlink as its content.
+            if kind == EntryKind::Symlink {
+                let mut target = String::new();
+                file.read_to_string(&mut target)?;
+                entry_member.link_target = Some(PathBuf::from(target));
+            }
+            self.members.insert(inner, entry_member);
+        }
+        Ok(())
+    }
+
+    /// Streams every member of the archive through `visit` in archive order.
+    fn each_member(
+        &self,
+        mut visit: impl FnMut(&Path, &mut dyn Read) -> io::Result<()>,
+    ) -> io::Result<()> {
+        if self.format == ArchiveFormat::Zip {
+            let mut zip = ZipArchive::new(fs::File::open(&self.path)?)?;
+            for index in 0..zip.len() {
+                let mut file = zip.by_index(index)?;
+                if let Some(inner) = file.enclosed_name().as_deref().and_then(sanitize) {
+                    visit(&inner, &mut file)?;
+                }
+            }
+            return Ok(());
+        }
+
+        let mut tar = tar::Archive::new(self.reader()?);
+        for entry in tar.entries()? {
+            let mut entry = entry?;
+            if let Some(inner) = sanitize(&entry.path()?) {
+                visit(&inner, &mut entry)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The path of `path` inside the archive; empty for the archive root.
+    fn inner(&self, path: &Path) -> io::Result<PathBuf> {
+        path.strip_prefix(&self.path)
+            .ok()
+            .filter(|inner| inner.as_os_str().is_empty() || self.members.contains_key(*inner))
+            .map(Path::to_path_buf)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("{} is not in the archive", path.display()),
+                )
+            })
+    }
+
+    /// Writes `member` and everything below it to `dest`.
+    fn extract(&self, member: &Path, dest: &Path) -> io::Result<()> {
+        // A file member is written to `dest` itself, not to `dest/`.
+        let below = |inner: &Path| {
+            let rel = inner.strip_prefix(member).ok()?;
+            Some(if rel.as_os_str().is_empty() {
+                dest.to_path_buf()
+            } else {
+                dest.join(rel)
+            })
+        };
+
+        // Directories come first since archives may list them after their
+        // contents, or not at all.
+        for (inner, entry) in &self.members {
+            if let Some(out) = below(inner).filter(|_| entry.is_dir()) {
+                check_no_symlinks(dest, &out)?;
+                fs::create_dir_all(out)?;
+            }
+        }
+
+        self.each_member(|inner, reader| {
+            let (Some(out), Some(entry)) = (below(inner), self.members.get(inner)) else {
+                return Ok(());
+            };
+            if let Some(parent) = out.parent() {
+                check_no_symlinks(dest, parent)?;
+            }
+            match (entry.kind, &entry.link_target) {
+                (EntryKind::File, _) => {
+                    let mut file = fs::OpenOptions::new()
+                        .write(true)
+                        .create_new(true)
+                        .mode(0o600)
+                        .custom_flags(libc::O_NOFOLLOW)
+                        .open(&out)?;
+                    io::copy(reader, &mut file)?;
+                    // Setuid and setgid bits aren't taken from untrusted archives.
+                    file.set_permissions(fs::Permissions::from_mode(entry.mode & 0o777))
+                }
+                (EntryKind::Symlink, Some(target)) => symlink(target, &out),
+                _ => Ok(()),
+            }
+        })
+    }
+
+    /// Extracts the whole archive into the directory `dest`.
+    pub fn extract_all(&self, dest: &Path) -> io::Result<()> {
+        fs::create_dir_all(dest)?;
+        self.extract(Path::new(""), dest)
+    }
+}
+
+impl FileSystem for Archive {
+    fn archive(&self) -> Option<&Path> {
+        Some(&self.path)
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<FileEntry>> {
+        let dir = self.inner(path)?;
+        Ok(self
+            .members
+            .iter()
+            .filter(|(inner, _)| inner.parent() == Some(dir.as_path()))
+            .map(|(_, entry)| entry.clone())
+            .collect())
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FileEntry> {
+        let inner = self.inner(path)?;
+        Ok(match self.members.get(&inner) {
+            Some(entry) => entry.clone(),
+            // The archive itself is the root directory.
+            None => member(path.to_path_buf(), EntryKind::Dir, 0, 0o755, None),
+        })
+    }
+
+    fn read(&self, path: &Path, limit: u64) -> io::Result<Vec<u8>> {
+        let member = self.inner(path)?;
+        let mut content = None;
+        self.each_member(|inner, reader| {
+            if content.is_none() && inner == member {
+                let mut buffer = vec![];
+                reader.take(limit).read_to_end(&mut buffer)?;
+                content = Some(buffer);
+            }
+            Ok(())
+        })?;
+        content.ok_or_else(|| io::Error::other(format!("{} has no content", path.display())))
+    }
+
+    fn copy_out(&self, path: &Path, dest: &Path) -> io::Result<()> {
+        if dest.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", dest.display()),
+            ));
+        }
+        let member = self.inner(path)?;
+        self.extract(&member, dest)
+    }
+}
+
+fn member(
+    path: PathBuf,
+    kind: EntryKind,
+    size: u64,
+    mode: u32,
+    modified: Option<SystemTime>,
+) -> FileEntry {
+    FileEntry {
+        // Links inside archives aren't resolved, but aren't broken either.
+        target_kind: (kind == EntryKind::Symlink).then_some(EntryKind::Other),
+        ..FileEntry::new(path, kind, size, mode, modified)
+    }
+}
+
+/// Normalises a member path, rejecting any that would escape the archive.
+fn sanitize(path: &Path) -> Option<PathBuf> {
+    let mut clean = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => clean.push(part),
+            Component::CurDir | Component::RootDir => {}
+            Component::ParentDir | Component::Prefix(_) => return None,
+        }
+    }
+    (!clean.as_os_str().is_empty()).then_some(clean)
+}
+
+/// Fails if `dir` or any directory between it and `dest` is a symlink. An
+/// archive can hold a link like `x -> /etc` followed by a member `x/passwd`,
+/// which would otherwise be written through the link outside `dest`.
+fn check_no_symlinks(dest: &Path, dir: &Path) -> io::Result<()> {
+    let Ok(relative) = dir.strip_prefix(dest) else {
+        return Ok(());
+    };
+    let mut current = dest.to_path_buf();
+    for component in relative.components() {
+        current.push(component);
+        if fs::symlink_metadata(&current).is_ok_and(|metadata| metadata.is_symlink()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} would be written through a symlink", current.display()),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Archives `targets` into `dest`, picking the format from its extension.
+pub fn create(dest: &Path, targets: &[FileEntry], report: &mut BatchReport) -> io::Result<()> {
+    let format = ArchiveFormat::detect(dest)
+        .ok_or_else(|| io::Error::other("Unsupported archive format"))?;
+    // Never truncate an existing file, which may even be one of the targets.
+    let file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(dest)
+        .map_err(|err| match err.kind() {
+            io::ErrorKind::AlreadyExists => io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", dest.display()),
+            ),
+            _ => err,
+        })?;
+
+    match format {
+        ArchiveFormat::Tar => {
+            write_tar(file, targets, report)?;
+        }
+        ArchiveFormat::TarGz => {
+            write_tar(
+                GzEncoder::new(file, Compression::default()),
+                targets,
+                report,
+            )?
+            .finish()?;
+        }
+        ArchiveFormat::TarZst => {
+            write_tar(zstd::Encoder::new(file, 0)?, targets, report)?.finish()?;
+        }
+        ArchiveFormat::Zip => {
+            let mut zip = ZipWriter::new(file);
+            for entry in targets {
+                let result = add_to_zip(&mut zip, &entry.path, Path::new(&entry.name()));
+                record(report, entry, result);
+            }
+            zip.finish()?;
+        }
+    }
+    Ok(())
+}
+
+fn write_tar<W: Write>(
+    writer: W,
+    targets: &[FileEntry],
+    report: &mut BatchReport,
+) -> io::Result<W> {
+    let mut builder = tar::Builder::new(writer);
+    builder.follow_symlinks(false);
+
+    for entry in targets {
+        let result = if entry.is_dir() {
+            builder.append_dir_all(entry.name(), &entry.path)
+        } else {
+            builder.append_path_with_name(&entry.path, entry.name())
+        };
+        record(report, entry, result);
+    }
+
+    builder.into_inner()
+}
+
+fn add_to_zip(zip: &mut ZipWriter<fs::File>, path: &Path, name: &Path) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(path)?;
+    let options = SimpleFileOptions::default()
+        .unix_permissions(metadata.mode() & 0o7777)
+        .large_file(metadata.len() >= u32::MAX.into());
+    let name_text = name.to_string_lossy();
+
+    if metadata.is_symlink() {
+        zip.add_symlink(name_text, fs::read_link(path)?.to_string_lossy(), options)?;
+    } else if metadata.is_dir() {
+        zip.add_directory(name_text, options)?;
+        for child in fs::read_dir(path)? {
+            let child = child?;
+            add_to_zip(zip, &child.path(), &name.join(child.file_name()))?;
+        }
+    } else {
+        zip.start_file(name_text, options)?;
+        io::copy(&mut fs::File::open(path)?, zip)?;
+    }
+    Ok(())
+}
+
+fn record(report: &mut BatchReport, entry: &FileEntry, result: io::Result<()>) {
+    match result {
+        Ok(()) => report.succeeded += 1,
+        Err(err) => report.failures.push((entry.path.clone(), err.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!(sanitize(Path::new("./a/b")), Some(PathBuf::from("a/b")));
+        assert_eq!(
+            sanitize(Path::new("/etc/passwd")),
+            Some(PathBuf::from("etc/passwd"))
+        );
+        assert_eq!(sanitize(Path::new("a/../../b")), None);
+        assert_eq!(sanitize(Path::new("./")), None);
+    }
+
+    #[test]
+    fn test_detect_format() {
+        let detect = |name: &str| ArchiveFormat::detect(Path::new(name));
+        assert_eq!(detect("a.TAR.GZ"), Some(ArchiveFormat::TarGz));
+        assert_eq!(detect("a.tar.zst"), Some(ArchiveFormat::TarZst));
+        assert_eq!(detect("a.tar"), Some(ArchiveFormat::Tar));
+        assert_eq!(detect("a.zip"), Some(ArchiveFormat::Zip));
+        assert_eq!(detect("a.gz"), None);
+    }
+
+    #[test]
+    fn test_extract_through_symlink() {
+        let dir = std::env::temp_dir().join(format!("rustor-archive-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("victim")).unwrap();
+        fs::write(dir.join("victim/passwd"), "root").unwrap();
+
+        // A link to a directory outside the destination, then a member
+        // below the link.
+        let mut builder = tar::Builder::new(fs::File::create(dir.join("evil.tar")).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        builder
+            .append_link(&mut header, "x", dir.join("victim"))
+            .unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_mode(0o644);
+        builder
+            .append_data(&mut header, "x/passwd", &b"owned"[..])
+            .unwrap();
+        builder.finish().unwrap();
+
+        let archive = Archive::open(&dir.join("evil.tar")).unwrap();
+        assert!(archive.extract_all(&dir.join("dest")).is_err());
+        assert_eq!(fs::read(dir.join("victim/passwd")).unwrap(), b"root");
+
+        let mut builder = tar::Builder::new(fs::File::create(dir.join("suid.tar")).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_mode(0o4755);
+        builder
+            .append_data(&mut header, "suid", &b"owned"[..])
+            .unwrap();
+        builder.finish().unwrap();
+        let archive = Archive::open(&dir.join("suid.tar")).unwrap();
+        archive.extract_all(&dir.join("dest2")).unwrap();
+        let mode = fs::metadata(dir.join("dest2/suid")).unwrap().mode();
+        assert_eq!(mode & 0o7777, 0o755);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_copy_out_file() {
+        let dir = std::env::temp_dir().join(format!("rustor-archive-file-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut builder = tar::Builder::new(fs::File::create(dir.join("a.tar")).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(4);
+        header.set_mode(0o644);
+        builder
+            .append_data(&mut header, "sub/file", &b"tar\n"[..])
+            .unwrap();
+        builder.finish().unwrap();
+
+        let mut zip = ZipWriter::new(fs::File::create(dir.join("a.zip")).unwrap());
+        zip.start_file("sub/file", SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"zip\n").unwrap();
+        zip.finish().unwrap();
+
+        let target = FileEntry::from_metadata(
+            dir.join("a.tar"),
+            &fs::symlink_metadata(dir.join("a.tar")).unwrap(),
+        );
+        let mut report = BatchReport {
+            op: "Archive".to_string(),
+            succeeded: 0,
+            failures: vec![],
+        };
+        let err = create(&dir.join("a.tar"), &[target], &mut report).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+
+        for (name, content) in [("a.tar", b"tar\n"), ("a.zip", b"zip\n")] {
+            let archive = Archive::open(&dir.join(name)).unwrap();
+            let out = dir.join(format!("{name}.out"));
+            archive
+                .copy_out(&dir.join(name).join("sub/file"), &out)
+                .unwrap();
+            assert_eq!(&fs::read(&out).unwrap(), content);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,490 @@
+use std::{
+    env, fmt, fs, io,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+};
+
+use super::archive::{self, Archive, ArchiveFormat};
+use super::listing::{format_size, EntryKind, FileEntry};
+use super::vfs::FileSystem;
+
+#[derive(Debug, Clone)]
+pub enum BatchOp {
+    Delete,
+    Copy(PathBuf),
+    Move(PathBuf),
+    Archive(PathBuf),
+    Extract(PathBuf),
+    Symlink(PathBuf),
+    Hardlink(PathBuf),
+}
+
+/// The argument a batch operation still needs before it can be confirmed.
+#[derive(Debug, Clone, Copy)]
+pub enum BatchPrompt {
+    Copy,
+    Move,
+    Archive,
+    Extract,
+    Symlink,
+    Hardlink,
+}
+
+impl BatchPrompt {
+    pub fn title(&self) -> &'static str {
+        match self {
+            BatchPrompt::Copy => "Copy to directory",
+            BatchPrompt::Move => "Move to directory",
+            BatchPrompt::Archive => "Archive path (.tar, .tar.gz, .tar.zst, .zip)",
+            BatchPrompt::Extract => "Extract to directory",
+            BatchPrompt::Symlink => "Create symlinks in directory (or link path)",
+            BatchPrompt::Hardlink => "Create hardlinks in directory (or link path)",
+        }
+    }
+
+    pub fn into_op(self, input: &str, base: &Path) -> Result<BatchOp, String> {
+        let path = base.join(input.trim());
+        match self {
+            BatchPrompt::Copy => Ok(BatchOp::Copy(path)),
+            BatchPrompt::Move => Ok(BatchOp::Move(path)),
+            BatchPrompt::Archive if ArchiveFormat::detect(&path).is_none() => {
+                Err(format!("Unsupported archive format {input:?}"))
+            }
+            BatchPrompt::Archive => Ok(BatchOp::Archive(path)),
+            BatchPrompt::Extract => Ok(BatchOp::Extract(path)),
+            BatchPrompt::Symlink => Ok(BatchOp::Symlink(path)),
+            BatchPrompt::Hardlink => Ok(BatchOp::Hardlink(path)),
+        }
+    }
+}
+
+impl fmt::Display for BatchOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BatchOp::Delete => write!(f, "Delete"),
+            BatchOp::Copy(dest) => write!(f, "Copy to {}", dest.display()),
+            BatchOp::Move(dest) => write!(f, "Move to {}", dest.display()),
+            BatchOp::Archive(dest) => write!(f, "Archive into {}", dest.display()),
+            BatchOp::Extract(dest) => write!(f, "Extract to {}", dest.display()),
+            BatchOp::Symlink(dest) => write!(f, "Symlink in {}", dest.display()),
+            BatchOp::Hardlink(dest) => write!(f, "Hardlink in {}", dest.display()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BatchReport {
+    pub op: String,
+    pub succeeded: usize,
+    pub failures: Vec<(PathBuf, String)>,
+}
+
+impl BatchReport {
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![format!(
+            "{}: {} succeeded, {} failed",
+            self.op,
+            self.succeeded,
+            self.failures.len()
+        )];
+        lines.extend(
+            self.failures
+                .iter()
+                .map(|(path, err)| format!("{}: {err}", path.display())),
+        );
+        lines
+    }
+}
+
+pub fn summary(op: &BatchOp, targets: &[FileEntry], follow_links: bool) -> String {
+    let dirs = targets.iter().filter(|entry| entry.is_dir()).count();
+    let size: u64 = targets
+        .iter()
+        .filter(|entry| !entry.is_dir())
+        .map(|entry| entry.size)
+        .sum();
+    let links = match op {
+        BatchOp::Delete | BatchOp::Copy(_) if follow_links => " following links",
+        _ => "",
+    };
+    // Deleting through links reaches outside the listing, so name where.
+    let mut link_targets = String::new();
+    if matches!(op, BatchOp::Delete) && follow_links {
+        let resolved: Vec<String> = targets
+            .iter()
+            .filter(|entry| entry.kind == EntryKind::Symlink)
+            .filter_map(|entry| fs::canonicalize(&entry.path).ok())
+            .map(|target| target.display().to_string())
+            .collect();
+        match resolved.len() {
+            0 => {}
+            1..=2 => link_targets = format!(" (and {})", resolved.join(", ")),
+            count => {
+                link_targets = format!(
+                    " (and {}, {} and {} more)",
+                    resolved[0],
+                    resolved[1],
+                    count - 2
+                )
+            }
+        }
+    }
+    format!(
+        "{op}{links}: {} files, {dirs} directories, {}{link_targets}? (Y/N)",
+        targets.len() - dirs,
+        format_size(size)
+    )
+}
+
+/// Runs `op` on `targets`, which live on `vfs`. Deleting, moving and copying
+/// go through `vfs` so they also work from inside archives or fail in the
+/// read-only mode. Local copies and moves run in the transfer queue instead.
+/// With `follow_links`, deleting a symlink also deletes its target, unless
+/// that would take `open_path` with it.
+pub fn run(
+    op: &BatchOp,
+    targets: &[FileEntry],
+    vfs: &dyn FileSystem,
+    follow_links: bool,
+    open_path: &Path,
+) -> BatchReport {
+    let mut report = BatchReport {
+        op: op.to_string(),
+        succeeded: 0,
+        failures: vec![],
+    };
+
+    if vfs.read_only() {
+        for entry in targets {
+            let err = "read-only mode is on".to_string();
+            report.failures.push((entry.path.clone(), err));
+        }
+        return report;
+    }
+
+    if let BatchOp::Archive(dest) = op {
+        match archive::create(dest, targets, &mut report) {
+            Ok(()) => {}
+            Err(err) => report.failures.push((dest.clone(), err.to_string())),
+        }
+        return report;
+    }
+
+    for entry in targets {
+        let result = match op {
+            BatchOp::Delete if follow_links && entry.kind == EntryKind::Symlink => {
+                remove_link_target(entry, open_path)
+            }
+            BatchOp::Delete => vfs.remove(&entry.path),
+            BatchOp::Copy(dest) if vfs.archive().is_none() => {
+                vfs.copy(&entry.path, &dest.join(file_name(entry)))
+            }
+            BatchOp::Copy(dest) => vfs.copy_out(&entry.path, &dest.join(file_name(entry))),
+            BatchOp::Move(dest) => vfs.rename(&entry.path, &dest.join(file_name(entry))),
+            BatchOp::Archive(_) => unreachable!(),
+            BatchOp::Extract(dest) if vfs.archive().is_some() => {
+                vfs.copy_out(&entry.path, &dest.join(file_name(entry)))
+            }
+            BatchOp::Extract(dest) => {
+                Archive::open(&entry.path).and_then(|archive| archive.extract_all(dest))
+            }
+            BatchOp::Symlink(dest) => {
+                std::os::unix::fs::symlink(&entry.path, link_path(dest, entry))
+            }
+            BatchOp::Hardlink(dest) => fs::hard_link(&entry.path, link_path(dest, entry)),
+        };
+
+        match result {
+            Ok(()) => report.succeeded += 1,
+            Err(err) => report.failures.push((entry.path.clone(), err.to_string())),
+        }
+    }
+
+    report
+}
+
+fn file_name(entry: &FileEntry) -> PathBuf {
+    PathBuf::from(entry.name())
+}
+
+/// Links are created inside `dest` when it is a directory, otherwise `dest`
+/// is the path of the link itself.
+fn link_path(dest: &Path, entry: &FileEntry) -> PathBuf {
+    if dest.is_dir() {
+        dest.join(file_name(entry))
+    } else {
+        dest.to_path_buf()
+    }
+}
+
+/// Why deleting the link target `target` is refused: the root, the home
+/// directory, mount points and the directories holding `open_path` are
+/// never deleted through a link.
+fn protected_target(target: &Path, open_path: &Path) -> Option<&'static str> {
+    let Some(parent) = target.parent() else {
+        return Some("it is /");
+    };
+    // `target` is canonical, and HOME may go through a symlink.
+    if env::var_os("HOME").is_some_and(|home| resolves_to(Path::new(&home), target)) {
+        return Some("it is the home directory");
+    }
+    let open_path = fs::canonicalize(open_path).unwrap_or_else(|_| open_path.to_path_buf());
+    if open_path.starts_with(target) {
+        return Some("it contains the open directory");
+    }
+    let device = |path: &Path| fs::metadata(path).map(|metadata| metadata.dev()).ok();
+    if device(target) != device(parent) {
+        return Some("it is a mount point");
+    }
+    None
+}
+
+fn resolves_to(path: &Path, target: &Path) -> bool {
+    fs::canonicalize(path).is_ok_and(|path| path == target)
+}
+
+/// Deletes what a symlink points to, then the link itself.
+fn remove_link_target(entry: &FileEntry, open_path: &Path) -> io::Result<()> {
+    let target = fs::canonicalize(&entry.path)?;
+    if let Some(reason) = protected_target(&target, open_path) {
+        return Err(io::Error::other(format!(
+            "refusing to delete {} since {reason}",
+            target.display()
+        )));
+    }
+    if target.is_dir() {
+        fs::remove_dir_all(&target)?;
+    } else {
+        fs::remove_file(&target)?;
+    }
+    fs::remove_file(&entry.path)
+}
+
+/// Copies `src` to `dest`. Symlinks are recreated as links, or with
+/// `follow_links` replaced by copies of what they point to.
+pub fn copy_recursive(src: &Path, dest: &Path, follow_links: bool) -> io::Result<()> {
+    check_outside(src, dest, follow_links)?;
+    copy_tree(src, dest, follow_links, &mut vec![])
+}
+
+/// Fails if `src` is a directory and `dest` lies inside it. A directory
+/// copied into itself would grow until the path is too long.
+pub fn check_outside(src: &Path, dest: &Path, follow_links: bool) -> io::Result<()> {
+    let metadata = if follow_links {
+        fs::metadata(src)?
+    } else {
+        fs::symlink_metadata(src)?
+    };
+    if metadata.is_dir() && inside(dest, src)? {
+        return Err(io::Error::other(format!(
+            "{} is inside {}",
+            dest.display(),
+            src.display()
+        )));
+    }
+    Ok(())
+}
+
+/// Whether `dest`, which need not exist yet, resolves to a path inside `dir`.
+fn inside(dest: &Path, dir: &Path) -> io::Result<bool> {
+    let dir = fs::canonicalize(dir)?;
+    let dest = match (dest.parent(), dest.file_name()) {
+        (Some(parent), Some(name)) => fs::canonicalize(parent)?.join(name),
+        _ => fs::canonicalize(dest)?,
+    };
+    Ok(dest.starts_with(dir))
+}
+
+fn copy_tree(
+    src: &Path,
+    dest: &Path,
+    follow_links: bool,
+    ancestors: &mut Vec<(u64, u64)>,
+) -> io::Result<()> {
+    if fs::symlink_metadata(dest).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", dest.display()),
+        ));
+    }
+
+    let metadata = if follow_links {
+        fs::metadata(src)?
+    } else {
+        fs::symlink_metadata(src)?
+    };
+    if metadata.is_dir() {
+        // Followed links can lead back into a directory being copied.
+        let id = (metadata.dev(), metadata.ino());
+        if ancestors.contains(&id) {
+            return Err(io::Error::other(format!(
+                "{} links to one of its parents",
+                src.display()
+            )));
+        }
+        ancestors.push(id);
+        fs::create_dir(dest)?;
+        for child in fs::read_dir(src)? {
+            let child = child?;
+            copy_tree(
+                &child.path(),
+                &dest.join(child.file_name()),
+                follow_links,
+                ancestors,
+            )?;
+        }
+        ancestors.pop();
+        fs::set_permissions(dest, metadata.permissions())
+    } else if metadata.is_symlink() {
+        std::os::unix::fs::symlink(fs::read_link(src)?, dest)
+    } else {
+        fs::copy(src, dest).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::vfs::{LocalFs, MemoryFs, ReadOnly};
+    use super::*;
+
+    #[test]
+    fn test_delete_link_target() {
+        let dir = env::temp_dir().join(format!("rustor-batch-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("open/sub")).unwrap();
+        fs::create_dir(dir.join("other")).unwrap();
+        std::os::unix::fs::symlink(dir.join("open"), dir.join("open/sub/up")).unwrap();
+        std::os::unix::fs::symlink(dir.join("other"), dir.join("open/sub/other")).unwrap();
+        std::os::unix::fs::symlink("/", dir.join("open/sub/root")).unwrap();
+
+        let entries: Vec<FileEntry> = ["up", "other", "root"]
+            .iter()
+            .map(|name| {
+                let path = dir.join("open/sub").join(name);
+                FileEntry::from_metadata(path.clone(), &fs::symlink_metadata(&path).unwrap())
+            })
+            .collect();
+        let summary = summary(&BatchOp::Delete, &entries[1..2], true);
+        assert!(summary.contains(&dir.join("other").display().to_string()));
+
+        let open_path = dir.join("open/sub");
+        let report = run(&BatchOp::Delete, &entries, &LocalFs, true, &open_path);
+        assert_eq!(report.succeeded, 1);
+        let failed: Vec<&PathBuf> = report.failures.iter().map(|(path, _)| path).collect();
+        assert_eq!(failed, [&entries[0].path, &entries[2].path]);
+        assert!(dir.join("open/sub").exists());
+        assert!(!dir.join("other").exists());
+
+        // A home directory reached through a link is still recognised.
+        fs::create_dir(dir.join("home")).unwrap();
+        std::os::unix::fs::symlink(dir.join("home"), dir.join("home-link")).unwrap();
+        let home = fs::canonicalize(dir.join("home")).unwrap();
+        assert!(resolves_to(&dir.join("home-link"), &home));
+        assert!(!resolves_to(&dir.join("open"), &home));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_copy_into_itself() {
+        let dir = env::temp_dir().join(format!("rustor-batch-copy-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("a/inner")).unwrap();
+        fs::create_dir(dir.join("b")).unwrap();
+        fs::write(dir.join("b/file"), "data").unwrap();
+
+        let entries: Vec<FileEntry> = ["a", "b"]
+            .iter()
+            .map(|name| {
+                let path = dir.join(name);
+                FileEntry::from_metadata(path.clone(), &fs::symlink_metadata(&path).unwrap())
+            })
+            .collect();
+        let dest = dir.join("b/../a/inner");
+        for follow_links in [false, true] {
+            let _ = fs::remove_dir_all(dir.join("a/inner/b"));
+            let op = BatchOp::Copy(dest.clone());
+            let report = run(&op, &entries, &LocalFs, follow_links, &dir);
+            assert_eq!(report.succeeded, 1);
+            assert_eq!(report.failures.len(), 1);
+            assert_eq!(report.failures[0].0, entries[0].path);
+            assert!(report.failures[0].1.contains("is inside"));
+            assert!(!dir.join("a/inner/a").exists());
+            assert_eq!(fs::read(dir.join("a/inner/b/file")).unwrap(), b"data");
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_read_only() {
+        let dir = env::temp_dir().join(format!("rustor-batch-ro-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("dest")).unwrap();
+        fs::write(dir.join("file"), "data").unwrap();
+        let mut builder = tar::Builder::new(fs::File::create(dir.join("a.tar")).unwrap());
+        builder
+            .append_path_with_name(dir.join("file"), "file")
+            .unwrap();
+        builder.finish().unwrap();
+
+        let entries: Vec<FileEntry> = ["file", "a.tar"]
+            .iter()
+            .map(|name| {
+                let path = dir.join(name);
+                FileEntry::from_metadata(path.clone(), &fs::symlink_metadata(&path).unwrap())
+            })
+            .collect();
+        let dest = dir.join("dest");
+        let ops = [
+            BatchOp::Delete,
+            BatchOp::Copy(dest.clone()),
+            BatchOp::Move(dest.clone()),
+            BatchOp::Archive(dest.join("new.tar")),
+            BatchOp::Extract(dest.clone()),
+            BatchOp::Symlink(dest.clone()),
+            BatchOp::Hardlink(dest.clone()),
+        ];
+        let vfs = ReadOnly(Box::new(LocalFs));
+        for op in &ops {
+            for follow_links in [false, true] {
+                let report = run(op, &entries, &vfs, follow_links, &dir);
+                assert_eq!(report.succeeded, 0, "{op}");
+                assert_eq!(report.failures.len(), entries.len(), "{op}");
+            }
+        }
+        assert_eq!(fs::read_dir(&dest).unwrap().count(), 0);
+        assert_eq!(fs::read(dir.join("file")).unwrap(), b"data");
+        assert!(dir.join("a.tar").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_memory_fs() {
+        let vfs = MemoryFs::new()
+            .with_file("/src/a.txt", b"a")
+            .with_file("/src/dir/b.txt", b"b")
+            .with_file("/dest/kept", b"");
+        let entries: Vec<FileEntry> = ["/src/a.txt", "/src/dir"]
+            .iter()
+            .map(|path| vfs.metadata(Path::new(path)).unwrap())
+            .collect();
+        let dest = PathBuf::from("/dest");
+
+        let report = run(&BatchOp::Copy(dest.clone()), &entries, &vfs, false, &dest);
+        assert_eq!((report.succeeded, report.failures.len()), (2, 0));
+        assert_eq!(vfs.read(Path::new("/dest/dir/b.txt"), 8).unwrap(), b"b");
+
+        // Every item that fails is reported on its own.
+        let report = run(&BatchOp::Move(dest.clone()), &entries, &vfs, false, &dest);
+        assert_eq!(report.succeeded, 0);
+        let failed: Vec<&PathBuf> = report.failures.iter().map(|(path, _)| path).collect();
+        assert_eq!(failed, [&entries[0].path, &entries[1].path]);
+
+        let report = run(&BatchOp::Delete, &entries[..1], &vfs, false, &dest);
+        assert_eq!((report.succeeded, report.failures.len()), (1, 0));
+        let report = run(&BatchOp::Delete, &entries, &vfs, false, &dest);
+        assert_eq!((report.succeeded, report.failures.len()), (1, 1));
+        assert_eq!(report.failures[0].0, entries[0].path);
+        assert_eq!(vfs.read_dir(Path::new("/src")).unwrap().len(), 0);
+        assert_eq!(vfs.read_dir(Path::new("/dest")).unwrap().len(), 3);
+    }
+}
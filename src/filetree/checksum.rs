@@ -0,0 +1,631 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use md5::Md5;
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style},
+    widgets::{Block, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+use sha1::Sha1;
+use sha2::{digest::DynDigest, Sha256, Sha512};
+
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, TryRecvError},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::listing::format_size;
+
+const CHUNK_LENGTH: usize = 1024 * 1024;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    Sha512,
+    Blake3,
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 5] = [
+        Algorithm::Md5,
+        Algorithm::Sha1,
+        Algorithm::Sha256,
+        Algorithm::Sha512,
+        Algorithm::Blake3,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Algorithm::Md5 => "MD5",
+            Algorithm::Sha1 => "SHA1",
+            Algorithm::Sha256 => "SHA256",
+            Algorithm::Sha512 => "SHA512",
+            Algorithm::Blake3 => "BLAKE3",
+        }
+    }
+
+    /// The conventional name of a checksum file, like `SHA256SUMS`.
+    pub fn sums_file(&self) -> &'static str {
+        match self {
+            Algorithm::Md5 => "MD5SUMS",
+            Algorithm::Sha1 => "SHA1SUMS",
+            Algorithm::Sha256 => "SHA256SUMS",
+            Algorithm::Sha512 => "SHA512SUMS",
+            Algorithm::Blake3 => "B3SUMS",
+        }
+    }
+
+    pub fn next(&self) -> Algorithm {
+        let index = Algorithm::ALL
+            .iter()
+            .position(|algorithm| algorithm == self);
+        Algorithm::ALL[index.map_or(0, |index| (index + 1) % Algorithm::ALL.len())]
+    }
+
+    fn from_tag(tag: &str) -> Option<Algorithm> {
+        let tag = tag.to_ascii_uppercase().replace('-', "");
+        Algorithm::ALL.into_iter().find(|algorithm| {
+            algorithm.name() == tag || tag == "B3" && *algorithm == Algorithm::Blake3
+        })
+    }
+
+    /// Guesses the algorithm of a checksum file from its name, falling back
+    /// to the length of a checksum. BLAKE3 and SHA-256 sums have the same
+    /// length, so BLAKE3 files need `b3` or `blake3` in their name.
+    fn detect(file_name: &str, hex_length: usize) -> Option<Algorithm> {
+        let name = file_name.to_ascii_lowercase();
+        let by_name = [
+            ("blake3", Algorithm::Blake3),
+            ("b3", Algorithm::Blake3),
+            ("sha512", Algorithm::Sha512),
+            ("sha256", Algorithm::Sha256),
+            ("sha1", Algorithm::Sha1),
+            ("md5", Algorithm::Md5),
+        ];
+        if let Some((_, algorithm)) = by_name.iter().find(|(tag, _)| name.contains(tag)) {
+            return Some(*algorithm);
+        }
+        match hex_length {
+            32 => Some(Algorithm::Md5),
+            40 => Some(Algorithm::Sha1),
+            64 => Some(Algorithm::Sha256),
+            128 => Some(Algorithm::Sha512),
+            _ => None,
+        }
+    }
+
+    fn hasher(&self) -> Hasher {
+        match self {
+            Algorithm::Md5 => Hasher::Digest(Box::new(Md5::default())),
+            Algorithm::Sha1 => Hasher::Digest(Box::new(Sha1::default())),
+            Algorithm::Sha256 => Hasher::Digest(Box::new(Sha256::default())),
+            Algorithm::Sha512 => Hasher::Digest(Box::new(Sha512::default())),
+            Algorithm::Blake3 => Hasher::Blake3(Box::default()),
+        }
+    }
+}
+
+enum Hasher {
+    Digest(Box<dyn DynDigest + Send>),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Digest(digest) => digest.update(data),
+            Hasher::Blake3(hasher) => {
+                hasher.update(data);
+            }
+        }
+    }
+
+    fn finalize(self) -> String {
+        let bytes = match self {
+            Hasher::Digest(digest) => digest.finalize().to_vec(),
+            Hasher::Blake3(hasher) => hasher.finalize().as_bytes().to_vec(),
+        };
+        bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+}
+
+/// Parses a checksum file in the GNU (`<hex>  <name>`, `<hex> *<name>`) or
+/// BSD (`SHA256 (<name>) = <hex>`) format into its algorithm and entries.
+pub fn parse_sums(
+    file_name: &str,
+    content: &str,
+) -> Result<(Algorithm, Vec<(String, String)>), String> {
+    let mut tagged = None;
+    let mut entries = vec![];
+
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let bsd = line.split_once(" (").and_then(|(tag, rest)| {
+            let (name, hex) = rest.rsplit_once(") = ")?;
+            Some((Algorithm::from_tag(tag)?, name, hex))
+        });
+        let (name, hex) = match bsd {
+            Some((algorithm, name, hex)) => {
+                tagged = Some(algorithm);
+                (name, hex)
+            }
+            None => {
+                let Some((hex, name)) = line.split_once(' ') else {
+                    return Err(format!(
+                        "line {}: expected a checksum and a name",
+                        number + 1
+                    ));
+                };
+                (name.strip_prefix(['*', ' ']).unwrap_or(name), hex)
+            }
+        };
+
+        if hex.is_empty() || !hex.chars().all(|c| c.is_ascii_hexdigit()) || name.is_empty() {
+            return Err(format!(
+                "line {}: expected a checksum and a name",
+                number + 1
+            ));
+        }
+        entries.push((hex.to_ascii_lowercase(), name.to_string()));
+    }
+
+    let Some((hex, _)) = entries.first() else {
+        return Err("no checksums found".to_string());
+    };
+    let algorithm = tagged
+        .or_else(|| Algorithm::detect(file_name, hex.len()))
+        .ok_or_else(|| format!("unknown checksum length {}", hex.len()))?;
+    Ok((algorithm, entries))
+}
+
+#[derive(Debug)]
+enum HashEvent {
+    Progress(usize, u64),
+    Finished(usize, Result<String, String>),
+}
+
+/// Hashes the files in order on a worker thread, reporting the bytes read
+/// of the current file so large files show progress.
+#[derive(Debug)]
+struct HashJob {
+    receiver: Receiver<HashEvent>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl HashJob {
+    fn start(algorithm: Algorithm, files: Vec<PathBuf>) -> HashJob {
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let worker_cancel = cancel.clone();
+
+        thread::spawn(move || {
+            for (index, path) in files.iter().enumerate() {
+                let result = hash_file(algorithm, path, &worker_cancel, |done| {
+                    let _ = sender.send(HashEvent::Progress(index, done));
+                });
+                if worker_cancel.load(Ordering::Relaxed) {
+                    return;
+                }
+                let result = result.map_err(|err| err.to_string());
+                if sender.send(HashEvent::Finished(index, result)).is_err() {
+                    return;
+                }
+            }
+        });
+
+        HashJob { receiver, cancel }
+    }
+
+    fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+fn hash_file(
+    algorithm: Algorithm,
+    path: &Path,
+    cancel: &AtomicBool,
+    progress: impl Fn(u64),
+) -> io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = algorithm.hasher();
+    let mut buffer = vec![0; CHUNK_LENGTH];
+    let mut done = 0;
+    let mut reported = Instant::now();
+
+    loop {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled"));
+        }
+        let read = match file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        hasher.update(&buffer[..read]);
+        done += read as u64;
+        if reported.elapsed() >= PROGRESS_INTERVAL {
+            progress(done);
+            reported = Instant::now();
+        }
+    }
+    Ok(hasher.finalize())
+}
+
+#[derive(Debug, Clone)]
+pub struct ChecksumEntry {
+    pub path: PathBuf,
+    /// The path relative to the directory checksums are listed for.
+    pub name: String,
+    pub size: u64,
+    pub expected: Option<String>,
+    pub actual: Option<Result<String, String>>,
+}
+
+impl ChecksumEntry {
+    fn passed(&self) -> Option<bool> {
+        match (&self.expected, &self.actual) {
+            (Some(expected), Some(Ok(actual))) => Some(expected == actual),
+            (Some(_), Some(Err(_))) => Some(false),
+            _ => None,
+        }
+    }
+}
+
+/// Checksums of a set of files, either computed for the selection or
+/// checked against a checksum file.
+#[derive(Debug)]
+pub struct ChecksumView {
+    pub algorithm: Algorithm,
+    /// The directory entry names are relative to.
+    pub base: PathBuf,
+    /// The checksum file being verified, if any.
+    pub sums_file: Option<PathBuf>,
+    pub entries: Vec<ChecksumEntry>,
+    pub select_state: ListState,
+    pub status: Option<String>,
+    job: Option<HashJob>,
+    current: Option<(usize, u64)>,
+}
+
+impl ChecksumView {
+    /// Computes checksums of `files`, descending into directories.
+    pub fn compute(algorithm: Algorithm, base: &Path, files: &[PathBuf]) -> ChecksumView {
+        let mut paths = vec![];
+        for file in files {
+            collect_files(file, &mut paths);
+        }
+        let entries = paths
+            .into_iter()
+            .map(|path| ChecksumEntry {
+                name: relative_name(base, &path),
+                size: fs::metadata(&path).map_or(0, |metadata| metadata.len()),
+                path,
+                expected: None,
+                actual: None,
+            })
+            .collect();
+        ChecksumView::start(algorithm, base, None, entries)
+    }
+
+    /// Verifies the files listed in `sums_file`, relative to its directory.
+    pub fn verify(sums_file: &Path) -> Result<ChecksumView, String> {
+        let content = fs::read_to_string(sums_file).map_err(|err| err.to_string())?;
+        let file_name = sums_file.file_name().unwrap_or_default().to_string_lossy();
+        let (algorithm, listed) = parse_sums(&file_name, &content)?;
+
+        let base = sums_file.parent().unwrap_or(Path::new("/"));
+        let entries = listed
+            .into_iter()
+            .map(|(hex, name)| {
+                let path = base.join(&name);
+                ChecksumEntry {
+                    size: fs::metadata(&path).map_or(0, |metadata| metadata.len()),
+                    path,
+                    name,
+                    expected: Some(hex),
+                    actual: None,
+                }
+            })
+            .collect();
+        Ok(ChecksumView::start(
+            algorithm,
+            base,
+            Some(sums_file.to_path_buf()),
+            entries,
+        ))
+    }
+
+    fn start(
+        algorithm: Algorithm,
+        base: &Path,
+        sums_file: Option<PathBuf>,
+        entries: Vec<ChecksumEntry>,
+    ) -> ChecksumView {
+        let files = entries.iter().map(|entry| entry.path.clone()).collect();
+        ChecksumView {
+            algorithm,
+            base: base.to_path_buf(),
+            sums_file,
+            entries,
+            select_state: ListState::default().with_selected(Some(0)),
+            status: None,
+            job: Some(HashJob::start(algorithm, files)),
+            current: None,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.job.is_some()
+    }
+
+    pub fn cancel(&mut self) {
+        if let Some(job) = self.job.take() {
+            job.cancel();
+            self.current = None;
+            self.status = Some("Cancelled".to_string());
+        }
+    }
+
+    /// Recomputes every checksum with the next algorithm.
+    pub fn next_algorithm(&mut self) {
+        if self.sums_file.is_some() {
+            return;
+        }
+        self.cancel();
+        self.algorithm = self.algorithm.next();
+        self.entries
+            .iter_mut()
+            .for_each(|entry| entry.actual = None);
+        let files = self
+            .entries
+            .iter()
+            .map(|entry| entry.path.clone())
+            .collect();
+        self.job = Some(HashJob::start(self.algorithm, files));
+        self.status = None;
+    }
+
+    pub fn poll(&mut self) {
+        let Some(job) = &self.job else {
+            return;
+        };
+        loop {
+            match job.receiver.try_recv() {
+                Ok(HashEvent::Progress(index, done)) => self.current = Some((index, done)),
+                Ok(HashEvent::Finished(index, result)) => {
+                    self.current = None;
+                    self.entries[index].actual = Some(result);
+                }
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => break,
+            }
+        }
+        self.job = None;
+        self.status = Some(self.summary());
+    }
+
+    fn summary(&self) -> String {
+        let failed = self
+            .entries
+            .iter()
+            .filter(|entry| matches!(entry.actual, Some(Err(_))))
+            .count();
+        match &self.sums_file {
+            Some(_) => {
+                let passed = self
+                    .entries
+                    .iter()
+                    .filter(|entry| entry.passed() == Some(true))
+                    .count();
+                format!("{passed} passed, {} failed", self.entries.len() - passed)
+            }
+            None if failed > 0 => format!("{failed} files could not be read"),
+            None => format!("{} files hashed", self.entries.len()),
+        }
+    }
+
+    /// The `<hex>  <name>` line of an entry, as `sha256sum` prints it.
+    fn line(&self, entry: &ChecksumEntry) -> Option<String> {
+        match &entry.actual {
+            Some(Ok(hex)) => Some(format!("{hex}  {}", entry.name)),
+            _ => None,
+        }
+    }
+
+    pub fn copy_highlighted(&mut self) {
+        let index = self.select_state.selected().unwrap_or_default();
+        let Some(line) = self.entries.get(index).and_then(|entry| self.line(entry)) else {
+            return;
+        };
+        self.status = Some(match copy_to_clipboard(&line) {
+            Ok(()) => format!("Copied checksum of {}", self.entries[index].name),
+            Err(err) => format!("Could not copy: {err}"),
+        });
+    }
+
+    pub fn copy_all(&mut self) {
+        let lines: Vec<String> = self
+            .entries
+            .iter()
+            .filter_map(|entry| self.line(entry))
+            .collect();
+        self.status = Some(match copy_to_clipboard(&(lines.join("\n") + "\n")) {
+            Ok(()) => format!("Copied {} checksums", lines.len()),
+            Err(err) => format!("Could not copy: {err}"),
+        });
+    }
+
+    /// Writes the computed checksums to a checksum file like `SHA256SUMS` in
+    /// the base directory, replacing an existing one.
+    pub fn write_sums(&mut self) -> io::Result<PathBuf> {
+        if self.sums_file.is_some() {
+            return Err(io::Error::other("checksums are being verified"));
+        }
+        if self.is_running() {
+            return Err(io::Error::other("checksums are still being computed"));
+        }
+        let path = self.base.join(self.algorithm.sums_file());
+        let content: String = self
+            .entries
+            .iter()
+            .filter(|entry| entry.path != path)
+            .filter_map(|entry| self.line(entry))
+            .map(|line| line + "\n")
+            .collect();
+        fs::write(&path, content)?;
+        Ok(path)
+    }
+}
+
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return;
+    };
+    if metadata.is_file() {
+        files.push(path.to_path_buf());
+    } else if metadata.is_dir() {
+        let Ok(children) = fs::read_dir(path) else {
+            return;
+        };
+        let mut children: Vec<PathBuf> = children.flatten().map(|child| child.path()).collect();
+        children.sort();
+        for child in children {
+            collect_files(&child, files);
+        }
+    }
+}
+
+fn relative_name(base: &Path, path: &Path) -> String {
+    path.strip_prefix(base)
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
+
+/// Puts `text` on the clipboard of the terminal with an OSC 52 escape
+/// sequence, which also works over SSH.
+fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", STANDARD.encode(text))?;
+    stdout.flush()
+}
+
+pub fn render(view: &mut ChecksumView, frame: &mut Frame, area: Rect, style: Style) {
+    let [header_area, list_area] =
+        Layout::vertical([Constraint::Length(3), Constraint::Min(1)]).areas(area);
+
+    let done = view
+        .entries
+        .iter()
+        .filter(|entry| entry.actual.is_some())
+        .count();
+    let status = match (&view.current, &view.status) {
+        (Some((index, bytes)), _) => {
+            let entry = &view.entries[*index];
+            format!(
+                "{} of {} files, {}: {} of {}",
+                done,
+                view.entries.len(),
+                entry.name,
+                format_size(*bytes),
+                format_size(entry.size)
+            )
+        }
+        (None, Some(status)) => status.clone(),
+        (None, None) if view.is_running() => format!("{done} of {} files", view.entries.len()),
+        (None, None) => String::new(),
+    };
+    let keys = match view.sums_file {
+        Some(_) => "c - copy, Esc - close",
+        None => "a - algorithm, c - copy, C - copy all, w - write sums file, Esc - close",
+    };
+    let title = match &view.sums_file {
+        Some(sums_file) => format!("Verify {} ({})", sums_file.display(), view.algorithm.name()),
+        None => format!(
+            "{} checksums in {}",
+            view.algorithm.name(),
+            view.base.display()
+        ),
+    };
+    let header = Paragraph::new(format!("{status}\n{keys}"))
+        .style(style)
+        .block(Block::bordered().title(title));
+    frame.render_widget(header, header_area);
+
+    let items: Vec<ListItem> = view
+        .entries
+        .iter()
+        .map(|entry| {
+            let (result, color) = match (&entry.actual, entry.passed()) {
+                (Some(Err(err)), _) => (format!("ERROR {err}"), Color::Red),
+                (Some(Ok(_)), Some(true)) => ("OK".to_string(), Color::Green),
+                (Some(Ok(_)), Some(false)) => ("FAILED".to_string(), Color::Red),
+                (Some(Ok(hex)), None) => (hex.clone(), Color::Reset),
+                (None, _) => ("...".to_string(), Color::DarkGray),
+            };
+            ListItem::new(format!("{result}  {}", entry.name)).style(Style::default().fg(color))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .style(style)
+        .highlight_style(Style::default().bg(Color::Green).fg(Color::White))
+        .block(Block::bordered());
+    frame.render_stateful_widget(list, list_area, &mut view.select_state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sums() {
+        let content = "d41d8cd98f00b204e9800998ecf8427e  empty.txt\n\
+                       D41D8CD98F00B204E9800998ECF8427E *dir/b in.bin\n";
+        let (algorithm, entries) = parse_sums("checksums", content).unwrap();
+        assert_eq!(algorithm, Algorithm::Md5);
+        assert_eq!(entries[1].0, "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(entries[1].1, "dir/b in.bin");
+
+        let content = "SHA1 (a (1).txt) = da39a3ee5e6b4b0d3255bfef95601890afd80709\n";
+        let (algorithm, entries) = parse_sums("CHECKSUM", content).unwrap();
+        assert_eq!(algorithm, Algorithm::Sha1);
+        assert_eq!(entries[0].1, "a (1).txt");
+
+        let line = format!("{}  a\n", "0".repeat(64));
+        assert_eq!(parse_sums("B3SUMS", &line).unwrap().0, Algorithm::Blake3);
+        assert_eq!(parse_sums("SUMS", &line).unwrap().0, Algorithm::Sha256);
+        assert!(parse_sums("SUMS", "not a checksum\n").is_err());
+    }
+
+    #[test]
+    fn test_hash_file() {
+        let path = std::env::temp_dir().join(format!("rustor-checksum-{}", std::process::id()));
+        fs::write(&path, "abc").unwrap();
+        let hash =
+            |algorithm| hash_file(algorithm, &path, &AtomicBool::new(false), |_| {}).unwrap();
+        assert_eq!(hash(Algorithm::Md5), "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(
+            hash(Algorithm::Sha256),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,872 @@
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+use similar::{ChangeTag, TextDiff};
+
+use std::{
+    collections::BTreeSet,
+    fs,
+    io::{self, Read},
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, TryRecvError},
+        Arc,
+    },
+    thread,
+};
+
+use super::batch::copy_recursive;
+
+const CONTEXT: usize = 3;
+const BINARY_SNIFF_LEN: u64 = 8192;
+/// Larger files are only compared, not diffed line by line.
+const MAX_DIFF_SIZE: u64 = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone)]
+struct Change {
+    tag: ChangeTag,
+    old: Option<usize>,
+    new: Option<usize>,
+    /// The text of the line, split into parts that are emphasized when they
+    /// differ from the paired line on the other side.
+    segments: Vec<(bool, String)>,
+}
+
+/// A line diff of two files, grouped into hunks with some lines of context.
+#[derive(Debug)]
+pub struct FileDiff {
+    pub left: PathBuf,
+    pub right: PathBuf,
+    hunks: Vec<(String, Vec<Change>)>,
+    /// Shown instead of the hunks for identical or binary files.
+    message: Option<String>,
+    pub split: bool,
+    pub scroll: usize,
+}
+
+impl FileDiff {
+    /// Diffs two text files. Files with a NUL byte near the start count as
+    /// binary and, like very large files, are only compared; text that isn't
+    /// UTF-8 is diffed lossily.
+    pub fn new(left: &Path, right: &Path) -> io::Result<FileDiff> {
+        let (left_meta, right_meta) = (fs::metadata(left)?, fs::metadata(right)?);
+        // Reading a FIFO or a device could block forever.
+        for (path, metadata) in [(left, &left_meta), (right, &right_meta)] {
+            if !metadata.is_file() {
+                return Err(io::Error::other(format!(
+                    "{} is not a regular file",
+                    path.display()
+                )));
+            }
+        }
+        let (left_size, right_size) = (left_meta.len(), right_meta.len());
+        let binary = is_binary(left)? || is_binary(right)?;
+        if binary || left_size.max(right_size) > MAX_DIFF_SIZE {
+            let same = left_size == right_size && same_content(left, right)?;
+            let message = match (same, binary) {
+                (true, _) => "Files are identical",
+                (false, true) => "Binary files differ",
+                (false, false) => "Files differ but are too large to diff",
+            };
+            return Ok(FileDiff {
+                message: Some(message.to_string()),
+                ..FileDiff::from_text(left, right, "", "")
+            });
+        }
+
+        let old = fs::read(left)?;
+        let new = fs::read(right)?;
+        Ok(FileDiff::from_text(
+            left,
+            right,
+            &String::from_utf8_lossy(&old),
+            &String::from_utf8_lossy(&new),
+        ))
+    }
+
+    fn from_text(left: &Path, right: &Path, old: &str, new: &str) -> FileDiff {
+        let diff = TextDiff::from_lines(old, new);
+        let hunks: Vec<(String, Vec<Change>)> = diff
+            .grouped_ops(CONTEXT)
+            .iter()
+            .map(|group| {
+                let (Some(first), Some(last)) = (group.first(), group.last()) else {
+                    return (String::new(), vec![]);
+                };
+                let header = format!(
+                    "@@ -{},{} +{},{} @@",
+                    first.old_range().start + 1,
+                    last.old_range().end - first.old_range().start,
+                    first.new_range().start + 1,
+                    last.new_range().end - first.new_range().start
+                );
+                let changes = group
+                    .iter()
+                    .flat_map(|op| diff.iter_inline_changes(op))
+                    .map(|change| Change {
+                        tag: change.tag(),
+                        old: change.old_index(),
+                        new: change.new_index(),
+                        segments: change
+                            .iter_strings_lossy()
+                            .map(|(emphasized, text)| {
+                                let text = text.trim_end_matches(['\n', '\r']);
+                                (emphasized, text.replace('\t', "    "))
+                            })
+                            .collect(),
+                    })
+                    .collect();
+                (header, changes)
+            })
+            .collect();
+
+        FileDiff {
+            left: left.to_path_buf(),
+            right: right.to_path_buf(),
+            message: hunks.is_empty().then(|| "Files are identical".to_string()),
+            hunks,
+            split: false,
+            scroll: 0,
+        }
+    }
+
+    /// The rows of the unified view and the row each hunk starts at.
+    fn unified_rows(&self) -> (Vec<Line<'static>>, Vec<usize>) {
+        let mut rows = vec![];
+        let mut starts = vec![];
+        for (header, changes) in &self.hunks {
+            starts.push(rows.len());
+            rows.push(Line::from(header.clone()).style(Style::default().fg(Color::Cyan)));
+            for change in changes {
+                let number = |index: Option<usize>| match index {
+                    Some(index) => format!("{:>5}", index + 1),
+                    None => "     ".to_string(),
+                };
+                let prefix = format!("{} {} ", number(change.old), number(change.new));
+                rows.push(change_line(prefix, change));
+            }
+        }
+        (rows, starts)
+    }
+
+    /// The rows of the split view as left and right halves, pairing deleted
+    /// lines with the inserted lines that replace them.
+    fn split_rows(&self) -> (Vec<(Line<'static>, Line<'static>)>, Vec<usize>) {
+        let mut rows = vec![];
+        let mut starts = vec![];
+        for (header, changes) in &self.hunks {
+            starts.push(rows.len());
+            let header = Line::from(header.clone()).style(Style::default().fg(Color::Cyan));
+            rows.push((header.clone(), header));
+
+            let mut deleted = vec![];
+            let mut inserted = vec![];
+            for change in changes {
+                match change.tag {
+                    ChangeTag::Delete => deleted.push(change),
+                    ChangeTag::Insert => inserted.push(change),
+                    ChangeTag::Equal => {
+                        pair_changes(&mut rows, &mut deleted, &mut inserted);
+                        let old = format!("{:>5} ", change.old.unwrap_or_default() + 1);
+                        let new = format!("{:>5} ", change.new.unwrap_or_default() + 1);
+                        rows.push((change_line(old, change), change_line(new, change)));
+                    }
+                }
+            }
+            pair_changes(&mut rows, &mut deleted, &mut inserted);
+        }
+        (rows, starts)
+    }
+
+    fn hunk_starts(&self) -> Vec<usize> {
+        if self.split {
+            self.split_rows().1
+        } else {
+            self.unified_rows().1
+        }
+    }
+
+    pub fn next_hunk(&mut self) {
+        if let Some(start) = self
+            .hunk_starts()
+            .into_iter()
+            .find(|start| *start > self.scroll)
+        {
+            self.scroll = start;
+        }
+    }
+
+    pub fn previous_hunk(&mut self) {
+        if let Some(start) = self
+            .hunk_starts()
+            .into_iter()
+            .rev()
+            .find(|start| *start < self.scroll)
+        {
+            self.scroll = start;
+        }
+    }
+
+    pub fn toggle_split(&mut self) {
+        // Keep the current hunk in view, since rows differ between the modes.
+        let hunk = self
+            .hunk_starts()
+            .iter()
+            .rposition(|start| *start <= self.scroll);
+        self.split = !self.split;
+        self.scroll = hunk
+            .and_then(|hunk| self.hunk_starts().get(hunk).copied())
+            .unwrap_or_default();
+    }
+
+    fn scroll_by(&mut self, down: bool) {
+        self.scroll = if down {
+            self.scroll + 1
+        } else {
+            self.scroll.saturating_sub(1)
+        };
+    }
+}
+
+fn change_line(prefix: String, change: &Change) -> Line<'static> {
+    let (marker, style, emphasis) = match change.tag {
+        ChangeTag::Delete => (
+            "-",
+            Style::default().fg(Color::Red),
+            Style::default().fg(Color::White).bg(Color::Red),
+        ),
+        ChangeTag::Insert => (
+            "+",
+            Style::default().fg(Color::Green),
+            Style::default().fg(Color::Black).bg(Color::Green),
+        ),
+        ChangeTag::Equal => (" ", Style::default(), Style::default()),
+    };
+    let mut spans = vec![Span::styled(format!("{prefix}{marker}"), style)];
+    spans.extend(change.segments.iter().map(|(emphasized, text)| {
+        Span::styled(text.clone(), if *emphasized { emphasis } else { style })
+    }));
+    Line::from(spans)
+}
+
+fn pair_changes(
+    rows: &mut Vec<(Line<'static>, Line<'static>)>,
+    deleted: &mut Vec<&Change>,
+    inserted: &mut Vec<&Change>,
+) {
+    for index in 0..deleted.len().max(inserted.len()) {
+        let half = |change: Option<&&Change>, number: fn(&Change) -> Option<usize>| match change {
+            Some(change) => {
+                let prefix = format!("{:>5} ", number(change).unwrap_or_default() + 1);
+                change_line(prefix, change)
+            }
+            None => Line::default(),
+        };
+        rows.push((
+            half(deleted.get(index), |change| change.old),
+            half(inserted.get(index), |change| change.new),
+        ));
+    }
+    deleted.clear();
+    inserted.clear();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DirStatus {
+    /// Only on the right.
+    Added,
+    /// Only on the left.
+    Removed,
+    Changed,
+}
+
+#[derive(Debug, Clone)]
+pub struct DirEntry {
+    pub relative: PathBuf,
+    pub status: DirStatus,
+    pub is_dir: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// A recursive comparison of two directories, listing the entries that
+/// differ. Entries only on one side are listed once, not their contents.
+#[derive(Debug)]
+pub struct DirDiff {
+    pub left: PathBuf,
+    pub right: PathBuf,
+    pub entries: Vec<DirEntry>,
+    pub select_state: ListState,
+    /// The diff of the changed file opened from the list.
+    pub file: Option<FileDiff>,
+    /// The side waiting for confirmation to be copied over the other.
+    pub confirm: Option<Side>,
+    pub status: Option<String>,
+    /// The comparison running on a worker thread, since it reads every file
+    /// that might have changed.
+    receiver: Option<Receiver<io::Result<Vec<DirEntry>>>>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl DirDiff {
+    pub fn new(left: &Path, right: &Path) -> DirDiff {
+        let mut dirs = DirDiff {
+            left: left.to_path_buf(),
+            right: right.to_path_buf(),
+            entries: vec![],
+            select_state: ListState::default().with_selected(Some(0)),
+            file: None,
+            confirm: None,
+            status: None,
+            receiver: None,
+            cancel: Arc::new(AtomicBool::new(false)),
+        };
+        dirs.compare();
+        dirs
+    }
+
+    /// Starts comparing the directories again, replacing a running
+    /// comparison. The old entries are shown until it is done.
+    fn compare(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let worker_cancel = cancel.clone();
+        let (left, right) = (self.left.clone(), self.right.clone());
+
+        thread::spawn(move || {
+            let mut entries = vec![];
+            let result = compare_dirs(&left, &right, Path::new(""), &worker_cancel, &mut entries)
+                .map(|()| entries);
+            let _ = sender.send(result);
+        });
+
+        self.receiver = Some(receiver);
+        self.cancel = cancel;
+    }
+
+    pub fn running(&self) -> bool {
+        self.receiver.is_some()
+    }
+
+    pub fn cancel(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+        self.receiver = None;
+        self.status = Some("Comparison cancelled".to_string());
+    }
+
+    /// Takes the result of a finished comparison.
+    pub fn poll(&mut self) {
+        let Some(receiver) = &self.receiver else {
+            return;
+        };
+        match receiver.try_recv() {
+            Ok(Ok(entries)) => {
+                self.entries = entries;
+                let last = self.entries.len().saturating_sub(1);
+                let selected = self.select_state.selected().map(|index| index.min(last));
+                self.select_state.select(selected);
+            }
+            Ok(Err(err)) => self.status = Some(format!("Could not compare: {err}")),
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => {}
+        }
+        self.receiver = None;
+    }
+
+    fn highlighted(&self) -> Option<&DirEntry> {
+        self.select_state
+            .selected()
+            .and_then(|index| self.entries.get(index))
+    }
+
+    pub fn open_highlighted(&mut self) {
+        let Some(entry) = self.highlighted() else {
+            return;
+        };
+        if entry.status != DirStatus::Changed || entry.is_dir {
+            return;
+        }
+        let (left, right) = (
+            self.left.join(&entry.relative),
+            self.right.join(&entry.relative),
+        );
+        match FileDiff::new(&left, &right) {
+            Ok(diff) => self.file = Some(diff),
+            Err(err) => self.status = Some(format!("Could not diff: {err}")),
+        }
+    }
+
+    /// Copies the highlighted entry from `side` over the other side. Files
+    /// are overwritten, but entries are never deleted, so an entry only on
+    /// the target side stays as it is.
+    pub fn copy_over(&mut self, side: Side) {
+        self.confirm = None;
+        let Some(entry) = self.highlighted() else {
+            return;
+        };
+        let (from, to) = match side {
+            Side::Left => (&self.left, &self.right),
+            Side::Right => (&self.right, &self.left),
+        };
+        let (source, dest) = (from.join(&entry.relative), to.join(&entry.relative));
+
+        let result = match (fs::symlink_metadata(&source), fs::symlink_metadata(&dest)) {
+            (Err(_), _) => Err(io::Error::other(format!(
+                "{} does not exist",
+                source.display()
+            ))),
+            (Ok(_), Err(_)) => copy_recursive(&source, &dest, false),
+            (Ok(source_meta), Ok(dest_meta)) if source_meta.is_file() && dest_meta.is_file() => {
+                fs::copy(&source, &dest).map(|_| ())
+            }
+            (Ok(_), Ok(_)) => Err(io::Error::other(format!(
+                "{} is of a different type",
+                dest.display()
+            ))),
+        };
+
+        self.status = Some(match result {
+            Ok(()) => format!("Copied {} to {}", source.display(), dest.display()),
+            Err(err) => format!("Could not copy: {err}"),
+        });
+        self.compare();
+    }
+}
+
+fn compare_dirs(
+    left: &Path,
+    right: &Path,
+    relative: &Path,
+    cancel: &AtomicBool,
+    entries: &mut Vec<DirEntry>,
+) -> io::Result<()> {
+    let names = |dir: &Path| -> io::Result<BTreeSet<_>> {
+        Ok(fs::read_dir(dir.join(relative))?
+            .flatten()
+            .map(|entry| entry.file_name())
+            .collect())
+    };
+    let (left_names, right_names) = (names(left)?, names(right)?);
+
+    for name in left_names.union(&right_names) {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled"));
+        }
+        let relative = relative.join(name);
+        let left_meta = fs::symlink_metadata(left.join(&relative));
+        let right_meta = fs::symlink_metadata(right.join(&relative));
+        let (status, is_dir) = match (&left_meta, &right_meta) {
+            (Ok(left_meta), Err(_)) => (DirStatus::Removed, left_meta.is_dir()),
+            (Err(_), Ok(right_meta)) => (DirStatus::Added, right_meta.is_dir()),
+            (Ok(left_meta), Ok(right_meta)) if left_meta.is_dir() && right_meta.is_dir() => {
+                compare_dirs(left, right, &relative, cancel, entries)?;
+                continue;
+            }
+            (Ok(left_meta), Ok(right_meta)) => {
+                let same = if left_meta.is_symlink() && right_meta.is_symlink() {
+                    fs::read_link(left.join(&relative))? == fs::read_link(right.join(&relative))?
+                } else if left_meta.is_file() && right_meta.is_file() {
+                    left_meta.len() == right_meta.len()
+                        && same_content(&left.join(&relative), &right.join(&relative))?
+                } else {
+                    // FIFOs, sockets and devices have no content to read,
+                    // and reading them could block.
+                    left_meta.file_type() == right_meta.file_type()
+                        && left_meta.rdev() == right_meta.rdev()
+                };
+                if same {
+                    continue;
+                }
+                (
+                    DirStatus::Changed,
+                    left_meta.is_dir() || right_meta.is_dir(),
+                )
+            }
+            (Err(_), Err(_)) => continue,
+        };
+        entries.push(DirEntry {
+            relative,
+            status,
+            is_dir,
+        });
+    }
+    Ok(())
+}
+
+fn is_binary(path: &Path) -> io::Result<bool> {
+    let mut head = vec![];
+    fs::File::open(path)?
+        .take(BINARY_SNIFF_LEN)
+        .read_to_end(&mut head)?;
+    Ok(head.contains(&0))
+}
+
+fn same_content(left: &Path, right: &Path) -> io::Result<bool> {
+    let (mut left, mut right) = (fs::File::open(left)?, fs::File::open(right)?);
+    let mut left_buffer = vec![0; 64 * 1024];
+    let mut right_buffer = vec![0; 64 * 1024];
+    loop {
+        let read = left.read(&mut left_buffer)?;
+        if read == 0 {
+            return Ok(right.read(&mut right_buffer)? == 0);
+        }
+        right.read_exact(&mut right_buffer[..read])?;
+        if left_buffer[..read] != right_buffer[..read] {
+            return Ok(false);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum DiffView {
+    Files(FileDiff),
+    Dirs(DirDiff),
+}
+
+impl DiffView {
+    /// Diffs two files, or compares two directories recursively.
+    pub fn new(left: &Path, right: &Path) -> io::Result<DiffView> {
+        if left.is_dir() && right.is_dir() {
+            Ok(DiffView::Dirs(DirDiff::new(left, right)))
+        } else if !left.is_dir() && !right.is_dir() {
+            Ok(DiffView::Files(FileDiff::new(left, right)?))
+        } else {
+            Err(io::Error::other("can't compare a file with a directory"))
+        }
+    }
+
+    /// The file diff being shown, if any.
+    fn file_mut(&mut self) -> Option<&mut FileDiff> {
+        match self {
+            DiffView::Files(file) => Some(file),
+            DiffView::Dirs(dirs) => dirs.file.as_mut(),
+        }
+    }
+
+    pub fn confirming(&self) -> bool {
+        matches!(
+            self,
+            DiffView::Dirs(DirDiff {
+                confirm: Some(_),
+                ..
+            })
+        )
+    }
+
+    pub fn down(&mut self) {
+        match self {
+            DiffView::Dirs(dirs) if dirs.file.is_none() => dirs.select_state.select_next(),
+            _ => self
+                .file_mut()
+                .into_iter()
+                .for_each(|file| file.scroll_by(true)),
+        }
+    }
+
+    pub fn up(&mut self) {
+        match self {
+            DiffView::Dirs(dirs) if dirs.file.is_none() => dirs.select_state.select_previous(),
+            _ => self
+                .file_mut()
+                .into_iter()
+                .for_each(|file| file.scroll_by(false)),
+        }
+    }
+
+    pub fn next_hunk(&mut self) {
+        if let Some(file) = self.file_mut() {
+            file.next_hunk();
+        }
+    }
+
+    pub fn previous_hunk(&mut self) {
+        if let Some(file) = self.file_mut() {
+            file.previous_hunk();
+        }
+    }
+
+    pub fn toggle_split(&mut self) {
+        if let Some(file) = self.file_mut() {
+            file.toggle_split();
+        }
+    }
+
+    pub fn open(&mut self) {
+        if let DiffView::Dirs(dirs) = self {
+            if dirs.file.is_none() {
+                dirs.open_highlighted();
+            }
+        }
+    }
+
+    pub fn ask_copy(&mut self, side: Side) {
+        if let DiffView::Dirs(dirs) = self {
+            if dirs.file.is_none() && dirs.highlighted().is_some() {
+                dirs.confirm = Some(side);
+            }
+        }
+    }
+
+    pub fn confirm(&mut self) {
+        if let DiffView::Dirs(dirs) = self {
+            if let Some(side) = dirs.confirm {
+                dirs.copy_over(side);
+            }
+        }
+    }
+
+    pub fn cancel(&mut self) {
+        if let DiffView::Dirs(dirs) = self {
+            dirs.confirm = None;
+        }
+    }
+
+    pub fn poll(&mut self) {
+        if let DiffView::Dirs(dirs) = self {
+            dirs.poll();
+        }
+    }
+
+    /// Steps back from a file opened from a directory comparison, or stops
+    /// a running comparison. Returns whether the whole view should be
+    /// closed.
+    pub fn back(&mut self) -> bool {
+        match self {
+            DiffView::Dirs(dirs) if dirs.file.is_some() => {
+                dirs.file = None;
+                false
+            }
+            DiffView::Dirs(dirs) if dirs.running() => {
+                dirs.cancel();
+                false
+            }
+            _ => true,
+        }
+    }
+}
+
+pub fn render(view: &mut DiffView, frame: &mut Frame, area: Rect, style: Style) {
+    match view {
+        DiffView::Files(file) => render_file(file, frame, area, style),
+        DiffView::Dirs(DirDiff {
+            file: Some(file), ..
+        }) => render_file(file, frame, area, style),
+        DiffView::Dirs(dirs) => render_dirs(dirs, frame, area, style),
+    }
+}
+
+fn render_file(file: &mut FileDiff, frame: &mut Frame, area: Rect, style: Style) {
+    let [header_area, body_area] =
+        Layout::vertical([Constraint::Length(3), Constraint::Min(1)]).areas(area);
+
+    let mode = if file.split { "split" } else { "unified" };
+    let header = Paragraph::new("j/k - scroll, n/N - next/previous hunk, s - split, Esc - close")
+        .style(style)
+        .block(Block::bordered().title(format!(
+            "{} -> {} ({mode}, {} hunks)",
+            file.left.display(),
+            file.right.display(),
+            file.hunks.len()
+        )));
+    frame.render_widget(header, header_area);
+
+    if let Some(message) = &file.message {
+        frame.render_widget(
+            Paragraph::new(message.as_str())
+                .style(style)
+                .block(Block::bordered()),
+            body_area,
+        );
+        return;
+    }
+
+    let rows = if file.split {
+        file.split_rows().0.len()
+    } else {
+        file.unified_rows().0.len()
+    };
+    file.scroll = file.scroll.min(rows.saturating_sub(1));
+    #[allow(clippy::cast_possible_truncation)]
+    let scroll = (file.scroll as u16, 0);
+
+    if file.split {
+        let (rows, _) = file.split_rows();
+        let (left, right): (Vec<_>, Vec<_>) = rows.into_iter().unzip();
+        let [left_area, right_area] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .areas(body_area);
+        let title = |path: &Path| path.display().to_string();
+        frame.render_widget(
+            Paragraph::new(left)
+                .style(style)
+                .scroll(scroll)
+                .block(Block::bordered().title(title(&file.left))),
+            left_area,
+        );
+        frame.render_widget(
+            Paragraph::new(right)
+                .style(style)
+                .scroll(scroll)
+                .block(Block::bordered().title(title(&file.right))),
+            right_area,
+        );
+    } else {
+        frame.render_widget(
+            Paragraph::new(file.unified_rows().0)
+                .style(style)
+                .scroll(scroll)
+                .block(Block::bordered()),
+            body_area,
+        );
+    }
+}
+
+fn render_dirs(dirs: &mut DirDiff, frame: &mut Frame, area: Rect, style: Style) {
+    let [header_area, list_area] =
+        Layout::vertical([Constraint::Length(3), Constraint::Min(1)]).areas(area);
+
+    let status = match (dirs.confirm, &dirs.status) {
+        (Some(Side::Left), _) => "Copy the left entry over the right one? (y/n)".to_string(),
+        (Some(Side::Right), _) => "Copy the right entry over the left one? (y/n)".to_string(),
+        (None, Some(status)) => status.clone(),
+        (None, None) if dirs.running() => "Comparing... (Esc - cancel)".to_string(),
+        (None, None) => {
+            "Enter - diff file, > - copy left to right, < - copy right to left, Esc - close"
+                .to_string()
+        }
+    };
+    let header = Paragraph::new(status)
+        .style(style)
+        .block(Block::bordered().title(format!(
+            "{} <-> {} ({} differences)",
+            dirs.left.display(),
+            dirs.right.display(),
+            dirs.entries.len()
+        )));
+    frame.render_widget(header, header_area);
+
+    let items: Vec<ListItem> = dirs
+        .entries
+        .iter()
+        .map(|entry| {
+            let (marker, color) = match entry.status {
+                DirStatus::Added => ("+", Color::Green),
+                DirStatus::Removed => ("-", Color::Red),
+                DirStatus::Changed => ("~", Color::Yellow),
+            };
+            let suffix = if entry.is_dir { "/" } else { "" };
+            ListItem::new(format!("{marker} {}{suffix}", entry.relative.display()))
+                .style(Style::default().fg(color))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .style(style)
+        .highlight_style(Style::default().bg(Color::Green).fg(Color::White))
+        .block(Block::bordered());
+    frame.render_stateful_widget(list, list_area, &mut dirs.select_state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_diff_hunks() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nm\n";
+        let diff = FileDiff::from_text(Path::new("old"), Path::new("new"), old, new);
+        assert_eq!(diff.hunks.len(), 2);
+        assert_eq!(diff.hunks[0].0, "@@ -1,5 +1,5 @@");
+
+        // The changed line is paired with its replacement in the split view.
+        let (rows, starts) = diff.split_rows();
+        assert_eq!(starts, vec![0, 6]);
+        assert_eq!(rows.len(), 11);
+        assert_eq!(rows[2].0.to_string(), "    2 -b");
+        assert_eq!(rows[2].1.to_string(), "    2 +B");
+
+        let same = FileDiff::from_text(Path::new("old"), Path::new("new"), old, old);
+        assert!(same.message.is_some());
+    }
+
+    #[test]
+    fn test_compare_dirs() {
+        let dir = std::env::temp_dir().join(format!("rustor-diff-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let (left, right) = (dir.join("left"), dir.join("right"));
+        for side in [&left, &right] {
+            fs::create_dir_all(side.join("sub")).unwrap();
+            fs::write(side.join("same"), "same").unwrap();
+            std::os::unix::fs::symlink("same", side.join("same-link")).unwrap();
+        }
+        fs::write(left.join("sub/changed"), "old").unwrap();
+        fs::write(right.join("sub/changed"), "new").unwrap();
+        fs::write(left.join("removed"), "").unwrap();
+        fs::write(right.join("added"), "").unwrap();
+        std::os::unix::fs::symlink("a", left.join("link")).unwrap();
+        std::os::unix::fs::symlink("b", right.join("link")).unwrap();
+        fs::write(left.join("kind"), "").unwrap();
+        fs::create_dir(right.join("kind")).unwrap();
+
+        // FIFOs are compared by type, reading them would block.
+        for side in [&left, &right] {
+            let fifo =
+                std::ffi::CString::new(side.join("fifo").into_os_string().into_encoded_bytes())
+                    .unwrap();
+            // SAFETY: `fifo` is a valid NUL-terminated path.
+            assert_eq!(unsafe { libc::mkfifo(fifo.as_ptr(), 0o600) }, 0);
+        }
+
+        let mut dirs = DirDiff::new(&left, &right);
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        while dirs.running() && std::time::Instant::now() < deadline {
+            thread::sleep(std::time::Duration::from_millis(10));
+            dirs.poll();
+        }
+        assert_eq!(dirs.status, None);
+        let entries = dirs.entries;
+        let entries: Vec<(String, DirStatus, bool)> = entries
+            .into_iter()
+            .map(|entry| {
+                (
+                    entry.relative.display().to_string(),
+                    entry.status,
+                    entry.is_dir,
+                )
+            })
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                ("added".to_string(), DirStatus::Added, false),
+                ("kind".to_string(), DirStatus::Changed, true),
+                ("link".to_string(), DirStatus::Changed, false),
+                ("removed".to_string(), DirStatus::Removed, false),
+                ("sub/changed".to_string(), DirStatus::Changed, false),
+            ]
+        );
+
+        // Latin-1 text is still diffed, binary files are only compared.
+        fs::write(left.join("latin"), b"caf\xe9\n").unwrap();
+        fs::write(right.join("latin"), b"caf\xe9s\n").unwrap();
+        let diff = FileDiff::new(&left.join("latin"), &right.join("latin")).unwrap();
+        assert_eq!(diff.hunks.len(), 1);
+        fs::write(left.join("binary"), b"\0a").unwrap();
+        fs::write(right.join("binary"), b"\0b").unwrap();
+        let diff = FileDiff::new(&left.join("binary"), &right.join("binary")).unwrap();
+        assert_eq!(diff.message.as_deref(), Some("Binary files differ"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
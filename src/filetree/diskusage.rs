@@ -0,0 +1,378 @@
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style},
+    widgets::{Block, List, ListState, Paragraph},
+    Frame,
+};
+
+use std::{
+    cmp::Reverse,
+    collections::HashSet,
+    fs, io,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, TryRecvError},
+        Arc,
+    },
+    thread,
+    time::SystemTime,
+};
+
+use super::listing::format_size;
+use super::treemap::{self, ColorBy};
+use super::vfs::FileSystem;
+
+const PROGRESS_INTERVAL: u64 = 1000;
+const BAR_WIDTH: usize = 20;
+
+#[derive(Debug, Clone)]
+pub struct DuNode {
+    pub path: PathBuf,
+    pub apparent: u64,
+    pub disk: u64,
+    pub is_dir: bool,
+    /// The newest modification time in the subtree.
+    pub modified: Option<SystemTime>,
+    pub children: Vec<DuNode>,
+}
+
+impl DuNode {
+    pub fn name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.path.display().to_string())
+    }
+
+    pub fn size(&self, on_disk: bool) -> u64 {
+        if on_disk {
+            self.disk
+        } else {
+            self.apparent
+        }
+    }
+
+    pub fn child(&self, path: &Path) -> Option<&DuNode> {
+        self.children.iter().find(|child| child.path == path)
+    }
+
+    pub fn sort_by_size(&mut self, on_disk: bool) {
+        self.children
+            .sort_by_key(|child| Reverse(child.size(on_disk)));
+        self.children
+            .iter_mut()
+            .for_each(|child| child.sort_by_size(on_disk));
+    }
+}
+
+#[derive(Debug)]
+enum ScanEvent {
+    Progress(u64, u64),
+    Done(DuNode),
+}
+
+struct Scanner<'a> {
+    device: u64,
+    seen: HashSet<(u64, u64)>,
+    files: u64,
+    bytes: u64,
+    cancel: &'a AtomicBool,
+    sender: &'a mpsc::Sender<ScanEvent>,
+}
+
+impl Scanner<'_> {
+    fn scan(&mut self, path: &Path, metadata: &fs::Metadata) -> DuNode {
+        let mut node = DuNode {
+            path: path.to_path_buf(),
+            apparent: metadata.len(),
+            disk: metadata.blocks() * 512,
+            is_dir: metadata.is_dir(),
+            modified: metadata.modified().ok(),
+            children: vec![],
+        };
+
+        // Hardlinked files are only counted the first time we see them.
+        if !node.is_dir
+            && metadata.nlink() > 1
+            && !self.seen.insert((metadata.dev(), metadata.ino()))
+        {
+            node.apparent = 0;
+            node.disk = 0;
+        }
+
+        self.files += 1;
+        self.bytes += node.apparent;
+        if self.files.is_multiple_of(PROGRESS_INTERVAL) {
+            let _ = self
+                .sender
+                .send(ScanEvent::Progress(self.files, self.bytes));
+        }
+
+        // Stay on the filesystem the scan started on, like `du -x`.
+        if !node.is_dir || metadata.dev() != self.device {
+            return node;
+        }
+
+        let Ok(children) = fs::read_dir(path) else {
+            return node;
+        };
+        for child in children.flatten() {
+            if self.cancel.load(Ordering::Relaxed) {
+                break;
+            }
+            let Ok(child_metadata) = child.metadata() else {
+                continue;
+            };
+            let child = self.scan(&child.path(), &child_metadata);
+            node.apparent += child.apparent;
+            node.disk += child.disk;
+            node.modified = node.modified.max(child.modified);
+            node.children.push(child);
+        }
+        node.children.sort_by_key(|child| Reverse(child.apparent));
+        node
+    }
+}
+
+#[derive(Debug)]
+pub struct SizeScan {
+    pub root: PathBuf,
+    pub files: u64,
+    pub bytes: u64,
+    receiver: Receiver<ScanEvent>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl SizeScan {
+    pub fn start(root: &Path) -> io::Result<SizeScan> {
+        let metadata = fs::symlink_metadata(root)?;
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let worker_cancel = cancel.clone();
+        let worker_root = root.to_path_buf();
+
+        thread::spawn(move || {
+            let mut scanner = Scanner {
+                device: metadata.dev(),
+                seen: HashSet::new(),
+                files: 0,
+                bytes: 0,
+                cancel: &worker_cancel,
+                sender: &sender,
+            };
+            let tree = scanner.scan(&worker_root, &metadata);
+            if !worker_cancel.load(Ordering::Relaxed) {
+                let _ = sender.send(ScanEvent::Done(tree));
+            }
+        });
+
+        Ok(SizeScan {
+            root: root.to_path_buf(),
+            files: 0,
+            bytes: 0,
+            receiver,
+            cancel,
+        })
+    }
+
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// Drains progress updates and returns the finished tree, if any.
+    pub fn poll(&mut self) -> Option<DuNode> {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(ScanEvent::Progress(files, bytes)) => {
+                    self.files = files;
+                    self.bytes = bytes;
+                }
+                Ok(ScanEvent::Done(tree)) => return Some(tree),
+                Err(TryRecvError::Empty) | Err(TryRecvError::Disconnected) => return None,
+            }
+        }
+    }
+
+    pub fn progress(&self) -> String {
+        format!(
+            "scanning {}: {} files, {}",
+            self.root.display(),
+            self.files,
+            format_size(self.bytes)
+        )
+    }
+}
+
+/// The ncdu-style explorer over a finished scan.
+#[derive(Debug, Default)]
+pub struct DuView {
+    /// Child indices leading from the scan root to the directory being shown.
+    pub stack: Vec<usize>,
+    pub select_state: ListState,
+    pub on_disk: bool,
+    pub confirm_delete: bool,
+    /// Whether the current directory is drawn as a treemap instead of a list.
+    pub treemap: bool,
+    pub color_by: ColorBy,
+}
+
+impl DuView {
+    pub fn current<'a>(&self, tree: &'a DuNode) -> &'a DuNode {
+        self.stack
+            .iter()
+            .fold(tree, |node, index| &node.children[*index])
+    }
+
+    fn current_mut<'a>(&self, tree: &'a mut DuNode) -> &'a mut DuNode {
+        self.stack
+            .iter()
+            .fold(tree, |node, index| &mut node.children[*index])
+    }
+
+    pub fn enter(&mut self, tree: &DuNode) {
+        let Some(index) = self.select_state.selected() else {
+            return;
+        };
+        if self
+            .current(tree)
+            .children
+            .get(index)
+            .is_some_and(|child| child.is_dir)
+        {
+            self.stack.push(index);
+            self.select_state.select(Some(0));
+        }
+    }
+
+    pub fn toggle_mode(&mut self, tree: &mut DuNode) {
+        self.on_disk = !self.on_disk;
+        tree.sort_by_size(self.on_disk);
+        self.stack.clear();
+        self.select_state.select(Some(0));
+    }
+
+    pub fn leave(&mut self) {
+        if let Some(index) = self.stack.pop() {
+            self.select_state.select(Some(index));
+        }
+    }
+
+    /// Deletes the highlighted child from disk and from the scanned tree.
+    pub fn delete(&mut self, tree: &mut DuNode, vfs: &dyn FileSystem) -> io::Result<PathBuf> {
+        self.confirm_delete = false;
+        let Some(index) = self.select_state.selected() else {
+            return Err(io::Error::other("Nothing selected"));
+        };
+        let current = self.current(tree);
+        let Some(child) = current.children.get(index) else {
+            return Err(io::Error::other("Nothing selected"));
+        };
+
+        let (path, apparent, disk) = (child.path.clone(), child.apparent, child.disk);
+        vfs.remove(&path)?;
+
+        let mut node = &mut *tree;
+        for step in &self.stack {
+            node.apparent -= apparent;
+            node.disk -= disk;
+            node = &mut node.children[*step];
+        }
+        node.apparent -= apparent;
+        node.disk -= disk;
+        self.current_mut(tree).children.remove(index);
+        Ok(path)
+    }
+}
+
+pub fn render(
+    view: &mut DuView,
+    tree: Option<&DuNode>,
+    scan: Option<&SizeScan>,
+    frame: &mut Frame,
+    area: Rect,
+    style: Style,
+) {
+    let [header_area, list_area] =
+        Layout::vertical([Constraint::Length(3), Constraint::Min(1)]).areas(area);
+
+    let Some(tree) = tree else {
+        let status = scan.map_or("No scan results".to_string(), SizeScan::progress);
+        frame.render_widget(
+            Paragraph::new(status)
+                .style(style)
+                .block(Block::bordered().title("Disk usage")),
+            area,
+        );
+        return;
+    };
+
+    let current = view.current(tree);
+    let total = current.size(view.on_disk).max(1);
+    let mode = if view.on_disk {
+        "disk usage"
+    } else {
+        "apparent size"
+    };
+    let status = match (scan, view.confirm_delete) {
+        (_, true) => "Delete highlighted entry? (y/n)".to_string(),
+        (Some(scan), _) => scan.progress(),
+        (None, _) if view.treemap => format!(
+            "j/k - select, l - zoom in, h - zoom out, c - color by {}, t - list, Esc - close",
+            view.color_by.next().name()
+        ),
+        (None, _) => {
+            "l - enter, h - up, d - delete, a - apparent/disk, t - treemap, Esc - close".to_string()
+        }
+    };
+    let header = Paragraph::new(status)
+        .style(style)
+        .block(Block::bordered().title(format!(
+            "{} ({mode}: {})",
+            current.path.display(),
+            format_size(current.size(view.on_disk))
+        )));
+    frame.render_widget(header, header_area);
+
+    if view.treemap {
+        // The list clamps its selection when drawn, the treemap does it here.
+        let last = current.children.len().saturating_sub(1);
+        let selected = view.select_state.selected().map(|index| index.min(last));
+        view.select_state.select(selected);
+        treemap::render(
+            current,
+            selected,
+            view.on_disk,
+            view.color_by,
+            frame,
+            list_area,
+            style,
+        );
+        return;
+    }
+
+    let items: Vec<String> = current
+        .children
+        .iter()
+        .map(|child| {
+            let size = child.size(view.on_disk);
+            let ratio = size as f64 / total as f64;
+            let filled = (ratio * BAR_WIDTH as f64).round() as usize;
+            let suffix = if child.is_dir { "/" } else { "" };
+            format!(
+                "{:>8} [{:<BAR_WIDTH$}] {:>5.1}% {}{suffix}",
+                format_size(size),
+                "#".repeat(filled.min(BAR_WIDTH)),
+                ratio * 100.0,
+                child.name()
+            )
+        })
+        .collect();
+
+    let list = List::new(items)
+        .style(style)
+        .highlight_style(Style::default().bg(Color::Green).fg(Color::White))
+        .block(Block::bordered());
+    frame.render_stateful_widget(list, list_area, &mut view.select_state);
+}
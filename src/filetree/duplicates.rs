@@ -0,0 +1,546 @@
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    fs,
+    io::{self, Read},
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, TryRecvError},
+        Arc,
+    },
+    thread,
+    time::SystemTime,
+};
+
+use super::batch::BatchReport;
+use super::listing::format_size;
+
+const PARTIAL_LENGTH: u64 = 16 * 1024;
+const PROGRESS_INTERVAL: u64 = 100;
+
+/// Files with identical content.
+#[derive(Debug, Clone)]
+pub struct DupGroup {
+    pub size: u64,
+    pub files: Vec<PathBuf>,
+    /// When each file was last modified as of the scan.
+    modified: HashMap<PathBuf, SystemTime>,
+}
+
+impl DupGroup {
+    pub fn wasted(&self) -> u64 {
+        self.size * (self.files.len() as u64).saturating_sub(1)
+    }
+}
+
+#[derive(Debug)]
+enum ScanEvent {
+    Progress(&'static str, u64, u64),
+    Done(Vec<DupGroup>),
+}
+
+struct Finder<'a> {
+    cancel: &'a AtomicBool,
+    sender: &'a mpsc::Sender<ScanEvent>,
+}
+
+impl Finder<'_> {
+    fn cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    /// Collects regular files by size with their modification times,
+    /// counting hardlinked files once since they don't waste any space.
+    fn walk(
+        &self,
+        path: &Path,
+        seen: &mut HashSet<(u64, u64)>,
+        by_size: &mut HashMap<u64, Vec<PathBuf>>,
+        modified: &mut HashMap<PathBuf, SystemTime>,
+    ) {
+        let Ok(children) = fs::read_dir(path) else {
+            return;
+        };
+        for child in children.flatten() {
+            if self.cancelled() {
+                return;
+            }
+            let Ok(metadata) = child.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                self.walk(&child.path(), seen, by_size, modified);
+            } else if metadata.is_file()
+                && metadata.len() > 0
+                && seen.insert((metadata.dev(), metadata.ino()))
+            {
+                if let Ok(time) = metadata.modified() {
+                    modified.insert(child.path(), time);
+                }
+                by_size
+                    .entry(metadata.len())
+                    .or_default()
+                    .push(child.path());
+                if (seen.len() as u64).is_multiple_of(PROGRESS_INTERVAL) {
+                    let _ = self
+                        .sender
+                        .send(ScanEvent::Progress("listing", seen.len() as u64, 0));
+                }
+            }
+        }
+    }
+
+    /// Splits every group by the hash of its files, dropping files that
+    /// can't be read and groups left with a single file.
+    fn refine(
+        &self,
+        stage: &'static str,
+        groups: Vec<DupGroup>,
+        limit: Option<u64>,
+    ) -> Vec<DupGroup> {
+        let total: u64 = groups.iter().map(|group| group.files.len() as u64).sum();
+        let mut done = 0;
+        let mut refined = vec![];
+
+        for group in groups {
+            let mut by_hash: HashMap<blake3::Hash, Vec<PathBuf>> = HashMap::new();
+            for file in group.files {
+                if self.cancelled() {
+                    return vec![];
+                }
+                if let Ok(hash) = hash_file(&file, limit) {
+                    by_hash.entry(hash).or_default().push(file);
+                }
+                done += 1;
+                if done % PROGRESS_INTERVAL == 0 {
+                    let _ = self.sender.send(ScanEvent::Progress(stage, done, total));
+                }
+            }
+            refined.extend(
+                by_hash
+                    .into_values()
+                    .filter(|files| files.len() > 1)
+                    .map(|files| DupGroup {
+                        size: group.size,
+                        files,
+                        modified: HashMap::new(),
+                    }),
+            );
+        }
+        refined
+    }
+}
+
+fn hash_file(path: &Path, limit: Option<u64>) -> io::Result<blake3::Hash> {
+    let file = fs::File::open(path)?;
+    let mut hasher = blake3::Hasher::new();
+    match limit {
+        Some(limit) => hasher.update_reader(file.take(limit))?,
+        None => hasher.update_reader(file)?,
+    };
+    Ok(hasher.finalize())
+}
+
+/// A background search for duplicate files below `root`: files are grouped
+/// by size, then by a hash of their first bytes and finally by a full
+/// BLAKE3 hash, so only likely duplicates are ever read completely.
+#[derive(Debug)]
+pub struct DupScan {
+    pub root: PathBuf,
+    stage: &'static str,
+    done: u64,
+    total: u64,
+    receiver: Receiver<ScanEvent>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl DupScan {
+    pub fn start(root: &Path) -> DupScan {
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let worker_cancel = cancel.clone();
+        let worker_root = root.to_path_buf();
+
+        thread::spawn(move || {
+            let finder = Finder {
+                cancel: &worker_cancel,
+                sender: &sender,
+            };
+
+            let mut by_size = HashMap::new();
+            let mut modified = HashMap::new();
+            finder.walk(
+                &worker_root,
+                &mut HashSet::new(),
+                &mut by_size,
+                &mut modified,
+            );
+            let groups: Vec<DupGroup> = by_size
+                .into_iter()
+                .filter(|(_, files)| files.len() > 1)
+                .map(|(size, files)| DupGroup {
+                    size,
+                    files,
+                    modified: HashMap::new(),
+                })
+                .collect();
+
+            let groups = finder.refine("partial hash", groups, Some(PARTIAL_LENGTH));
+            let mut groups = finder.refine("full hash", groups, None);
+            groups.sort_by_key(|group| Reverse(group.wasted()));
+            for group in &mut groups {
+                group.files.sort();
+                group.modified = group
+                    .files
+                    .iter()
+                    .filter_map(|file| Some((file.clone(), *modified.get(file)?)))
+                    .collect();
+            }
+
+            if !finder.cancelled() {
+                let _ = sender.send(ScanEvent::Done(groups));
+            }
+        });
+
+        DupScan {
+            root: root.to_path_buf(),
+            stage: "listing",
+            done: 0,
+            total: 0,
+            receiver,
+            cancel,
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// Drains progress updates and returns the duplicate groups once done.
+    pub fn poll(&mut self) -> Option<Vec<DupGroup>> {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(ScanEvent::Progress(stage, done, total)) => {
+                    self.stage = stage;
+                    self.done = done;
+                    self.total = total;
+                }
+                Ok(ScanEvent::Done(groups)) => return Some(groups),
+                Err(TryRecvError::Empty) | Err(TryRecvError::Disconnected) => return None,
+            }
+        }
+    }
+
+    pub fn progress(&self) -> String {
+        match self.total {
+            0 => format!("{}: {} files", self.stage, self.done),
+            total => format!("{}: {}/{total} files", self.stage, self.done),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DupAction {
+    Delete,
+    Hardlink,
+}
+
+/// The list of duplicate groups, where copies can be marked for deletion or
+/// for replacement by a hardlink to an unmarked copy.
+#[derive(Debug, Default)]
+pub struct DupView {
+    pub groups: Vec<DupGroup>,
+    pub select_state: ListState,
+    pub marked: HashSet<PathBuf>,
+    pub confirm: Option<DupAction>,
+    pub status: Option<String>,
+}
+
+impl DupView {
+    /// Every row as its group and, for file rows, the file in the group.
+    fn rows(&self) -> Vec<(usize, Option<usize>)> {
+        self.groups
+            .iter()
+            .enumerate()
+            .flat_map(|(group, dup)| {
+                std::iter::once((group, None))
+                    .chain((0..dup.files.len()).map(move |f| (group, Some(f))))
+            })
+            .collect()
+    }
+
+    fn highlighted(&self) -> Option<&PathBuf> {
+        let index = self.select_state.selected()?;
+        match self.rows().get(index)? {
+            (group, Some(file)) => self.groups[*group].files.get(*file),
+            (_, None) => None,
+        }
+    }
+
+    pub fn toggle_mark(&mut self) {
+        if let Some(path) = self.highlighted().cloned() {
+            if !self.marked.remove(&path) {
+                self.marked.insert(path);
+            }
+        }
+        self.select_state.select_next();
+    }
+
+    /// Marks every copy but the first of each group.
+    pub fn mark_copies(&mut self) {
+        self.marked = self
+            .groups
+            .iter()
+            .flat_map(|group| group.files.iter().skip(1).cloned())
+            .collect();
+    }
+
+    /// Deletes or hardlinks the marked copies. Groups where every copy is
+    /// marked are left alone so at least one copy always survives, as are
+    /// copies that changed since the scan.
+    pub fn apply(&mut self, action: DupAction) -> BatchReport {
+        self.confirm = None;
+        let mut report = BatchReport {
+            op: match action {
+                DupAction::Delete => "Delete duplicates".to_string(),
+                DupAction::Hardlink => "Hardlink duplicates".to_string(),
+            },
+            succeeded: 0,
+            failures: vec![],
+        };
+
+        for group in &mut self.groups {
+            let Some(keep) = group
+                .files
+                .iter()
+                .find(|file| !self.marked.contains(*file))
+                .cloned()
+            else {
+                for file in &group.files {
+                    report
+                        .failures
+                        .push((file.clone(), "every copy is marked".to_string()));
+                }
+                continue;
+            };
+
+            group.files.retain(|file| {
+                if !self.marked.contains(file) {
+                    return true;
+                }
+                let unchanged = |path: &Path| {
+                    let metadata = fs::symlink_metadata(path)?;
+                    let modified = metadata.modified().ok();
+                    if metadata.is_file()
+                        && metadata.len() == group.size
+                        && modified.is_some()
+                        && modified.as_ref() == group.modified.get(path)
+                    {
+                        Ok(())
+                    } else {
+                        Err(io::Error::other(format!(
+                            "{} changed since the scan",
+                            path.display()
+                        )))
+                    }
+                };
+                let result = unchanged(&keep)
+                    .and_then(|_| unchanged(file))
+                    .and_then(|_| match action {
+                        DupAction::Delete => fs::remove_file(file),
+                        DupAction::Hardlink => replace_with_link(&keep, file),
+                    });
+                // Hardlinked copies no longer waste space either.
+                match result {
+                    Ok(()) => {
+                        report.succeeded += 1;
+                        false
+                    }
+                    Err(err) => {
+                        report.failures.push((file.clone(), err.to_string()));
+                        true
+                    }
+                }
+            });
+        }
+
+        self.groups.retain(|group| group.files.len() > 1);
+        self.marked.clear();
+        self.select_state.select(Some(0));
+        report
+    }
+}
+
+/// Atomically replaces `copy` with a hardlink to `keep`.
+fn replace_with_link(keep: &Path, copy: &Path) -> io::Result<()> {
+    let temp = copy.with_file_name(format!(
+        ".rustor-link-{}",
+        copy.file_name().unwrap_or_default().to_string_lossy()
+    ));
+    fs::hard_link(keep, &temp)?;
+    fs::rename(&temp, copy).inspect_err(|_| {
+        let _ = fs::remove_file(&temp);
+    })
+}
+
+pub fn render(
+    view: &mut DupView,
+    scan: Option<&DupScan>,
+    frame: &mut Frame,
+    area: Rect,
+    style: Style,
+) {
+    let [header_area, list_area] =
+        Layout::vertical([Constraint::Length(3), Constraint::Min(1)]).areas(area);
+
+    let wasted: u64 = view.groups.iter().map(DupGroup::wasted).sum();
+    let status = match (view.confirm, scan, &view.status) {
+        (Some(DupAction::Delete), _, _) => {
+            format!("Delete {} marked copies? (y/n)", view.marked.len())
+        }
+        (Some(DupAction::Hardlink), _, _) => {
+            format!(
+                "Replace {} marked copies with hardlinks? (y/n)",
+                view.marked.len()
+            )
+        }
+        (None, Some(scan), _) => scan.progress(),
+        (None, None, Some(status)) => status.clone(),
+        (None, None, None) => {
+            "space - mark, a - mark all copies, d - delete, L - hardlink, Esc - close".to_string()
+        }
+    };
+    let title = match scan {
+        Some(scan) => format!("Duplicates in {}", scan.root.display()),
+        None => format!(
+            "Duplicates: {} groups, {} wasted, {} marked",
+            view.groups.len(),
+            format_size(wasted),
+            view.marked.len()
+        ),
+    };
+    let header = Paragraph::new(status)
+        .style(style)
+        .block(Block::bordered().title(title));
+    frame.render_widget(header, header_area);
+
+    let items: Vec<ListItem> = view
+        .rows()
+        .into_iter()
+        .map(|(group, file)| {
+            let dup = &view.groups[group];
+            match file {
+                None => ListItem::new(Line::from(format!(
+                    "{} copies of {}, {} wasted",
+                    dup.files.len(),
+                    format_size(dup.size),
+                    format_size(dup.wasted())
+                )))
+                .style(Style::default().fg(Color::Cyan)),
+                Some(file) => {
+                    let path = &dup.files[file];
+                    let marked = view.marked.contains(path);
+                    let marker = if marked { "*" } else { " " };
+                    let item = ListItem::new(format!("  {marker} {}", path.display()));
+                    if marked {
+                        item.style(Style::default().fg(Color::Yellow))
+                    } else {
+                        item
+                    }
+                }
+            }
+        })
+        .collect();
+
+    let list = List::new(items)
+        .style(style)
+        .highlight_style(Style::default().bg(Color::Green).fg(Color::White))
+        .block(Block::bordered());
+    frame.render_stateful_widget(list, list_area, &mut view.select_state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, time::Duration};
+
+    fn scan(root: &Path) -> Vec<DupGroup> {
+        let mut scan = DupScan::start(root);
+        loop {
+            if let Some(groups) = scan.poll() {
+                return groups;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("rustor-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_scan_groups() {
+        let dir = temp_dir("dups");
+        let content = vec![b'x'; PARTIAL_LENGTH as usize + 10];
+        let mut tail = content.clone();
+        *tail.last_mut().unwrap() = b'y';
+        fs::write(dir.join("a"), &content).unwrap();
+        fs::write(dir.join("sub/b"), &content).unwrap();
+        // Same size and start, different end.
+        fs::write(dir.join("c"), &tail).unwrap();
+        // A hardlink wastes nothing.
+        fs::hard_link(dir.join("a"), dir.join("sub/a-link")).unwrap();
+        fs::write(dir.join("d"), "other").unwrap();
+        fs::write(dir.join("e"), "").unwrap();
+        fs::write(dir.join("f"), "").unwrap();
+
+        let groups = scan(&dir);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].size, content.len() as u64);
+        assert_eq!(groups[0].files.len(), 2);
+        assert!(groups[0].files.contains(&dir.join("sub/b")));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_apply() {
+        let dir = temp_dir("dups-apply");
+        for name in ["a", "b", "c", "x", "y"] {
+            let content = if "abc".contains(name) { "same" } else { "copy" };
+            fs::write(dir.join(name), content).unwrap();
+        }
+        let mut view = DupView {
+            groups: scan(&dir),
+            ..Default::default()
+        };
+        assert_eq!(view.groups.len(), 2);
+
+        // Every copy of x and y is marked, and c changed since the scan.
+        view.marked = HashSet::from([dir.join("b"), dir.join("c"), dir.join("x"), dir.join("y")]);
+        let c = fs::File::options().write(true).open(dir.join("c")).unwrap();
+        c.set_modified(SystemTime::now() - Duration::from_secs(60))
+            .unwrap();
+
+        let report = view.apply(DupAction::Delete);
+        assert_eq!(report.succeeded, 1);
+        assert_eq!(report.failures.len(), 3);
+        assert!(!dir.join("b").exists());
+        for name in ["a", "c", "x", "y"] {
+            assert!(dir.join(name).exists());
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
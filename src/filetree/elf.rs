@@ -0,0 +1,565 @@
+use memmap2::Mmap;
+use object::{
+    elf,
+    read::elf::{ElfFile, FileHeader, ProgramHeader},
+    Endianness, FileKind, Object, ObjectSection, ObjectSymbol, SectionFlags, SymbolKind,
+};
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
+    widgets::{Block, Row, Table, TableState, Tabs},
+    Frame,
+};
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tab {
+    Header,
+    Sections,
+    Segments,
+    Dynamic,
+    Symbols,
+    Hardening,
+}
+
+const TABS: [Tab; 6] = [
+    Tab::Header,
+    Tab::Sections,
+    Tab::Segments,
+    Tab::Dynamic,
+    Tab::Symbols,
+    Tab::Hardening,
+];
+
+impl Tab {
+    fn title(&self) -> &'static str {
+        match self {
+            Tab::Header => "Header",
+            Tab::Sections => "Sections",
+            Tab::Segments => "Segments",
+            Tab::Dynamic => "Dynamic",
+            Tab::Symbols => "Symbols",
+            Tab::Hardening => "Hardening",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Yes,
+    Partial,
+    No,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    /// The Rust or C++ name behind a mangled name.
+    pub demangled: Option<String>,
+    pub kind: String,
+    pub address: u64,
+    pub size: u64,
+    pub defined: bool,
+    pub dynamic: bool,
+}
+
+/// What `readelf` and `checksec` would tell about an ELF file.
+#[derive(Debug, Clone, Default)]
+pub struct ElfInfo {
+    pub header: Vec<(String, String)>,
+    /// Name, kind, address, size and flags of every section.
+    pub sections: Vec<[String; 5]>,
+    /// Type, offset, address, file size, memory size and flags of every
+    /// segment.
+    pub segments: Vec<[String; 6]>,
+    /// The dynamic entries with string values, like needed libraries and
+    /// the rpath.
+    pub dynamic: Vec<(String, String)>,
+    pub symbols: Vec<Symbol>,
+    pub hardening: Vec<(&'static str, Verdict, String)>,
+}
+
+fn demangle(name: &str) -> Option<String> {
+    if let Ok(demangled) = rustc_demangle::try_demangle(name) {
+        return Some(format!("{demangled:#}"));
+    }
+    if name.starts_with("_Z") {
+        return cpp_demangle::Symbol::new(name)
+            .ok()
+            .and_then(|symbol| symbol.demangle().ok());
+    }
+    None
+}
+
+fn segment_type(p_type: u32) -> String {
+    match p_type {
+        elf::PT_NULL => "NULL".to_string(),
+        elf::PT_LOAD => "LOAD".to_string(),
+        elf::PT_DYNAMIC => "DYNAMIC".to_string(),
+        elf::PT_INTERP => "INTERP".to_string(),
+        elf::PT_NOTE => "NOTE".to_string(),
+        elf::PT_SHLIB => "SHLIB".to_string(),
+        elf::PT_PHDR => "PHDR".to_string(),
+        elf::PT_TLS => "TLS".to_string(),
+        elf::PT_GNU_EH_FRAME => "GNU_EH_FRAME".to_string(),
+        elf::PT_GNU_STACK => "GNU_STACK".to_string(),
+        elf::PT_GNU_RELRO => "GNU_RELRO".to_string(),
+        elf::PT_GNU_PROPERTY => "GNU_PROPERTY".to_string(),
+        other => format!("0x{other:x}"),
+    }
+}
+
+fn segment_flags(p_flags: u32) -> String {
+    [(elf::PF_R, 'R'), (elf::PF_W, 'W'), (elf::PF_X, 'E')]
+        .iter()
+        .map(|(flag, letter)| if p_flags & flag != 0 { *letter } else { ' ' })
+        .collect()
+}
+
+/// The section flags as `readelf` letters: write, alloc, execute, merge,
+/// strings, info link, TLS.
+fn section_flags(flags: SectionFlags) -> String {
+    let SectionFlags::Elf { sh_flags } = flags else {
+        return String::new();
+    };
+    [
+        (elf::SHF_WRITE, 'W'),
+        (elf::SHF_ALLOC, 'A'),
+        (elf::SHF_EXECINSTR, 'X'),
+        (elf::SHF_MERGE, 'M'),
+        (elf::SHF_STRINGS, 'S'),
+        (elf::SHF_INFO_LINK, 'I'),
+        (elf::SHF_TLS, 'T'),
+    ]
+    .iter()
+    .filter(|(flag, _)| sh_flags & u64::from(*flag) != 0)
+    .map(|(_, letter)| letter)
+    .collect()
+}
+
+fn os_abi(abi: u8) -> String {
+    match abi {
+        elf::ELFOSABI_SYSV => "UNIX System V".to_string(),
+        elf::ELFOSABI_GNU => "GNU/Linux".to_string(),
+        elf::ELFOSABI_FREEBSD => "FreeBSD".to_string(),
+        elf::ELFOSABI_NETBSD => "NetBSD".to_string(),
+        elf::ELFOSABI_OPENBSD => "OpenBSD".to_string(),
+        elf::ELFOSABI_SOLARIS => "Solaris".to_string(),
+        other => other.to_string(),
+    }
+}
+
+fn file_type(e_type: u16) -> &'static str {
+    match e_type {
+        elf::ET_REL => "REL (relocatable)",
+        elf::ET_EXEC => "EXEC (executable)",
+        elf::ET_DYN => "DYN (position independent or shared object)",
+        elf::ET_CORE => "CORE (core dump)",
+        _ => "unknown",
+    }
+}
+
+/// Parses an ELF file of either class and byte order.
+pub fn inspect(data: &[u8]) -> Result<ElfInfo, String> {
+    let result = match FileKind::parse(data).map_err(|err| err.to_string())? {
+        FileKind::Elf32 => read::<elf::FileHeader32<Endianness>>(data),
+        FileKind::Elf64 => read::<elf::FileHeader64<Endianness>>(data),
+        kind => return Err(format!("Not an ELF file but {kind:?}")),
+    };
+    result.map_err(|err| err.to_string())
+}
+
+fn read<Elf: FileHeader<Endian = Endianness>>(data: &[u8]) -> object::Result<ElfInfo> {
+    let file = ElfFile::<Elf>::parse(data)?;
+    let endian = file.endian();
+    let e_type = file.elf_header().e_type(endian);
+    let mut info = ElfInfo::default();
+
+    let mut interpreter = None;
+    let (mut relro, mut stack) = (false, None);
+    for header in file.elf_program_headers() {
+        let p_type = header.p_type(endian);
+        let p_flags = header.p_flags(endian);
+        if let Some(path) = header.interpreter(endian, data)? {
+            interpreter = Some(String::from_utf8_lossy(path).to_string());
+        }
+        relro |= p_type == elf::PT_GNU_RELRO;
+        if p_type == elf::PT_GNU_STACK {
+            stack = Some(p_flags & elf::PF_X == 0);
+        }
+        info.segments.push([
+            segment_type(p_type),
+            format!("0x{:x}", header.p_offset(endian).into()),
+            format!("0x{:x}", header.p_vaddr(endian).into()),
+            format!("0x{:x}", header.p_filesz(endian).into()),
+            format!("0x{:x}", header.p_memsz(endian).into()),
+            segment_flags(p_flags),
+        ]);
+    }
+
+    let mut bind_now = false;
+    let mut pie_flag = false;
+    let dynamic = file.elf_dynamic_table()?;
+    for entry in dynamic.iter() {
+        let name = match entry.tag {
+            elf::DT_NEEDED => "NEEDED",
+            elf::DT_SONAME => "SONAME",
+            elf::DT_RPATH => "RPATH",
+            elf::DT_RUNPATH => "RUNPATH",
+            elf::DT_BIND_NOW => {
+                bind_now = true;
+                continue;
+            }
+            elf::DT_FLAGS => {
+                bind_now |= entry.val & u64::from(elf::DF_BIND_NOW) != 0;
+                continue;
+            }
+            elf::DT_FLAGS_1 => {
+                bind_now |= entry.val & u64::from(elf::DF_1_NOW) != 0;
+                pie_flag |= entry.val & u64::from(elf::DF_1_PIE) != 0;
+                continue;
+            }
+            _ => continue,
+        };
+        if let Ok(value) = dynamic.string(entry) {
+            info.dynamic
+                .push((name.to_string(), String::from_utf8_lossy(value).to_string()));
+        }
+    }
+
+    for section in file.sections() {
+        info.sections.push([
+            section.name().unwrap_or_default().to_string(),
+            format!("{:?}", section.kind()),
+            format!("0x{:x}", section.address()),
+            format!("0x{:x}", section.size()),
+            section_flags(section.flags()),
+        ]);
+    }
+
+    for (symbols, dynamic) in [(file.symbols(), false), (file.dynamic_symbols(), true)] {
+        for symbol in symbols {
+            let name = symbol.name().unwrap_or_default();
+            if name.is_empty() || matches!(symbol.kind(), SymbolKind::File | SymbolKind::Section) {
+                continue;
+            }
+            info.symbols.push(Symbol {
+                name: name.to_string(),
+                demangled: demangle(name),
+                kind: format!("{:?}", symbol.kind()),
+                address: symbol.address(),
+                size: symbol.size(),
+                defined: !symbol.is_undefined(),
+                dynamic,
+            });
+        }
+    }
+
+    let build_id = file
+        .build_id()?
+        .map(|id| id.iter().map(|byte| format!("{byte:02x}")).collect())
+        .unwrap_or_else(|| "-".to_string());
+    info.header = vec![
+        (
+            "Class".to_string(),
+            if file.is_64() { "ELF64" } else { "ELF32" }.to_string(),
+        ),
+        (
+            "Data".to_string(),
+            if file.is_little_endian() {
+                "little endian"
+            } else {
+                "big endian"
+            }
+            .to_string(),
+        ),
+        ("Type".to_string(), file_type(e_type).to_string()),
+        ("Machine".to_string(), format!("{:?}", file.architecture())),
+        (
+            "OS/ABI".to_string(),
+            os_abi(file.elf_header().e_ident().os_abi),
+        ),
+        ("Entry point".to_string(), format!("0x{:x}", file.entry())),
+        (
+            "Interpreter".to_string(),
+            interpreter.clone().unwrap_or_else(|| "-".to_string()),
+        ),
+        ("Build ID".to_string(), build_id),
+        ("Sections".to_string(), info.sections.len().to_string()),
+        ("Segments".to_string(), info.segments.len().to_string()),
+        ("Symbols".to_string(), info.symbols.len().to_string()),
+    ];
+
+    info.hardening = hardening(
+        e_type,
+        interpreter.is_some() || pie_flag,
+        relro,
+        bind_now,
+        stack,
+        &info.symbols,
+    );
+    Ok(info)
+}
+
+/// The checks `checksec` runs, from the file type, the segments, the
+/// dynamic flags and the symbols imported from libc.
+fn hardening(
+    e_type: u16,
+    has_interpreter: bool,
+    relro: bool,
+    bind_now: bool,
+    stack: Option<bool>,
+    symbols: &[Symbol],
+) -> Vec<(&'static str, Verdict, String)> {
+    let pie = match e_type {
+        elf::ET_DYN if has_interpreter => (Verdict::Yes, "position independent executable"),
+        elf::ET_DYN => (Verdict::Yes, "shared object"),
+        elf::ET_EXEC => (Verdict::No, "loaded at a fixed address"),
+        _ => (Verdict::No, "not an executable"),
+    };
+    let relro = match (relro, bind_now) {
+        (true, true) => (Verdict::Yes, "full, the GOT is read-only after startup"),
+        (true, false) => (Verdict::Partial, "partial, without BIND_NOW"),
+        (false, _) => (Verdict::No, "no GNU_RELRO segment"),
+    };
+    let nx = match stack {
+        Some(true) => (Verdict::Yes, "the stack isn't executable"),
+        Some(false) => (Verdict::No, "GNU_STACK is executable"),
+        None => (Verdict::No, "no GNU_STACK segment"),
+    };
+
+    // Libraries like libc define these functions themselves, so only what is
+    // imported counts, unless nothing is, as in static binaries.
+    let imports = symbols.iter().any(|symbol| !symbol.defined);
+    let names = || {
+        symbols
+            .iter()
+            .filter(move |symbol| !imports || !symbol.defined)
+            .map(|symbol| symbol.name.as_str())
+    };
+    let canary = names().any(|name| {
+        matches!(
+            name,
+            "__stack_chk_fail" | "__stack_chk_guard" | "__intel_security_cookie"
+        )
+    });
+    let mut fortified: Vec<&str> = names()
+        .filter(|name| name.starts_with("__") && name.ends_with("_chk"))
+        .filter(|name| !name.starts_with("__stack_chk"))
+        .collect();
+    fortified.sort();
+    fortified.dedup();
+
+    vec![
+        ("PIE", pie.0, pie.1.to_string()),
+        ("RELRO", relro.0, relro.1.to_string()),
+        ("NX", nx.0, nx.1.to_string()),
+        match canary {
+            true => ("Canary", Verdict::Yes, "calls __stack_chk_fail".to_string()),
+            false => (
+                "Canary",
+                Verdict::No,
+                "no stack protector symbols".to_string(),
+            ),
+        },
+        match fortified.len() {
+            0 => ("FORTIFY", Verdict::No, "no fortified functions".to_string()),
+            _ => (
+                "FORTIFY",
+                Verdict::Yes,
+                format!("{} fortified: {}", fortified.len(), fortified.join(", ")),
+            ),
+        },
+    ]
+}
+
+/// The inspector for one ELF file, one tab at a time.
+#[derive(Debug)]
+pub struct ElfView {
+    pub path: PathBuf,
+    info: ElfInfo,
+    tab: Tab,
+    pub table_state: TableState,
+}
+
+impl ElfView {
+    pub fn open(path: &Path) -> Result<ElfView, String> {
+        let file = fs::File::open(path).map_err(|err| err.to_string())?;
+        // SAFETY: the map is only read while parsing and dropped after.
+        let map = unsafe { Mmap::map(&file) }.map_err(|err| err.to_string())?;
+        Ok(ElfView {
+            path: path.to_path_buf(),
+            info: inspect(&map)?,
+            tab: Tab::Header,
+            table_state: TableState::default().with_selected(Some(0)),
+        })
+    }
+
+    pub fn switch_tab(&mut self, forward: bool) {
+        let index = TABS.iter().position(|tab| *tab == self.tab).unwrap_or(0);
+        let index = match forward {
+            true => (index + 1) % TABS.len(),
+            false => (index + TABS.len() - 1) % TABS.len(),
+        };
+        self.tab = TABS[index];
+        self.table_state.select(Some(0));
+    }
+}
+
+fn verdict_color(verdict: Verdict) -> Color {
+    match verdict {
+        Verdict::Yes => Color::Green,
+        Verdict::Partial => Color::Yellow,
+        Verdict::No => Color::Red,
+    }
+}
+
+pub fn render(view: &mut ElfView, frame: &mut Frame, area: Rect, style: Style) {
+    let [header_area, table_area] =
+        Layout::vertical([Constraint::Length(3), Constraint::Min(1)]).areas(area);
+
+    let index = TABS.iter().position(|tab| *tab == view.tab).unwrap_or(0);
+    let tabs = Tabs::new(TABS.iter().map(Tab::title))
+        .select(index)
+        .style(style)
+        .highlight_style(Style::default().fg(Color::Green).bold())
+        .block(Block::bordered().title(format!(
+            "{} (h/l - switch tab, j/k - scroll, Esc - close)",
+            view.path.display()
+        )));
+    frame.render_widget(tabs, header_area);
+
+    let info = &view.info;
+    let cells = |values: &[String]| Row::new(values.to_vec());
+    let (header, rows, widths): (Vec<&str>, Vec<Row>, Vec<Constraint>) = match view.tab {
+        Tab::Header => (
+            vec!["Field", "Value"],
+            info.header
+                .iter()
+                .map(|(field, value)| Row::new([field.clone(), value.clone()]))
+                .collect(),
+            vec![Constraint::Length(12), Constraint::Fill(1)],
+        ),
+        Tab::Sections => (
+            vec!["Name", "Kind", "Address", "Size", "Flags"],
+            info.sections.iter().map(|section| cells(section)).collect(),
+            vec![
+                Constraint::Fill(1),
+                Constraint::Length(20),
+                Constraint::Length(20),
+                Constraint::Length(12),
+                Constraint::Length(7),
+            ],
+        ),
+        Tab::Segments => (
+            vec![
+                "Type",
+                "Offset",
+                "Address",
+                "File size",
+                "Mem size",
+                "Flags",
+            ],
+            info.segments.iter().map(|segment| cells(segment)).collect(),
+            vec![
+                Constraint::Length(14),
+                Constraint::Length(12),
+                Constraint::Length(20),
+                Constraint::Length(12),
+                Constraint::Length(12),
+                Constraint::Length(5),
+            ],
+        ),
+        Tab::Dynamic => (
+            vec!["Tag", "Value"],
+            info.dynamic
+                .iter()
+                .map(|(tag, value)| Row::new([tag.clone(), value.clone()]))
+                .collect(),
+            vec![Constraint::Length(10), Constraint::Fill(1)],
+        ),
+        Tab::Symbols => (
+            vec!["Name", "Kind", "Address", "Size", ""],
+            info.symbols
+                .iter()
+                .map(|symbol| {
+                    let name = symbol.demangled.as_ref().unwrap_or(&symbol.name).clone();
+                    let scope = match (symbol.defined, symbol.dynamic) {
+                        (false, _) => "undefined",
+                        (true, true) => "dynamic",
+                        (true, false) => "",
+                    };
+                    Row::new([
+                        name,
+                        symbol.kind.clone(),
+                        format!("0x{:x}", symbol.address),
+                        symbol.size.to_string(),
+                        scope.to_string(),
+                    ])
+                })
+                .collect(),
+            vec![
+                Constraint::Fill(1),
+                Constraint::Length(8),
+                Constraint::Length(20),
+                Constraint::Length(10),
+                Constraint::Length(10),
+            ],
+        ),
+        Tab::Hardening => (
+            vec!["Check", "Enabled", "Detail"],
+            info.hardening
+                .iter()
+                .map(|(check, verdict, detail)| {
+                    let enabled = match verdict {
+                        Verdict::Yes => "yes",
+                        Verdict::Partial => "partial",
+                        Verdict::No => "no",
+                    };
+                    Row::new([check.to_string(), enabled.to_string(), detail.clone()])
+                        .fg(verdict_color(*verdict))
+                })
+                .collect(),
+            vec![
+                Constraint::Length(8),
+                Constraint::Length(8),
+                Constraint::Fill(1),
+            ],
+        ),
+    };
+
+    let count = rows.len();
+    let table = Table::new(rows, widths)
+        .header(Row::new(header).bold())
+        .style(style)
+        .highlight_style(Style::default().bg(Color::Green).fg(Color::White))
+        .block(Block::bordered().title(format!("{} ({count})", view.tab.title())));
+    frame.render_stateful_widget(table, table_area, &mut view.table_state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inspect_self() {
+        let data = fs::read(std::env::current_exe().unwrap()).unwrap();
+        let info = inspect(&data).unwrap();
+        assert!(info.sections.iter().any(|section| section[0] == ".text"));
+        assert!(info.segments.iter().any(|segment| segment[0] == "LOAD"));
+        assert!(info.symbols.iter().any(|symbol| symbol
+            .demangled
+            .as_deref()
+            .is_some_and(|name| name.contains("test_inspect_self"))));
+        assert_eq!(info.hardening.len(), 5);
+
+        assert_eq!(demangle("_ZN3foo3barE").as_deref(), Some("foo::bar"));
+        assert_eq!(demangle("_Z3addii").as_deref(), Some("add(int, int)"));
+        assert_eq!(demangle("main"), None);
+        assert!(inspect(b"not an executable").is_err());
+    }
+}
@@ -0,0 +1,245 @@
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use regex::Regex;
+
+use std::time::{Duration, SystemTime};
+
+use super::listing::{EntryKind, FileEntry};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypeFilter {
+    All,
+    Files,
+    Dirs,
+    Symlinks,
+    Executables,
+}
+
+impl TypeFilter {
+    pub fn next(self) -> TypeFilter {
+        match self {
+            TypeFilter::All => TypeFilter::Files,
+            TypeFilter::Files => TypeFilter::Dirs,
+            TypeFilter::Dirs => TypeFilter::Symlinks,
+            TypeFilter::Symlinks => TypeFilter::Executables,
+            TypeFilter::Executables => TypeFilter::All,
+        }
+    }
+
+    fn matches(&self, entry: &FileEntry) -> bool {
+        match self {
+            TypeFilter::All => true,
+            TypeFilter::Files => entry.kind == EntryKind::File,
+            TypeFilter::Dirs => entry.kind == EntryKind::Dir,
+            TypeFilter::Symlinks => entry.kind == EntryKind::Symlink,
+            TypeFilter::Executables => entry.kind == EntryKind::File && entry.mode & 0o111 != 0,
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            TypeFilter::All => "all",
+            TypeFilter::Files => "files",
+            TypeFilter::Dirs => "dirs",
+            TypeFilter::Symlinks => "symlinks",
+            TypeFilter::Executables => "executables",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterScope {
+    Session,
+    Directory,
+}
+
+/// A parsed filter expression: space separated globs, `/regex/`,
+/// `size>N`/`size<N` and `age>N`/`age<N` terms. A name matches when it
+/// matches any of the globs and every regex.
+#[derive(Debug, Clone, Default)]
+pub struct Expression {
+    pub source: String,
+    globs: Option<GlobSet>,
+    regexes: Vec<Regex>,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    min_age: Option<Duration>,
+    max_age: Option<Duration>,
+}
+
+impl Expression {
+    pub fn parse(source: &str) -> Result<Expression, String> {
+        let mut expression = Expression {
+            source: source.trim().to_string(),
+            ..Default::default()
+        };
+        let mut globs = GlobSetBuilder::new();
+        let mut has_globs = false;
+
+        for token in source.split_whitespace() {
+            if let Some(pattern) = token
+                .strip_prefix('/')
+                .and_then(|rest| rest.strip_suffix('/'))
+            {
+                let regex = Regex::new(pattern).map_err(|err| err.to_string())?;
+                expression.regexes.push(regex);
+            } else if let Some(limit) = token.strip_prefix("size>") {
+                expression.min_size = Some(parse_size(limit)?);
+            } else if let Some(limit) = token.strip_prefix("size<") {
+                expression.max_size = Some(parse_size(limit)?);
+            } else if let Some(limit) = token.strip_prefix("age>") {
+                expression.min_age = Some(parse_age(limit)?);
+            } else if let Some(limit) = token.strip_prefix("age<") {
+                expression.max_age = Some(parse_age(limit)?);
+            } else {
+                globs.add(Glob::new(token).map_err(|err| err.to_string())?);
+                has_globs = true;
+            }
+        }
+
+        if has_globs {
+            expression.globs = Some(globs.build().map_err(|err| err.to_string())?);
+        }
+
+        Ok(expression)
+    }
+
+    fn matches(&self, entry: &FileEntry, now: SystemTime) -> bool {
+        let name = entry.name();
+
+        if let Some(globs) = &self.globs {
+            if !globs.is_match(&name) {
+                return false;
+            }
+        }
+        if !self.regexes.iter().all(|regex| regex.is_match(&name)) {
+            return false;
+        }
+        if self.min_size.is_some_and(|min| entry.size <= min)
+            || self.max_size.is_some_and(|max| entry.size >= max)
+        {
+            return false;
+        }
+
+        let age = entry
+            .modified
+            .and_then(|modified| now.duration_since(modified).ok())
+            .unwrap_or_default();
+        if self.min_age.is_some_and(|min| age <= min) || self.max_age.is_some_and(|max| age >= max)
+        {
+            return false;
+        }
+
+        true
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Filter {
+    pub show_hidden: bool,
+    pub types: TypeFilter,
+    pub expression: Expression,
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Filter {
+            show_hidden: true,
+            types: TypeFilter::All,
+            expression: Expression::default(),
+        }
+    }
+}
+
+impl Filter {
+    pub fn apply(&self, entries: &[FileEntry]) -> Vec<FileEntry> {
+        let now = SystemTime::now();
+        entries
+            .iter()
+            .filter(|entry| self.show_hidden || !entry.name().starts_with('.'))
+            .filter(|entry| self.types.matches(entry))
+            .filter(|entry| self.expression.matches(entry, now))
+            .cloned()
+            .collect()
+    }
+
+    pub fn title(&self) -> String {
+        let mut active = vec![];
+        if !self.show_hidden {
+            active.push("no dotfiles".to_string());
+        }
+        if self.types != TypeFilter::All {
+            active.push(format!("type:{}", self.types.label()));
+        }
+        if !self.expression.source.is_empty() {
+            active.push(self.expression.source.clone());
+        }
+        active.join(", ")
+    }
+}
+
+fn parse_size(text: &str) -> Result<u64, String> {
+    let (number, unit) = split_unit(text);
+    let multiplier = match unit.to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        "T" => 1 << 40,
+        other => return Err(format!("Unknown size unit {other:?}")),
+    };
+    number?
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("Size too large in {text:?}"))
+}
+
+fn parse_age(text: &str) -> Result<Duration, String> {
+    let (number, unit) = split_unit(text);
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "" | "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        other => return Err(format!("Unknown age unit {other:?}")),
+    };
+    number?
+        .checked_mul(seconds)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("Age too large in {text:?}"))
+}
+
+fn split_unit(text: &str) -> (Result<u64, String>, &str) {
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number = number
+        .parse()
+        .map_err(|_| format!("Invalid number in {text:?}"));
+    (number, unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_expression() {
+        let expression = Expression::parse("*.rs size>2K age<7d").unwrap();
+        assert!(expression.globs.is_some());
+        assert_eq!(expression.min_size, Some(2048));
+        assert_eq!(expression.max_age, Some(Duration::from_secs(7 * 86400)));
+
+        let expression = Expression::parse("/^main/ /rs$/").unwrap();
+        let now = SystemTime::now();
+        let entry = |name: &str| FileEntry::new(name.into(), EntryKind::File, 0, 0o644, None);
+        assert!(expression.matches(&entry("main.rs"), now));
+        assert!(!expression.matches(&entry("main.c"), now));
+        assert!(!expression.matches(&entry("lib.rs"), now));
+
+        assert!(Expression::parse("size>2X").is_err());
+        assert!(Expression::parse("/(/").is_err());
+        assert!(Expression::parse("size>99999999999T").is_err());
+        assert!(Expression::parse("age<999999999999999w").is_err());
+    }
+}
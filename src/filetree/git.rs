@@ -0,0 +1,298 @@
+use git2::{build::CheckoutBuilder, IndexAddOption, Repository, Status, StatusOptions};
+use ratatui::style::Color;
+
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use super::batch::BatchReport;
+
+/// The status of an entry as shown by `git status --short`: the state in the
+/// index and the state in the working tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct GitStatus {
+    pub index: char,
+    pub worktree: char,
+}
+
+impl GitStatus {
+    fn from_status(status: Status) -> Option<GitStatus> {
+        if status.is_conflicted() {
+            return Some(GitStatus {
+                index: 'U',
+                worktree: 'U',
+            });
+        }
+        if status.is_ignored() {
+            return Some(GitStatus {
+                index: '!',
+                worktree: '!',
+            });
+        }
+        if status.is_wt_new() {
+            return Some(GitStatus {
+                index: '?',
+                worktree: '?',
+            });
+        }
+
+        let index = if status.is_index_new() {
+            'A'
+        } else if status.is_index_modified() || status.is_index_typechange() {
+            'M'
+        } else if status.is_index_deleted() {
+            'D'
+        } else if status.is_index_renamed() {
+            'R'
+        } else {
+            ' '
+        };
+        let worktree = if status.is_wt_modified() || status.is_wt_typechange() {
+            'M'
+        } else if status.is_wt_deleted() {
+            'D'
+        } else if status.is_wt_renamed() {
+            'R'
+        } else {
+            ' '
+        };
+        (index != ' ' || worktree != ' ').then_some(GitStatus { index, worktree })
+    }
+
+    pub fn conflicted(&self) -> bool {
+        self.index == 'U'
+    }
+
+    pub fn untracked(&self) -> bool {
+        self.index == '?'
+    }
+
+    pub fn ignored(&self) -> bool {
+        self.index == '!'
+    }
+
+    pub fn color(&self) -> Color {
+        if self.conflicted() {
+            Color::Red
+        } else if self.ignored() {
+            Color::DarkGray
+        } else if self.untracked() {
+            Color::Magenta
+        } else if self.worktree != ' ' {
+            Color::Yellow
+        } else {
+            Color::Green
+        }
+    }
+
+    /// How much attention the status needs, to summarize a directory by its
+    /// most important child.
+    fn weight(&self) -> u8 {
+        if self.conflicted() {
+            4
+        } else if self.untracked() {
+            1
+        } else if self.ignored() {
+            0
+        } else if self.worktree != ' ' {
+            3
+        } else {
+            2
+        }
+    }
+}
+
+impl fmt::Display for GitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.index, self.worktree)
+    }
+}
+
+/// The repository the open directory belongs to, with the status of the
+/// entries below that directory.
+pub struct GitRepo {
+    repo: Repository,
+    pub workdir: PathBuf,
+    pub branch: String,
+    statuses: HashMap<PathBuf, GitStatus>,
+}
+
+impl fmt::Debug for GitRepo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GitRepo")
+            .field("workdir", &self.workdir)
+            .field("branch", &self.branch)
+            .field("statuses", &self.statuses.len())
+            .finish()
+    }
+}
+
+impl GitRepo {
+    /// Finds the repository `dir` is in, if any. Bare repositories have no
+    /// files to decorate and are skipped.
+    pub fn discover(dir: &Path) -> Option<GitRepo> {
+        let repo = Repository::discover(dir).ok()?;
+        let workdir = repo.workdir()?.canonicalize().ok()?;
+        Some(GitRepo {
+            repo,
+            workdir,
+            branch: String::new(),
+            statuses: HashMap::new(),
+        })
+    }
+
+    fn relative(&self, path: &Path) -> Option<PathBuf> {
+        let path = match path.parent().and_then(|parent| parent.canonicalize().ok()) {
+            Some(parent) => parent.join(path.file_name()?),
+            None => path.to_path_buf(),
+        };
+        path.strip_prefix(&self.workdir).ok().map(Path::to_path_buf)
+    }
+
+    /// Reads the branch and the status of everything below `dir`.
+    pub fn refresh(&mut self, dir: &Path) -> Result<(), git2::Error> {
+        self.branch = match self.repo.head() {
+            Ok(head) if head.is_branch() => head.shorthand().unwrap_or("HEAD").to_string(),
+            Ok(head) => match head.target() {
+                Some(oid) => format!("detached at {:.7}", oid.to_string()),
+                None => "HEAD".to_string(),
+            },
+            // A new repository has a branch without commits.
+            Err(_) => self
+                .repo
+                .find_reference("HEAD")
+                .ok()
+                .and_then(|head| head.symbolic_target().map(str::to_string))
+                .map(|target| target.trim_start_matches("refs/heads/").to_string())
+                .unwrap_or_else(|| "HEAD".to_string()),
+        };
+
+        let mut options = StatusOptions::new();
+        options
+            .include_untracked(true)
+            .include_ignored(true)
+            .recurse_untracked_dirs(false)
+            .recurse_ignored_dirs(false);
+        let canonical = dir.canonicalize().unwrap_or(dir.to_path_buf());
+        if let Ok(relative) = canonical.strip_prefix(&self.workdir) {
+            if !relative.as_os_str().is_empty() {
+                options.pathspec(relative);
+            }
+        }
+
+        self.statuses = self
+            .repo
+            .statuses(Some(&mut options))?
+            .iter()
+            .filter_map(|entry| {
+                let path = self.workdir.join(entry.path()?.trim_end_matches('/'));
+                Some((path, GitStatus::from_status(entry.status())?))
+            })
+            .collect();
+        Ok(())
+    }
+
+    /// The status of a file, or the most important status below a directory.
+    pub fn status_of(&self, path: &Path) -> Option<GitStatus> {
+        let path = self.workdir.join(self.relative(path)?);
+        if let Some(status) = self.statuses.get(&path) {
+            return Some(*status);
+        }
+        self.statuses
+            .iter()
+            .filter(|(child, status)| !status.ignored() && child.starts_with(&path))
+            .map(|(_, status)| *status)
+            .max_by_key(GitStatus::weight)
+    }
+
+    fn run(
+        &self,
+        op: &str,
+        paths: &[PathBuf],
+        action: impl Fn(&Path) -> Result<(), git2::Error>,
+    ) -> BatchReport {
+        let mut report = BatchReport {
+            op: op.to_string(),
+            succeeded: 0,
+            failures: vec![],
+        };
+        for path in paths {
+            let result = match self.relative(path) {
+                Some(relative) => action(&relative).map_err(|err| err.message().to_string()),
+                None => Err("not in the repository".to_string()),
+            };
+            match result {
+                Ok(()) => report.succeeded += 1,
+                Err(err) => report.failures.push((path.clone(), err)),
+            }
+        }
+        report
+    }
+
+    /// Adds the current content of files, including deletions, to the index.
+    pub fn stage(&self, paths: &[PathBuf]) -> BatchReport {
+        self.run("Stage", paths, |relative| {
+            let mut index = self.repo.index()?;
+            index.add_all([relative], IndexAddOption::DEFAULT, None)?;
+            index.update_all([relative], None)?;
+            index.write()
+        })
+    }
+
+    /// Resets the index entries of files to `HEAD`, keeping the working tree.
+    pub fn unstage(&self, paths: &[PathBuf]) -> BatchReport {
+        let head = self
+            .repo
+            .head()
+            .and_then(|head| head.peel(git2::ObjectType::Commit))
+            .ok();
+        self.run("Unstage", paths, |relative| {
+            match &head {
+                Some(head) => self.repo.reset_default(Some(head), [relative]),
+                // Without commits, unstaging removes the entries from the index.
+                None => {
+                    let mut index = self.repo.index()?;
+                    index.remove_all([relative], None)?;
+                    index.write()
+                }
+            }
+        })
+    }
+
+    /// Restores files in the working tree from the index, like `git restore`.
+    /// Untracked files are left alone, they can be deleted instead.
+    pub fn discard(&self, paths: &[PathBuf]) -> BatchReport {
+        self.run("Discard", paths, |relative| {
+            let status = self.statuses.get(&self.workdir.join(relative));
+            if status.is_some_and(GitStatus::untracked) {
+                return Err(git2::Error::from_str("untracked, delete it instead"));
+            }
+            let mut checkout = CheckoutBuilder::new();
+            checkout.force().path(relative);
+            self.repo.checkout_index(None, Some(&mut checkout))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_status() {
+        let short = |status| GitStatus::from_status(status).map(|git| git.to_string());
+        assert_eq!(
+            short(Status::INDEX_MODIFIED | Status::WT_MODIFIED).as_deref(),
+            Some("MM")
+        );
+        assert_eq!(short(Status::INDEX_NEW).as_deref(), Some("A "));
+        assert_eq!(short(Status::WT_NEW).as_deref(), Some("??"));
+        assert_eq!(
+            short(Status::CONFLICTED | Status::WT_MODIFIED).as_deref(),
+            Some("UU")
+        );
+        assert_eq!(short(Status::CURRENT), None);
+    }
+}
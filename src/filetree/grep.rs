@@ -0,0 +1,444 @@
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span, Text},
+    widgets::{Block, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+
+use ignore::WalkBuilder;
+use regex::{Regex, RegexBuilder};
+
+use std::{
+    collections::VecDeque,
+    fs,
+    io::{self, BufRead, BufReader},
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, TryRecvError},
+        Arc,
+    },
+    thread,
+};
+
+const CONTEXT_LINES: usize = 2;
+const BINARY_SNIFF_LEN: usize = 8192;
+const MAX_RESULTS: usize = 10_000;
+/// Longer lines are cut, so a huge file without newlines isn't read into
+/// memory whole.
+const MAX_LINE_LENGTH: usize = 64 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct GrepMatch {
+    pub path: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    pub text: String,
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+}
+
+#[derive(Debug)]
+enum GrepEvent {
+    Match(GrepMatch),
+    Done(usize),
+}
+
+#[derive(Debug)]
+struct GrepJob {
+    receiver: Receiver<GrepEvent>,
+    cancel: Arc<AtomicBool>,
+}
+
+#[derive(Debug)]
+pub struct GrepState {
+    pub query: String,
+    pub literal: bool,
+    pub results: Vec<GrepMatch>,
+    pub select_state: ListState,
+    status: String,
+    job: Option<GrepJob>,
+    /// The cached preview: a file, the range of lines shown and their text.
+    preview: Option<(PathBuf, Range<usize>, Vec<String>)>,
+}
+
+impl Default for GrepState {
+    fn default() -> Self {
+        GrepState {
+            query: String::new(),
+            literal: false,
+            results: vec![],
+            select_state: ListState::default(),
+            status: "Enter - search, Ctrl-l - literal/regex, Esc - cancel/close".to_string(),
+            job: None,
+            preview: None,
+        }
+    }
+}
+
+impl GrepState {
+    pub fn is_running(&self) -> bool {
+        self.job.is_some()
+    }
+
+    pub fn start(&mut self, root: &Path) {
+        if self.query.is_empty() {
+            return;
+        }
+        self.cancel();
+        self.results.clear();
+        self.select_state = ListState::default();
+        self.preview = None;
+
+        let pattern = if self.literal {
+            regex::escape(&self.query)
+        } else {
+            self.query.clone()
+        };
+
+        let regex = match RegexBuilder::new(&pattern).build() {
+            Ok(regex) => regex,
+            Err(err) => {
+                self.status = format!("Invalid pattern: {err}");
+                return;
+            }
+        };
+
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let worker_cancel = cancel.clone();
+        self.status = format!("Searching {} for {pattern:?}...", root.display());
+        let root = root.to_path_buf();
+
+        thread::spawn(move || {
+            let mut searched = 0;
+            for entry in WalkBuilder::new(&root).build() {
+                if worker_cancel.load(Ordering::Relaxed) {
+                    break;
+                }
+                let Ok(entry) = entry else { continue };
+                if !entry.file_type().is_some_and(|t| t.is_file()) {
+                    continue;
+                }
+                searched += 1;
+                for found in search_file(entry.path(), &regex, &worker_cancel) {
+                    if sender.send(GrepEvent::Match(found)).is_err() {
+                        return;
+                    }
+                }
+            }
+            let _ = sender.send(GrepEvent::Done(searched));
+        });
+
+        self.job = Some(GrepJob { receiver, cancel });
+    }
+
+    pub fn cancel(&mut self) {
+        if let Some(job) = self.job.take() {
+            job.cancel.store(true, Ordering::Relaxed);
+            self.status = format!("Cancelled after {} matches", self.results.len());
+        }
+    }
+
+    pub fn poll(&mut self) {
+        let Some(job) = &self.job else { return };
+
+        loop {
+            match job.receiver.try_recv() {
+                Ok(GrepEvent::Match(found)) => {
+                    if self.results.len() >= MAX_RESULTS {
+                        job.cancel.store(true, Ordering::Relaxed);
+                        self.status = format!("Stopped at {MAX_RESULTS} matches");
+                        self.job = None;
+                        return;
+                    }
+                    self.results.push(found);
+                    if self.select_state.selected().is_none() {
+                        self.select_state.select(Some(0));
+                    }
+                }
+                Ok(GrepEvent::Done(searched)) => {
+                    self.status = format!(
+                        "{} matches in {searched} searched files",
+                        self.results.len()
+                    );
+                    self.job = None;
+                    return;
+                }
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => {
+                    self.job = None;
+                    return;
+                }
+            }
+        }
+    }
+
+    pub fn select_next(&mut self) {
+        if !self.results.is_empty() {
+            self.select_state.select_next();
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        if !self.results.is_empty() {
+            self.select_state.select_previous();
+        }
+    }
+
+    pub fn selected(&self) -> Option<&GrepMatch> {
+        self.select_state
+            .selected()
+            .and_then(|index| self.results.get(index))
+    }
+
+    /// Up to `count` lines of `path` from the zero-based line `first`,
+    /// reading only as far into the file as needed.
+    fn preview_lines(&mut self, path: &Path, first: usize, count: usize) -> &[String] {
+        let range = first..first + count;
+        let stale =
+            !matches!(&self.preview, Some((cached, shown, _)) if cached == path && *shown == range);
+        if stale {
+            let mut lines = vec![];
+            if let Ok(file) = fs::File::open(path) {
+                let mut reader = BufReader::new(file);
+                let mut bytes = vec![];
+                let never = AtomicBool::new(false);
+                let mut index = 0;
+                while lines.len() < count
+                    && read_line(&mut reader, &mut bytes, &never).unwrap_or(false)
+                {
+                    if index >= first {
+                        lines.push(line_text(&bytes));
+                    }
+                    index += 1;
+                }
+            }
+            self.preview = Some((path.to_path_buf(), range, lines));
+        }
+        self.preview
+            .as_ref()
+            .map(|(_, _, lines)| lines.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+/// Reads the next line into `line` without its newline, keeping at most
+/// `MAX_LINE_LENGTH` bytes of it. Returns false at the end of the file and
+/// fails once `cancel` is set.
+fn read_line(
+    reader: &mut impl BufRead,
+    line: &mut Vec<u8>,
+    cancel: &AtomicBool,
+) -> io::Result<bool> {
+    line.clear();
+    let mut read_any = false;
+    loop {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled"));
+        }
+        let buffer = reader.fill_buf()?;
+        if buffer.is_empty() {
+            return Ok(read_any);
+        }
+        read_any = true;
+        let newline = buffer.iter().position(|byte| *byte == b'\n');
+        let end = newline.unwrap_or(buffer.len());
+        let room = MAX_LINE_LENGTH.saturating_sub(line.len());
+        line.extend_from_slice(&buffer[..end.min(room)]);
+        let consumed = newline.map_or(buffer.len(), |newline| newline + 1);
+        reader.consume(consumed);
+        if newline.is_some() {
+            return Ok(true);
+        }
+    }
+}
+
+fn line_text(bytes: &[u8]) -> String {
+    let text = String::from_utf8_lossy(bytes);
+    text.strip_suffix('\r').unwrap_or(&text).to_string()
+}
+
+/// Searches `path` line by line, skipping files with a NUL byte near the
+/// start as binary.
+fn search_file(path: &Path, regex: &Regex, cancel: &AtomicBool) -> Vec<GrepMatch> {
+    let Ok(file) = fs::File::open(path) else {
+        return vec![];
+    };
+    let mut reader = BufReader::with_capacity(BINARY_SNIFF_LEN, file);
+    match reader.fill_buf() {
+        Ok(head) if !head.contains(&0) => {}
+        _ => return vec![],
+    }
+
+    let mut matches: Vec<GrepMatch> = vec![];
+    let mut before = VecDeque::with_capacity(CONTEXT_LINES + 1);
+    let mut bytes = vec![];
+    let mut number = 0;
+    while matches.len() < MAX_RESULTS && read_line(&mut reader, &mut bytes, cancel).unwrap_or(false)
+    {
+        number += 1;
+        let line = line_text(&bytes);
+        for earlier in matches
+            .iter_mut()
+            .rev()
+            .take_while(|earlier| number - earlier.line <= CONTEXT_LINES)
+        {
+            earlier.after.push(line.clone());
+        }
+        if let Some(found) = regex.find(&line) {
+            matches.push(GrepMatch {
+                path: path.to_path_buf(),
+                line: number,
+                column: line[..found.start()].chars().count() + 1,
+                text: line.clone(),
+                before: before.iter().cloned().collect(),
+                after: vec![],
+            });
+        }
+        before.push_back(line);
+        if before.len() > CONTEXT_LINES {
+            before.pop_front();
+        }
+    }
+    matches
+}
+
+pub fn render(state: &mut GrepState, frame: &mut Frame, area: Rect, style: Style) {
+    let vertical = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Percentage(55),
+        Constraint::Min(3),
+    ]);
+    let [input_area, results_area, preview_area] = vertical.areas(area);
+
+    let mode = if state.literal { "literal" } else { "regex" };
+    let input = Paragraph::new(state.query.as_str())
+        .style(style)
+        .block(Block::bordered().title(format!("Grep ({mode})")));
+    frame.render_widget(input, input_area);
+
+    #[allow(clippy::cast_possible_truncation)]
+    frame.set_cursor_position((
+        input_area.x + state.query.chars().count() as u16 + 1,
+        input_area.y + 1,
+    ));
+
+    let items: Vec<ListItem> = state
+        .results
+        .iter()
+        .map(|found| {
+            let mut lines: Vec<Line> = found
+                .before
+                .iter()
+                .map(|l| Line::from(format!("  {l}")).dark_gray())
+                .collect();
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!("{}:{}:{}: ", found.path.display(), found.line, found.column),
+                    Style::default().fg(Color::Cyan),
+                ),
+                Span::raw(found.text.clone()),
+            ]));
+            lines.extend(
+                found
+                    .after
+                    .iter()
+                    .map(|l| Line::from(format!("  {l}")).dark_gray()),
+            );
+            ListItem::new(Text::from(lines))
+        })
+        .collect();
+
+    let spinner = if state.is_running() { " [running]" } else { "" };
+    let results = List::new(items)
+        .block(Block::bordered().title(format!("Results: {}{spinner}", state.status)))
+        .style(style)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(results, results_area, &mut state.select_state);
+
+    let Some(selected) = state.selected().cloned() else {
+        frame.render_widget(
+            Block::bordered().title("Preview").style(style),
+            preview_area,
+        );
+        return;
+    };
+
+    let height = preview_area.height.saturating_sub(2) as usize;
+    let first = selected.line.saturating_sub(height / 2 + 1);
+    let preview: Vec<Line> = state
+        .preview_lines(&selected.path, first, height)
+        .iter()
+        .zip(first..)
+        .map(|(text, index)| {
+            let line = Line::from(format!("{:>5} {text}", index + 1));
+            if index + 1 == selected.line {
+                line.style(Style::default().fg(Color::Black).bg(Color::Yellow))
+            } else {
+                line
+            }
+        })
+        .collect();
+
+    let preview = Paragraph::new(preview)
+        .style(style)
+        .block(Block::bordered().title(format!(
+            "Preview: {}:{}",
+            selected.path.display(),
+            selected.line
+        )));
+    frame.render_widget(preview, preview_area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, time::Duration};
+
+    fn search(state: &mut GrepState, root: &Path, query: &str, literal: bool) -> Vec<String> {
+        state.query = query.to_string();
+        state.literal = literal;
+        state.start(root);
+        while state.is_running() {
+            thread::sleep(Duration::from_millis(10));
+            state.poll();
+        }
+        let mut found: Vec<String> = state
+            .results
+            .iter()
+            .map(|found| {
+                let name = found.path.strip_prefix(root).unwrap().display();
+                format!("{name}:{}:{}", found.line, found.column)
+            })
+            .collect();
+        found.sort();
+        found
+    }
+
+    #[test]
+    fn test_search() {
+        let dir = env::temp_dir().join(format!("rustor-grep-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("skipped")).unwrap();
+        fs::write(dir.join("a.txt"), "one\ntwo fn(x)\nthree\r\nfour\n").unwrap();
+        fs::write(dir.join("binary"), b"fn(x)\0\n").unwrap();
+        fs::write(dir.join("skipped/b.txt"), "fn(x)\n").unwrap();
+        fs::write(dir.join(".ignore"), "skipped/\n").unwrap();
+
+        let mut state = GrepState::default();
+        assert_eq!(search(&mut state, &dir, "fn(x)", true), ["a.txt:2:5"]);
+        let found = &state.results[0];
+        assert_eq!(found.before, ["one"]);
+        assert_eq!(found.after, ["three", "four"]);
+
+        assert_eq!(
+            search(&mut state, &dir, "^t\\w+", false),
+            ["a.txt:2:1", "a.txt:3:1"]
+        );
+        assert!(search(&mut state, &dir, "fn(x)", false).is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,696 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use memmap2::Mmap;
+use ratatui::{
+    layout::{Constraint, Layout, Position, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Paragraph, Row, Table},
+    Frame,
+};
+use regex::bytes::RegexBuilder;
+
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    io::{self, Write},
+    os::unix::fs::FileExt,
+    path::{Path, PathBuf},
+};
+
+use super::listing::format_size;
+use super::pathinput::{expand, Edit, PathInput};
+
+const ROW_LENGTH: u64 = 16;
+/// How much is copied at once when saving to another file.
+const CHUNK_LENGTH: usize = 1 << 20;
+/// The most bytes of the selection the interpretation panel looks at.
+const INTERPRET_LENGTH: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prompt {
+    Goto,
+    Search,
+    SaveAs,
+}
+
+impl Prompt {
+    fn title(&self) -> &'static str {
+        match self {
+            Prompt::Goto => "Go to offset (0x1f00, 4096, +16, -0x10, 50%)",
+            Prompt::Search => "Search (hex bytes like 7f 45 4c 46, or \"text\")",
+            Prompt::SaveAs => "Save as",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Confirm {
+    Write,
+    Discard,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HexAction {
+    Move(i64),
+    PageDown,
+    PageUp,
+    Start,
+    End,
+    ToggleSelect,
+    OpenPrompt(Prompt),
+    EditPrompt(Edit),
+    AcceptPrompt,
+    CancelPrompt,
+    NextMatch,
+    ToggleEdit,
+    Nibble(u8),
+    Revert,
+    Write,
+    Close,
+    Confirm,
+    Cancel,
+}
+
+/// Parses an absolute offset in decimal or `0x` hex, an offset relative to
+/// `current` with a leading `+` or `-`, or a percentage of `len`.
+pub fn parse_offset(text: &str, current: u64, len: u64) -> Result<u64, String> {
+    let text = text.trim();
+    if let Some(percent) = text.strip_suffix('%') {
+        let percent: f64 = percent
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid percentage {percent}"))?;
+        return Ok((len as f64 * percent.clamp(0.0, 100.0) / 100.0) as u64);
+    }
+
+    let (sign, number) = match text.as_bytes().first() {
+        Some(b'+') => (Some(true), &text[1..]),
+        Some(b'-') => (Some(false), &text[1..]),
+        _ => (None, text),
+    };
+    let number = number.trim();
+    let value = match number
+        .strip_prefix("0x")
+        .or_else(|| number.strip_prefix("0X"))
+    {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => number.parse(),
+    }
+    .map_err(|_| format!("Invalid offset {number}"))?;
+
+    Ok(match sign {
+        Some(true) => current.saturating_add(value),
+        Some(false) => current.saturating_sub(value),
+        None => value,
+    })
+}
+
+/// Parses a search pattern: text in double quotes, hex bytes with optional
+/// spaces, or any other text as is.
+pub fn parse_pattern(text: &str) -> Option<Vec<u8>> {
+    if let Some(quoted) = text
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+    {
+        return Some(quoted.as_bytes().to_vec()).filter(|bytes| !bytes.is_empty());
+    }
+
+    let digits: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    if digits.is_empty() {
+        return None;
+    }
+    if digits.len().is_multiple_of(2) && digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return (0..digits.len())
+            .step_by(2)
+            .map(|index| u8::from_str_radix(&digits[index..index + 2], 16).ok())
+            .collect();
+    }
+    Some(text.as_bytes().to_vec())
+}
+
+/// Formats a float in scientific notation when plain digits would be too
+/// long to read.
+fn float<T: Copy + Into<f64> + fmt::Display + fmt::LowerExp>(value: T) -> String {
+    let magnitude = value.into().abs();
+    if magnitude == 0.0 || !magnitude.is_finite() || (1e-4..1e15).contains(&magnitude) {
+        value.to_string()
+    } else {
+        format!("{value:e}")
+    }
+}
+
+fn timestamp(seconds: i64) -> String {
+    chrono::DateTime::from_timestamp(seconds, 0)
+        .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// Reads the first bytes as the common integer, float and timestamp types,
+/// each as little and big endian.
+pub fn interpret(bytes: &[u8]) -> Vec<(&'static str, String, String)> {
+    let mut rows = vec![];
+    if let Some(byte) = bytes.first() {
+        rows.push(("u8", byte.to_string(), String::new()));
+        rows.push(("i8", (*byte as i8).to_string(), String::new()));
+        rows.push(("binary", format!("{byte:08b}"), String::new()));
+    }
+    if let Some(bytes) = bytes.first_chunk::<2>() {
+        let (le, be) = (u16::from_le_bytes(*bytes), u16::from_be_bytes(*bytes));
+        rows.push(("u16", le.to_string(), be.to_string()));
+        rows.push(("i16", (le as i16).to_string(), (be as i16).to_string()));
+    }
+    if let Some(bytes) = bytes.first_chunk::<4>() {
+        let (le, be) = (u32::from_le_bytes(*bytes), u32::from_be_bytes(*bytes));
+        rows.push(("u32", le.to_string(), be.to_string()));
+        rows.push(("i32", (le as i32).to_string(), (be as i32).to_string()));
+        rows.push(("f32", float(f32::from_bits(le)), float(f32::from_bits(be))));
+        rows.push(("time32", timestamp(le.into()), timestamp(be.into())));
+    }
+    if let Some(bytes) = bytes.first_chunk::<8>() {
+        let (le, be) = (u64::from_le_bytes(*bytes), u64::from_be_bytes(*bytes));
+        rows.push(("u64", le.to_string(), be.to_string()));
+        rows.push(("i64", (le as i64).to_string(), (be as i64).to_string()));
+        rows.push(("f64", float(f64::from_bits(le)), float(f64::from_bits(be))));
+        rows.push(("time64", timestamp(le as i64), timestamp(be as i64)));
+    }
+    rows
+}
+
+/// A hex and ASCII view of a memory-mapped file. Edits are kept aside until
+/// they are written back in place or saved to another file.
+#[derive(Debug)]
+pub struct HexView {
+    pub path: PathBuf,
+    map: Option<Mmap>,
+    len: u64,
+    edits: BTreeMap<u64, u8>,
+    cursor: u64,
+    /// Where the selection started, the cursor being its other end.
+    anchor: Option<u64>,
+    top_row: u64,
+    /// The rows that fit in the view, as of the last render.
+    page_rows: u64,
+    editable: bool,
+    editing: bool,
+    /// Whether the high nibble at the cursor was typed and the low one is
+    /// next.
+    low_nibble: bool,
+    prompt: Option<(Prompt, PathInput)>,
+    pattern: Option<Vec<u8>>,
+    confirm: Option<Confirm>,
+    status: Option<String>,
+}
+
+impl HexView {
+    pub fn open(path: &Path, editable: bool) -> io::Result<HexView> {
+        let file = fs::File::open(path)?;
+        let len = file.metadata()?.len();
+        // Empty files can't be mapped.
+        let map = match len {
+            0 => None,
+            // SAFETY: the map is only read. Another process truncating the
+            // file while it is open makes reads fault, as with any viewer
+            // based on mmap.
+            _ => Some(unsafe { Mmap::map(&file)? }),
+        };
+        Ok(HexView {
+            path: path.to_path_buf(),
+            map,
+            len,
+            edits: BTreeMap::new(),
+            cursor: 0,
+            anchor: None,
+            top_row: 0,
+            page_rows: 1,
+            editable,
+            editing: false,
+            low_nibble: false,
+            prompt: None,
+            pattern: None,
+            confirm: None,
+            status: None,
+        })
+    }
+
+    fn byte(&self, offset: u64) -> Option<u8> {
+        self.edits.get(&offset).copied().or_else(|| {
+            self.map
+                .as_ref()
+                .and_then(|map| map.get(offset as usize).copied())
+        })
+    }
+
+    /// The first and last offset of the selection, or the cursor.
+    fn selection(&self) -> (u64, u64) {
+        let anchor = self.anchor.unwrap_or(self.cursor);
+        (anchor.min(self.cursor), anchor.max(self.cursor))
+    }
+
+    fn selected_bytes(&self, limit: usize) -> Vec<u8> {
+        let (start, end) = self.selection();
+        (start..=end)
+            .take(limit)
+            .map_while(|offset| self.byte(offset))
+            .collect()
+    }
+
+    fn move_to(&mut self, offset: u64) {
+        self.cursor = offset.min(self.len.saturating_sub(1));
+        self.low_nibble = false;
+    }
+
+    fn move_by(&mut self, delta: i64) {
+        self.move_to(self.cursor.saturating_add_signed(delta));
+    }
+
+    /// The action bound to `key` in the current state of the view.
+    pub fn key_action(&self, key: KeyEvent) -> Option<HexAction> {
+        if self.confirm.is_some() {
+            return Some(match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => HexAction::Confirm,
+                _ => HexAction::Cancel,
+            });
+        }
+        if self.prompt.is_some() {
+            return match key.code {
+                KeyCode::Enter => Some(HexAction::AcceptPrompt),
+                KeyCode::Esc => Some(HexAction::CancelPrompt),
+                _ => Edit::from_key(key).map(HexAction::EditPrompt),
+            };
+        }
+
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        let action = match key.code {
+            KeyCode::Left => HexAction::Move(-1),
+            KeyCode::Right => HexAction::Move(1),
+            KeyCode::Down => HexAction::Move(ROW_LENGTH as i64),
+            KeyCode::Up => HexAction::Move(-(ROW_LENGTH as i64)),
+            KeyCode::PageDown => HexAction::PageDown,
+            KeyCode::PageUp => HexAction::PageUp,
+            KeyCode::Home => HexAction::Start,
+            KeyCode::End => HexAction::End,
+            KeyCode::Esc if self.editing => HexAction::ToggleEdit,
+            KeyCode::Backspace if self.editing => HexAction::Move(-1),
+            KeyCode::Char(c) if self.editing && c.is_ascii_hexdigit() => {
+                HexAction::Nibble(c.to_digit(16)? as u8)
+            }
+            _ if self.editing => return None,
+            KeyCode::Char('d') if control => HexAction::PageDown,
+            KeyCode::Char('u') if control => HexAction::PageUp,
+            KeyCode::Char('h') => HexAction::Move(-1),
+            KeyCode::Char('l') => HexAction::Move(1),
+            KeyCode::Char('j') => HexAction::Move(ROW_LENGTH as i64),
+            KeyCode::Char('k') => HexAction::Move(-(ROW_LENGTH as i64)),
+            KeyCode::Char('g') => HexAction::Start,
+            KeyCode::Char('G') => HexAction::End,
+            KeyCode::Char('v') => HexAction::ToggleSelect,
+            KeyCode::Char(':') => HexAction::OpenPrompt(Prompt::Goto),
+            KeyCode::Char('/') => HexAction::OpenPrompt(Prompt::Search),
+            KeyCode::Char('S') => HexAction::OpenPrompt(Prompt::SaveAs),
+            KeyCode::Char('n') => HexAction::NextMatch,
+            KeyCode::Char('i') => HexAction::ToggleEdit,
+            KeyCode::Char('u') => HexAction::Revert,
+            KeyCode::Char('w') => HexAction::Write,
+            KeyCode::Esc | KeyCode::Char('q') => HexAction::Close,
+            _ => return None,
+        };
+        Some(action)
+    }
+
+    /// Applies `action` and returns whether the view should be closed.
+    pub fn apply(&mut self, action: HexAction) -> bool {
+        if !matches!(action, HexAction::EditPrompt(_)) {
+            self.status = None;
+        }
+        let page = (self.page_rows * ROW_LENGTH) as i64;
+        match action {
+            HexAction::Move(delta) => self.move_by(delta),
+            HexAction::PageDown => self.move_by(page),
+            HexAction::PageUp => self.move_by(-page),
+            HexAction::Start => self.move_to(0),
+            HexAction::End => self.move_to(self.len),
+            HexAction::ToggleSelect => {
+                self.anchor = match self.anchor {
+                    Some(_) => None,
+                    None => Some(self.cursor),
+                }
+            }
+            HexAction::OpenPrompt(Prompt::SaveAs) if !self.editable => {
+                self.status = Some("The file is read-only".to_string());
+            }
+            HexAction::OpenPrompt(kind) => {
+                let mut input = PathInput::default();
+                if kind == Prompt::SaveAs {
+                    input.set(&self.path.display().to_string());
+                }
+                self.prompt = Some((kind, input));
+            }
+            HexAction::EditPrompt(edit) => {
+                if let Some((_, input)) = &mut self.prompt {
+                    input.edit(edit);
+                }
+            }
+            HexAction::AcceptPrompt => {
+                if let Some((kind, input)) = self.prompt.take() {
+                    self.accept(kind, &input.text);
+                }
+            }
+            HexAction::CancelPrompt => self.prompt = None,
+            HexAction::NextMatch => self.find_next(),
+            HexAction::ToggleEdit if !self.editable => {
+                self.status = Some("The file is read-only".to_string());
+            }
+            HexAction::ToggleEdit => {
+                self.editing = !self.editing;
+                self.low_nibble = false;
+            }
+            HexAction::Nibble(value) => self.type_nibble(value),
+            HexAction::Revert => {
+                let (start, end) = self.selection();
+                let reverted: Vec<u64> = self.edits.range(start..=end).map(|(k, _)| *k).collect();
+                reverted.iter().for_each(|offset| {
+                    self.edits.remove(offset);
+                });
+            }
+            HexAction::Write if self.edits.is_empty() => {
+                self.status = Some("No changes to write".to_string());
+            }
+            HexAction::Write => self.confirm = Some(Confirm::Write),
+            HexAction::Close if self.edits.is_empty() => return true,
+            HexAction::Close => self.confirm = Some(Confirm::Discard),
+            HexAction::Confirm => match self.confirm.take() {
+                Some(Confirm::Write) => {
+                    self.status = Some(match self.write_in_place() {
+                        Ok(count) => format!("Wrote {count} changed bytes"),
+                        Err(err) => format!("Could not write: {err}"),
+                    })
+                }
+                Some(Confirm::Discard) => return true,
+                None => {}
+            },
+            HexAction::Cancel => self.confirm = None,
+        }
+        false
+    }
+
+    fn accept(&mut self, kind: Prompt, text: &str) {
+        match kind {
+            Prompt::Goto => match parse_offset(text, self.cursor, self.len) {
+                Ok(offset) => {
+                    self.anchor = None;
+                    self.move_to(offset);
+                }
+                Err(err) => self.status = Some(err),
+            },
+            Prompt::Search => {
+                self.pattern = parse_pattern(text);
+                self.find_next();
+            }
+            Prompt::SaveAs => {
+                let dest = PathBuf::from(expand(text));
+                self.status = Some(match self.save_as(&dest) {
+                    Ok(()) => format!("Saved to {}", dest.display()),
+                    Err(err) => format!("Could not save to {}: {err}", dest.display()),
+                });
+            }
+        }
+    }
+
+    /// Selects the next match of the search pattern after the selection
+    /// start, wrapping around at the end. Unsaved edits aren't searched.
+    fn find_next(&mut self) {
+        let (Some(pattern), Some(map)) = (&self.pattern, &self.map) else {
+            return;
+        };
+        let expression: String = pattern
+            .iter()
+            .map(|byte| format!("\\x{byte:02x}"))
+            .collect();
+        let Ok(regex) = RegexBuilder::new(&expression).unicode(false).build() else {
+            return;
+        };
+
+        let from = (self.selection().0 + 1).min(self.len) as usize;
+        let (found, wrapped) = match regex.find_at(map, from) {
+            Some(found) => (Some(found), false),
+            None => (regex.find(map), true),
+        };
+        match found {
+            Some(found) => {
+                self.anchor = Some(found.start() as u64);
+                self.move_to(found.end() as u64 - 1);
+                if wrapped {
+                    self.status = Some("Search wrapped around".to_string());
+                }
+            }
+            None => self.status = Some("Pattern not found".to_string()),
+        }
+    }
+
+    fn type_nibble(&mut self, value: u8) {
+        let Some(old) = self.byte(self.cursor) else {
+            return;
+        };
+        let new = match self.low_nibble {
+            false => (value << 4) | (old & 0x0f),
+            true => (old & 0xf0) | value,
+        };
+        let original = self
+            .map
+            .as_ref()
+            .and_then(|map| map.get(self.cursor as usize).copied());
+        if original == Some(new) {
+            self.edits.remove(&self.cursor);
+        } else {
+            self.edits.insert(self.cursor, new);
+        }
+
+        if self.low_nibble {
+            self.move_by(1);
+        } else {
+            self.low_nibble = true;
+        }
+    }
+
+    /// Writes the changed bytes into the file itself. The map shares the
+    /// file's pages, so it shows them right away.
+    fn write_in_place(&mut self) -> io::Result<usize> {
+        let file = fs::OpenOptions::new().write(true).open(&self.path)?;
+        for (offset, byte) in &self.edits {
+            file.write_all_at(&[*byte], *offset)?;
+        }
+        file.sync_data()?;
+        Ok(std::mem::take(&mut self.edits).len())
+    }
+
+    /// Writes the file with the edits to `dest`, which must not exist yet,
+    /// and continues with `dest` as the file being edited.
+    fn save_as(&mut self, dest: &Path) -> io::Result<()> {
+        if dest == self.path {
+            return self.write_in_place().map(|_| ());
+        }
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(dest)?;
+        let content = self.map.as_deref().unwrap_or_default();
+        for (index, chunk) in content.chunks(CHUNK_LENGTH).enumerate() {
+            let start = (index * CHUNK_LENGTH) as u64;
+            let mut chunk = chunk.to_vec();
+            for (offset, byte) in self.edits.range(start..start + chunk.len() as u64) {
+                chunk[(offset - start) as usize] = *byte;
+            }
+            file.write_all(&chunk)?;
+        }
+        file.sync_all()?;
+
+        let (cursor, anchor) = (self.cursor, self.anchor);
+        *self = HexView::open(dest, self.editable)?;
+        self.cursor = cursor;
+        self.anchor = anchor;
+        Ok(())
+    }
+
+    /// Scrolls so the cursor row is visible in `rows` rows.
+    fn scroll_to_cursor(&mut self, rows: u64) {
+        self.page_rows = rows.max(1);
+        let row = self.cursor / ROW_LENGTH;
+        if row < self.top_row {
+            self.top_row = row;
+        } else if row >= self.top_row + self.page_rows {
+            self.top_row = row + 1 - self.page_rows;
+        }
+    }
+
+    fn status_text(&self) -> String {
+        if let Some(confirm) = self.confirm {
+            return match confirm {
+                Confirm::Write => format!(
+                    "Write {} changed bytes to {}? (y/n)",
+                    self.edits.len(),
+                    self.path.display()
+                ),
+                Confirm::Discard => {
+                    format!("Discard {} unsaved changes? (y/n)", self.edits.len())
+                }
+            };
+        }
+        if let Some(status) = &self.status {
+            return status.clone();
+        }
+        match self.editing {
+            true => "0-9a-f - overwrite, arrows - move, Esc - stop editing".to_string(),
+            false => "g/G - start/end, : - go to, / - search, n - next, v - select, i - edit, \
+                      u - revert, w - write, S - save as, q - close"
+                .to_string(),
+        }
+    }
+}
+
+fn render_row(view: &HexView, row: u64, offset_width: usize) -> Line<'static> {
+    let (start, end) = view.selection();
+    let selected = view.anchor.is_some();
+    let mut hex = vec![Span::raw(format!("{:0offset_width$x}  ", row * ROW_LENGTH))];
+    let mut ascii = vec![Span::raw(" ")];
+
+    for column in 0..ROW_LENGTH {
+        let offset = row * ROW_LENGTH + column;
+        let gap = if column == 7 { "  " } else { " " };
+        let Some(byte) = view.byte(offset) else {
+            hex.push(Span::raw(format!("  {gap}")));
+            continue;
+        };
+
+        let mut style = Style::default();
+        if view.edits.contains_key(&offset) {
+            style = style.fg(Color::Yellow);
+        }
+        if selected && (start..=end).contains(&offset) {
+            style = style.bg(Color::Blue);
+        }
+        if offset == view.cursor {
+            style = style.add_modifier(Modifier::REVERSED);
+        }
+        let character = match byte {
+            0x20..=0x7e => byte as char,
+            _ => '.',
+        };
+        hex.push(Span::styled(format!("{byte:02x}"), style));
+        hex.push(Span::raw(gap));
+        ascii.push(Span::styled(character.to_string(), style));
+    }
+    hex.extend(ascii);
+    Line::from(hex)
+}
+
+pub fn render(view: &mut HexView, frame: &mut Frame, area: Rect, style: Style) {
+    let [header_area, body_area] =
+        Layout::vertical([Constraint::Length(3), Constraint::Min(1)]).areas(area);
+
+    let (start, end) = view.selection();
+    let mut title = format!(
+        "{} ({}) at 0x{:x}",
+        view.path.display(),
+        format_size(view.len),
+        view.cursor
+    );
+    if view.anchor.is_some() {
+        title.push_str(&format!(", {} bytes selected", end - start + 1));
+    }
+    if !view.edits.is_empty() {
+        title.push_str(&format!(", {} changed", view.edits.len()));
+    }
+    if view.editing {
+        title.push_str(" [editing]");
+    } else if !view.editable {
+        title.push_str(" [read-only]");
+    }
+
+    match &view.prompt {
+        Some((kind, input)) => {
+            let header = Paragraph::new(input.text.as_str())
+                .style(Style::default().fg(Color::Yellow))
+                .block(Block::bordered().title(kind.title()));
+            frame.render_widget(header, header_area);
+            frame.set_cursor_position(Position::new(
+                header_area.x + input.cursor as u16 + 1,
+                header_area.y + 1,
+            ));
+        }
+        None => {
+            let header = Paragraph::new(view.status_text())
+                .style(style)
+                .block(Block::bordered().title(title));
+            frame.render_widget(header, header_area);
+        }
+    }
+
+    // The offset, two spaces, the hex bytes with a wider gap in the middle,
+    // the ASCII column and the borders.
+    let offset_width = format!("{:x}", view.len).len().max(8);
+    let hex_width = offset_width + 2 + 3 * ROW_LENGTH as usize + 2 + ROW_LENGTH as usize + 2;
+    let [hex_area, panel_area] =
+        Layout::horizontal([Constraint::Length(hex_width as u16), Constraint::Fill(1)])
+            .areas(body_area);
+
+    let rows = u64::from(hex_area.height.saturating_sub(2));
+    view.scroll_to_cursor(rows);
+    let lines: Vec<Line> = (view.top_row..view.top_row + rows)
+        .take_while(|row| row * ROW_LENGTH < view.len)
+        .map(|row| render_row(view, row, offset_width))
+        .collect();
+    frame.render_widget(
+        Paragraph::new(lines).style(style).block(Block::bordered()),
+        hex_area,
+    );
+
+    let rows: Vec<Row> = interpret(&view.selected_bytes(INTERPRET_LENGTH))
+        .into_iter()
+        .map(|(name, le, be)| Row::new([name.to_string(), le, be]))
+        .collect();
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(6),
+            Constraint::Fill(1),
+            Constraint::Fill(1),
+        ],
+    )
+    .header(Row::new(["", "LE", "BE"]).style(Style::default().add_modifier(Modifier::BOLD)))
+    .style(style)
+    .block(Block::bordered().title(format!("At 0x{start:x}")));
+    frame.render_widget(table, panel_area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse_offset("0x1f00", 0, 0), Ok(0x1f00));
+        assert_eq!(parse_offset("4096", 0, 0), Ok(4096));
+        assert_eq!(parse_offset("+0x10", 32, 0), Ok(48));
+        assert_eq!(parse_offset("-64", 32, 0), Ok(0));
+        assert_eq!(parse_offset("50%", 0, 1000), Ok(500));
+        assert!(parse_offset("0xzz", 0, 0).is_err());
+
+        assert_eq!(parse_pattern("7f 45 4c 46"), Some(b"\x7fELF".to_vec()));
+        assert_eq!(parse_pattern("\"cafe\""), Some(b"cafe".to_vec()));
+        assert_eq!(parse_pattern("ELF"), Some(b"ELF".to_vec()));
+        assert_eq!(parse_pattern("  "), None);
+    }
+
+    #[test]
+    fn test_interpret() {
+        let rows = interpret(&[0x00, 0x00, 0x80, 0x3f]);
+        let value = |name| rows.iter().find(|row| row.0 == name).cloned().unwrap();
+        assert_eq!(value("u8"), ("u8", "0".to_string(), String::new()));
+        assert_eq!(value("u16"), ("u16", "0".to_string(), "0".to_string()));
+        assert_eq!(value("f32").1, "1");
+        assert_eq!(value("f32").2, "4.6006e-41");
+        assert_eq!(value("u32").2, "32831");
+        assert_eq!(value("time32").2, "1970-01-01 09:07:11");
+        assert!(!rows.iter().any(|row| row.0 == "u64"));
+    }
+}
@@ -0,0 +1,539 @@
+use chrono::{DateTime, Local};
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style, Stylize},
+    text::Line,
+    widgets::{Block, Paragraph, Wrap},
+    Frame,
+};
+
+use std::{
+    ffi::CString,
+    fs, io,
+    os::unix::{
+        ffi::OsStrExt,
+        fs::{FileTypeExt, MetadataExt, OpenOptionsExt},
+    },
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use super::listing::symbolic_mode;
+use super::users::UserDb;
+
+const PREVIEW_LENGTH: usize = 48;
+
+/// Capability names by bit, as in `linux/capability.h`.
+const CAPABILITIES: [&str; 41] = [
+    "chown",
+    "dac_override",
+    "dac_read_search",
+    "fowner",
+    "fsetid",
+    "kill",
+    "setgid",
+    "setuid",
+    "setpcap",
+    "linux_immutable",
+    "net_bind_service",
+    "net_broadcast",
+    "net_admin",
+    "net_raw",
+    "ipc_lock",
+    "ipc_owner",
+    "sys_module",
+    "sys_rawio",
+    "sys_chroot",
+    "sys_ptrace",
+    "sys_pacct",
+    "sys_admin",
+    "sys_boot",
+    "sys_nice",
+    "sys_resource",
+    "sys_time",
+    "sys_tty_config",
+    "mknod",
+    "lease",
+    "audit_write",
+    "audit_control",
+    "setfcap",
+    "mac_override",
+    "mac_admin",
+    "syslog",
+    "wake_alarm",
+    "block_suspend",
+    "audit_read",
+    "perfmon",
+    "bpf",
+    "checkpoint_restore",
+];
+
+/// Inode flags in the order `lsattr` prints them.
+const FLAGS: [(u32, char, &str); 22] = [
+    (0x0000_0001, 's', "secure deletion"),
+    (0x0000_0002, 'u', "undeletable"),
+    (0x0000_0008, 'S', "synchronous updates"),
+    (0x0001_0000, 'D', "synchronous directory updates"),
+    (0x0000_0010, 'i', "immutable"),
+    (0x0000_0020, 'a', "append only"),
+    (0x0000_0040, 'd', "no dump"),
+    (0x0000_0080, 'A', "no atime updates"),
+    (0x0000_0004, 'c', "compressed"),
+    (0x0000_0800, 'E', "encrypted"),
+    (0x0000_4000, 'j', "journaled data"),
+    (0x0000_1000, 'I', "indexed directory"),
+    (0x0000_8000, 't', "no tail merging"),
+    (0x0002_0000, 'T', "top of directory hierarchy"),
+    (0x0008_0000, 'e', "extents"),
+    (0x0080_0000, 'C', "no copy on write"),
+    (0x0200_0000, 'x', "DAX"),
+    (0x4000_0000, 'F', "casefolded"),
+    (0x1000_0000, 'N', "inline data"),
+    (0x2000_0000, 'P', "project hierarchy"),
+    (0x0010_0000, 'V', "verity"),
+    (0x0000_0400, 'm', "don't compress"),
+];
+
+/// The flags that stop even root from changing a file.
+const PROTECTING_FLAGS: u32 = 0x10 | 0x20;
+
+/// Everything known about one file: its `stat`, extended attributes and
+/// the security information kept in them.
+#[derive(Debug, Clone)]
+pub struct Details {
+    pub path: PathBuf,
+    lines: Vec<Line<'static>>,
+}
+
+impl Details {
+    pub fn read(path: &Path, users: &UserDb) -> Details {
+        let lines = match describe(path, users) {
+            Ok(lines) => lines,
+            Err(err) => vec![Line::from(format!("Could not inspect: {err}")).fg(Color::Red)],
+        };
+        Details {
+            path: path.to_path_buf(),
+            lines,
+        }
+    }
+}
+
+fn c_path(path: &Path) -> io::Result<CString> {
+    Ok(CString::new(path.as_os_str().as_bytes())?)
+}
+
+/// The names of the extended attributes of `path`, without following a
+/// final symlink.
+fn xattr_names(path: &Path) -> io::Result<Vec<String>> {
+    let path = c_path(path)?;
+    loop {
+        // SAFETY: a null buffer of size 0 only asks for the needed size.
+        let size = unsafe { libc::llistxattr(path.as_ptr(), std::ptr::null_mut(), 0) };
+        if size < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut buffer = vec![0u8; size as usize];
+        // SAFETY: the buffer is as long as the size passed.
+        let read =
+            unsafe { libc::llistxattr(path.as_ptr(), buffer.as_mut_ptr().cast(), buffer.len()) };
+        // The list grew in between, ask again.
+        if read < 0 && io::Error::last_os_error().raw_os_error() == Some(libc::ERANGE) {
+            continue;
+        }
+        if read < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        buffer.truncate(read as usize);
+        return Ok(buffer
+            .split(|byte| *byte == 0)
+            .filter(|name| !name.is_empty())
+            .map(|name| String::from_utf8_lossy(name).to_string())
+            .collect());
+    }
+}
+
+/// The value of the extended attribute `name` of `path`, without following
+/// a final symlink.
+pub fn xattr(path: &Path, name: &str) -> io::Result<Vec<u8>> {
+    let path = c_path(path)?;
+    let name = CString::new(name)?;
+    loop {
+        // SAFETY: a null buffer of size 0 only asks for the needed size.
+        let size =
+            unsafe { libc::lgetxattr(path.as_ptr(), name.as_ptr(), std::ptr::null_mut(), 0) };
+        if size < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut buffer = vec![0u8; size as usize];
+        // SAFETY: the buffer is as long as the size passed.
+        let read = unsafe {
+            libc::lgetxattr(
+                path.as_ptr(),
+                name.as_ptr(),
+                buffer.as_mut_ptr().cast(),
+                buffer.len(),
+            )
+        };
+        if read < 0 && io::Error::last_os_error().raw_os_error() == Some(libc::ERANGE) {
+            continue;
+        }
+        if read < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        buffer.truncate(read as usize);
+        return Ok(buffer);
+    }
+}
+
+/// The inode flags `chattr` sets. Only regular files and directories are
+/// opened for this, opening devices or FIFOs could have side effects.
+fn inode_flags(path: &Path) -> io::Result<u32> {
+    use std::os::fd::AsRawFd;
+    let file = fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK | libc::O_NOFOLLOW)
+        .open(path)?;
+    let mut flags: libc::c_int = 0;
+    // SAFETY: FS_IOC_GETFLAGS writes an int to the pointer.
+    if unsafe { libc::ioctl(file.as_raw_fd(), libc::FS_IOC_GETFLAGS, &mut flags) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(flags as u32)
+}
+
+/// Formats flags like `lsattr`, followed by the names of those set.
+pub fn format_flags(flags: u32) -> String {
+    let letters: String = FLAGS
+        .iter()
+        .map(|(bit, letter, _)| if flags & bit != 0 { *letter } else { '-' })
+        .collect();
+    let names: Vec<&str> = FLAGS
+        .iter()
+        .filter(|(bit, _, _)| flags & bit != 0)
+        .map(|(_, _, name)| *name)
+        .collect();
+    if names.is_empty() {
+        letters
+    } else {
+        format!("{letters} ({})", names.join(", "))
+    }
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+/// Decodes a `system.posix_acl_access` or `system.posix_acl_default` value
+/// into `getfacl` style entries.
+pub fn decode_acl(value: &[u8], users: &UserDb) -> Option<Vec<String>> {
+    if u32_at(value, 0)? != 2 {
+        return None;
+    }
+    value[4..]
+        .chunks(8)
+        .map(|entry| {
+            let tag = u16::from_le_bytes(entry.get(0..2)?.try_into().ok()?);
+            let perm = u16::from_le_bytes(entry.get(2..4)?.try_into().ok()?);
+            let id = u32_at(entry, 4)?;
+            let qualifier = match tag {
+                0x01 => "user::".to_string(),
+                0x02 => format!("user:{}:", users.user_name(id)),
+                0x04 => "group::".to_string(),
+                0x08 => format!("group:{}:", users.group_name(id)),
+                0x10 => "mask::".to_string(),
+                0x20 => "other::".to_string(),
+                _ => return None,
+            };
+            let bit = |mask: u16, c: char| if perm & mask != 0 { c } else { '-' };
+            Some(format!(
+                "{qualifier}{}{}{}",
+                bit(4, 'r'),
+                bit(2, 'w'),
+                bit(1, 'x')
+            ))
+        })
+        .collect()
+}
+
+/// Decodes `security.capability` like `getcap`, grouping the capabilities
+/// by the sets they are in: `cap_net_bind_service,cap_net_raw=ep`.
+pub fn decode_capability(value: &[u8]) -> Option<String> {
+    let magic = u32_at(value, 0)?;
+    let words = match magic & 0xff00_0000 {
+        0x0100_0000 => 1,
+        0x0200_0000 | 0x0300_0000 => 2,
+        _ => return None,
+    };
+    let effective = magic & 1 != 0;
+
+    let mut permitted = 0u64;
+    let mut inheritable = 0u64;
+    for word in 0..words {
+        permitted |= u64::from(u32_at(value, 4 + word * 8)?) << (32 * word);
+        inheritable |= u64::from(u32_at(value, 8 + word * 8)?) << (32 * word);
+    }
+
+    // Capabilities with the same sets are listed together, in bit order.
+    let mut groups: Vec<(String, Vec<String>)> = vec![];
+    for bit in 0..64 {
+        let (p, i) = (permitted >> bit & 1 != 0, inheritable >> bit & 1 != 0);
+        if !p && !i {
+            continue;
+        }
+        let sets = format!(
+            "{}{}{}",
+            if p && effective { "e" } else { "" },
+            if i { "i" } else { "" },
+            if p { "p" } else { "" }
+        );
+        let name = match CAPABILITIES.get(bit) {
+            Some(name) => format!("cap_{name}"),
+            None => format!("cap_{bit}"),
+        };
+        match groups.iter_mut().find(|(group, _)| *group == sets) {
+            Some((_, names)) => names.push(name),
+            None => groups.push((sets, vec![name])),
+        }
+    }
+
+    let mut text = groups
+        .iter()
+        .map(|(sets, names)| format!("{}={sets}", names.join(",")))
+        .collect::<Vec<_>>()
+        .join(" ");
+    if magic & 0xff00_0000 == 0x0300_0000 {
+        text.push_str(&format!(" [rootid={}]", u32_at(value, 20)?));
+    }
+    Some(text)
+}
+
+/// Shows text values quoted and anything else as hex.
+fn preview(value: &[u8]) -> String {
+    let trimmed = value.strip_suffix(&[0]).unwrap_or(value);
+    match std::str::from_utf8(trimmed) {
+        Ok(text) if !text.chars().any(char::is_control) => {
+            let short: String = text.chars().take(PREVIEW_LENGTH).collect();
+            let ellipsis = if short.len() < text.len() { "…" } else { "" };
+            format!("\"{short}{ellipsis}\"")
+        }
+        _ => {
+            let hex: String = value
+                .iter()
+                .take(PREVIEW_LENGTH / 2)
+                .map(|byte| format!("{byte:02x}"))
+                .collect();
+            let ellipsis = if value.len() > PREVIEW_LENGTH / 2 {
+                "…"
+            } else {
+                ""
+            };
+            format!("0x{hex}{ellipsis} ({} bytes)", value.len())
+        }
+    }
+}
+
+fn format_time(time: Option<SystemTime>) -> String {
+    time.map(|time| {
+        DateTime::<Local>::from(time)
+            .format("%Y-%m-%d %H:%M:%S%.9f %z")
+            .to_string()
+    })
+    .unwrap_or_else(|| "-".to_string())
+}
+
+fn file_type(metadata: &fs::Metadata) -> &'static str {
+    let file_type = metadata.file_type();
+    if file_type.is_symlink() {
+        "symbolic link"
+    } else if file_type.is_dir() {
+        "directory"
+    } else if file_type.is_block_device() {
+        "block special file"
+    } else if file_type.is_char_device() {
+        "character special file"
+    } else if file_type.is_fifo() {
+        "fifo"
+    } else if file_type.is_socket() {
+        "socket"
+    } else if metadata.len() == 0 {
+        "regular empty file"
+    } else {
+        "regular file"
+    }
+}
+
+/// The lines of the detail pane, laid out like `stat`, `lsattr`, `getfacl`
+/// and `getcap` would print them.
+fn describe(path: &Path, users: &UserDb) -> io::Result<Vec<Line<'static>>> {
+    let metadata = fs::symlink_metadata(path)?;
+    let name = path.display().to_string();
+    let mut lines = vec![];
+
+    let file = match fs::read_link(path) {
+        Ok(target) => format!("  File: {name} -> {}", target.display()),
+        Err(_) => format!("  File: {name}"),
+    };
+    lines.push(Line::from(file));
+    lines.push(Line::from(format!(
+        "  Size: {:<10} Blocks: {:<8} IO Block: {:<6} {}",
+        metadata.len(),
+        metadata.blocks(),
+        metadata.blksize(),
+        file_type(&metadata)
+    )));
+    let device = metadata.dev();
+    lines.push(Line::from(format!(
+        "Device: {},{:<8} Inode: {:<10} Links: {}",
+        libc::major(device),
+        libc::minor(device),
+        metadata.ino(),
+        metadata.nlink()
+    )));
+    let mode = Line::from(format!(
+        "Access: ({:04o}/{})  Uid: ({}/{})  Gid: ({}/{})",
+        metadata.mode() & 0o7777,
+        symbolic_mode(metadata.mode()),
+        metadata.uid(),
+        users.user_name(metadata.uid()),
+        metadata.gid(),
+        users.group_name(metadata.gid())
+    ));
+    // Set-id bits are worth a look in a review.
+    lines.push(match metadata.mode() & 0o6000 {
+        0 => mode,
+        _ => mode.fg(Color::Yellow),
+    });
+    let change = SystemTime::UNIX_EPOCH.checked_add(Duration::new(
+        metadata.ctime() as u64,
+        metadata.ctime_nsec() as u32,
+    ));
+    lines.push(Line::from(format!(
+        "Access: {}",
+        format_time(metadata.accessed().ok())
+    )));
+    lines.push(Line::from(format!(
+        "Modify: {}",
+        format_time(metadata.modified().ok())
+    )));
+    lines.push(Line::from(format!("Change: {}", format_time(change))));
+    lines.push(Line::from(format!(
+        " Birth: {}",
+        format_time(metadata.created().ok())
+    )));
+
+    lines.push(Line::default());
+    if metadata.is_file() || metadata.is_dir() {
+        let attributes = match inode_flags(path) {
+            Ok(flags) => {
+                let line = Line::from(format!("Attributes: {}", format_flags(flags)));
+                match flags & PROTECTING_FLAGS {
+                    0 => line,
+                    _ => line.fg(Color::Yellow),
+                }
+            }
+            Err(err) => Line::from(format!("Attributes: unavailable ({err})")),
+        };
+        lines.push(attributes);
+    }
+
+    let names = xattr_names(path).unwrap_or_default();
+    let value = |name: &str| {
+        names
+            .iter()
+            .any(|xattr_name| xattr_name == name)
+            .then(|| xattr(path, name).ok())
+            .flatten()
+    };
+
+    if let Some(label) = value("security.selinux") {
+        let label = String::from_utf8_lossy(label.strip_suffix(&[0]).unwrap_or(&label));
+        lines.push(Line::from(format!("SELinux: {label}")));
+    }
+
+    if let Some(capability) = value("security.capability") {
+        let text = decode_capability(&capability).unwrap_or_else(|| preview(&capability));
+        lines.push(Line::from(format!("Capabilities: {text}")).fg(Color::Yellow));
+    }
+
+    for (xattr_name, prefix) in [
+        ("system.posix_acl_access", ""),
+        ("system.posix_acl_default", "default:"),
+    ] {
+        let Some(acl) = value(xattr_name) else {
+            continue;
+        };
+        if !lines.iter().any(|line| line.to_string() == "ACL:") {
+            lines.push(Line::from("ACL:").bold());
+        }
+        match decode_acl(&acl, users) {
+            Some(entries) => lines.extend(
+                entries
+                    .into_iter()
+                    .map(|entry| Line::from(format!("  {prefix}{entry}"))),
+            ),
+            None => lines.push(Line::from(format!("  {prefix}{}", preview(&acl)))),
+        }
+    }
+
+    lines.push(Line::default());
+    lines.push(Line::from(format!("Extended attributes ({}):", names.len())).bold());
+    for xattr_name in &names {
+        let text = match xattr(path, xattr_name) {
+            Ok(value) => preview(&value),
+            Err(err) => format!("unreadable ({err})"),
+        };
+        lines.push(Line::from(format!("  {xattr_name} = {text}")));
+    }
+
+    Ok(lines)
+}
+
+pub fn render(details: &Details, frame: &mut Frame, area: Rect, style: Style) {
+    let name = details
+        .path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| details.path.display().to_string());
+    let pane = Paragraph::new(details.lines.clone())
+        .style(style)
+        .wrap(Wrap { trim: false })
+        .block(Block::bordered().title(format!("Details: {name} (i - close)")));
+    frame.render_widget(pane, area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_security_xattrs() {
+        // cap_net_raw and cap_net_bind_service, permitted and effective.
+        let mut capability = vec![];
+        for word in [0x0200_0001u32, 1 << 13 | 1 << 10, 0, 0, 0] {
+            capability.extend(word.to_le_bytes());
+        }
+        assert_eq!(
+            decode_capability(&capability).as_deref(),
+            Some("cap_net_bind_service,cap_net_raw=ep")
+        );
+
+        let mut acl = 2u32.to_le_bytes().to_vec();
+        for (tag, perm, id) in [(0x01u16, 6u16, 0u32), (0x02, 5, 1001), (0x10, 5, 0)] {
+            acl.extend(tag.to_le_bytes());
+            acl.extend(perm.to_le_bytes());
+            acl.extend(id.to_le_bytes());
+        }
+        let users = UserDb::default();
+        assert_eq!(
+            decode_acl(&acl, &users).unwrap(),
+            ["user::rw-", "user:1001:r-x", "mask::r-x"]
+        );
+
+        assert_eq!(
+            format_flags(0x10 | 0x0008_0000),
+            "----i---------e------- (immutable, extents)"
+        );
+    }
+}
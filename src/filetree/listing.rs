@@ -0,0 +1,468 @@
+use ratatui::{
+    layout::Constraint,
+    style::{Color, Style},
+    widgets::{Cell, Row},
+};
+
+use chrono::{DateTime, Local};
+
+use std::{cmp::Ordering, fs, os::unix::fs::MetadataExt, path::PathBuf, time::SystemTime};
+
+use super::git::GitStatus;
+use super::users::UserDb;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    File,
+    Dir,
+    Symlink,
+    Other,
+}
+
+#[derive(Debug, Clone)]
+pub struct FileEntry {
+    pub path: PathBuf,
+    pub kind: EntryKind,
+    pub size: u64,
+    pub disk: u64,
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub inode: u64,
+    pub links: u64,
+    pub modified: Option<SystemTime>,
+    pub accessed: Option<SystemTime>,
+    pub created: Option<SystemTime>,
+    pub link_target: Option<PathBuf>,
+    /// What a symlink resolves to, `None` for other entries and broken links.
+    pub target_kind: Option<EntryKind>,
+    pub git: Option<GitStatus>,
+}
+
+impl EntryKind {
+    fn from_file_type(file_type: fs::FileType) -> EntryKind {
+        if file_type.is_symlink() {
+            EntryKind::Symlink
+        } else if file_type.is_dir() {
+            EntryKind::Dir
+        } else if file_type.is_file() {
+            EntryKind::File
+        } else {
+            EntryKind::Other
+        }
+    }
+}
+
+impl FileEntry {
+    /// Builds an entry from metadata that doesn't follow symlinks, resolving
+    /// the target of links separately.
+    pub fn from_metadata(path: PathBuf, metadata: &fs::Metadata) -> FileEntry {
+        let kind = EntryKind::from_file_type(metadata.file_type());
+        let (link_target, target_kind) = match kind {
+            EntryKind::Symlink => (
+                fs::read_link(&path).ok(),
+                fs::metadata(&path)
+                    .ok()
+                    .map(|target| EntryKind::from_file_type(target.file_type())),
+            ),
+            _ => (None, None),
+        };
+
+        FileEntry {
+            path,
+            kind,
+            size: metadata.len(),
+            disk: metadata.blocks() * 512,
+            mode: metadata.mode(),
+            uid: metadata.uid(),
+            gid: metadata.gid(),
+            inode: metadata.ino(),
+            links: metadata.nlink(),
+            modified: metadata.modified().ok(),
+            accessed: metadata.accessed().ok(),
+            created: metadata.created().ok(),
+            link_target,
+            target_kind,
+            git: None,
+        }
+    }
+
+    /// An entry that doesn't exist on disk, like an archive member.
+    pub fn new(
+        path: PathBuf,
+        kind: EntryKind,
+        size: u64,
+        mode: u32,
+        modified: Option<SystemTime>,
+    ) -> FileEntry {
+        let file_type = match kind {
+            EntryKind::Dir => 0o040000,
+            EntryKind::Symlink => 0o120000,
+            EntryKind::File => 0o100000,
+            EntryKind::Other => 0,
+        };
+
+        FileEntry {
+            path,
+            kind,
+            size,
+            disk: size,
+            mode: file_type | mode & 0o7777,
+            uid: 0,
+            gid: 0,
+            inode: 0,
+            links: 1,
+            modified,
+            accessed: None,
+            created: None,
+            link_target: None,
+            target_kind: None,
+            git: None,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.path.display().to_string())
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.kind == EntryKind::Dir
+    }
+
+    /// Whether the entry is a directory or a symlink to one.
+    pub fn points_to_dir(&self) -> bool {
+        self.is_dir() || self.target_kind == Some(EntryKind::Dir)
+    }
+
+    pub fn is_broken_link(&self) -> bool {
+        self.kind == EntryKind::Symlink && self.target_kind.is_none()
+    }
+
+    fn icon(&self) -> &'static str {
+        match self.kind {
+            EntryKind::File => "\u{f15c}",
+            EntryKind::Dir => "\u{e5fe}",
+            EntryKind::Symlink => "\u{f0337}",
+            EntryKind::Other => " ",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Git,
+    Name,
+    Size,
+    Usage,
+    Modified,
+    Accessed,
+    Created,
+    Owner,
+    Group,
+    Octal,
+    Permissions,
+    Inode,
+    Links,
+    Target,
+}
+
+impl Column {
+    pub const ALL: [Column; 14] = [
+        Column::Git,
+        Column::Name,
+        Column::Permissions,
+        Column::Octal,
+        Column::Size,
+        Column::Usage,
+        Column::Modified,
+        Column::Accessed,
+        Column::Created,
+        Column::Owner,
+        Column::Group,
+        Column::Inode,
+        Column::Links,
+        Column::Target,
+    ];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Column::Git => "Git",
+            Column::Name => "Name",
+            Column::Size => "Size",
+            Column::Usage => "Disk",
+            Column::Modified => "Modified",
+            Column::Accessed => "Accessed",
+            Column::Created => "Created",
+            Column::Owner => "Owner",
+            Column::Group => "Group",
+            Column::Octal => "Octal",
+            Column::Permissions => "Mode",
+            Column::Inode => "Inode",
+            Column::Links => "Links",
+            Column::Target => "Target",
+        }
+    }
+
+    fn width(&self) -> Constraint {
+        match self {
+            Column::Name | Column::Target => Constraint::Min(20),
+            Column::Size | Column::Usage => Constraint::Length(7),
+            Column::Modified | Column::Accessed | Column::Created => Constraint::Length(16),
+            Column::Owner | Column::Group => Constraint::Length(10),
+            Column::Octal => Constraint::Length(6),
+            Column::Permissions => Constraint::Length(10),
+            Column::Inode => Constraint::Length(10),
+            Column::Links => Constraint::Length(5),
+            Column::Git => Constraint::Length(3),
+        }
+    }
+
+    fn cell(&self, entry: &FileEntry, users: &UserDb) -> String {
+        match self {
+            Column::Git => entry.git.map(|git| git.to_string()).unwrap_or_default(),
+            Column::Name => match &entry.link_target {
+                Some(target) => {
+                    format!("{} {} -> {}", entry.icon(), entry.name(), target.display())
+                }
+                None => format!("{} {}", entry.icon(), entry.name()),
+            },
+            Column::Size => format_size(entry.size),
+            Column::Usage => format_size(entry.disk),
+            Column::Modified => format_time(entry.modified),
+            Column::Accessed => format_time(entry.accessed),
+            Column::Created => format_time(entry.created),
+            Column::Owner => users.user_name(entry.uid),
+            Column::Group => users.group_name(entry.gid),
+            Column::Octal => format!("{:04o}", entry.mode & 0o7777),
+            Column::Permissions => symbolic_mode(entry.mode),
+            Column::Inode => entry.inode.to_string(),
+            Column::Links => entry.links.to_string(),
+            Column::Target => entry
+                .link_target
+                .as_ref()
+                .map(|target| target.display().to_string())
+                .unwrap_or_default(),
+        }
+    }
+
+    fn compare(&self, a: &FileEntry, b: &FileEntry, users: &UserDb) -> Ordering {
+        match self {
+            Column::Git => a.git.cmp(&b.git),
+            Column::Name => a.name().cmp(&b.name()),
+            Column::Size => a.size.cmp(&b.size),
+            Column::Usage => a.disk.cmp(&b.disk),
+            Column::Modified => a.modified.cmp(&b.modified),
+            Column::Accessed => a.accessed.cmp(&b.accessed),
+            Column::Created => a.created.cmp(&b.created),
+            Column::Owner => users.user_name(a.uid).cmp(&users.user_name(b.uid)),
+            Column::Group => users.group_name(a.gid).cmp(&users.group_name(b.gid)),
+            Column::Octal | Column::Permissions => (a.mode & 0o7777).cmp(&(b.mode & 0o7777)),
+            Column::Inode => a.inode.cmp(&b.inode),
+            Column::Links => a.links.cmp(&b.links),
+            Column::Target => a.link_target.cmp(&b.link_target),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ListingConfig {
+    pub columns: Vec<Column>,
+    pub sort_column: Column,
+    pub descending: bool,
+    pub dirs_first: bool,
+}
+
+impl Default for ListingConfig {
+    fn default() -> Self {
+        ListingConfig {
+            columns: vec![
+                Column::Git,
+                Column::Name,
+                Column::Permissions,
+                Column::Size,
+                Column::Modified,
+            ],
+            sort_column: Column::Name,
+            descending: false,
+            dirs_first: true,
+        }
+    }
+}
+
+impl ListingConfig {
+    pub fn toggle_column(&mut self, column: Column) {
+        if self.columns.contains(&column) {
+            if self.columns.len() > 1 {
+                self.columns.retain(|c| *c != column);
+            }
+        } else {
+            self.columns = Column::ALL
+                .into_iter()
+                .filter(|c| *c == column || self.columns.contains(c))
+                .collect();
+        }
+
+        if !self.columns.contains(&self.sort_column) {
+            self.sort_column = self.columns[0];
+        }
+    }
+
+    /// Moves sorting to the next visible column.
+    pub fn next_sort_column(&mut self) {
+        let position = self
+            .columns
+            .iter()
+            .position(|c| *c == self.sort_column)
+            .map_or(0, |index| (index + 1) % self.columns.len());
+        self.sort_column = self.columns[position];
+    }
+
+    pub fn sort(&self, entries: &mut [FileEntry], users: &UserDb) {
+        entries.sort_by(|a, b| {
+            let group = if self.dirs_first {
+                b.points_to_dir().cmp(&a.points_to_dir())
+            } else {
+                Ordering::Equal
+            };
+            let order = self.sort_column.compare(a, b, users);
+            let order = if self.descending {
+                order.reverse()
+            } else {
+                order
+            };
+            group.then(order)
+        });
+    }
+
+    pub fn title(&self) -> String {
+        let direction = if self.descending { "desc" } else { "asc" };
+        let grouping = if self.dirs_first { ", dirs first" } else { "" };
+        format!(
+            "sorted by {} {direction}{grouping}",
+            self.sort_column.title()
+        )
+    }
+
+    pub fn widths(&self) -> Vec<Constraint> {
+        let mut widths = vec![Constraint::Length(4)];
+        widths.extend(self.columns.iter().map(Column::width));
+        widths
+    }
+
+    pub fn header(&self) -> Row<'static> {
+        let mut cells = vec![Cell::from("#")];
+        cells.extend(self.columns.iter().map(|column| {
+            if *column == self.sort_column {
+                let arrow = if self.descending { '↓' } else { '↑' };
+                Cell::from(format!("{}{arrow}", column.title()))
+            } else {
+                Cell::from(column.title())
+            }
+        }));
+        Row::new(cells)
+    }
+
+    pub fn row(&self, num: usize, entry: &FileEntry, marked: bool, users: &UserDb) -> Row<'static> {
+        let marker = if marked { '*' } else { ':' };
+        let mut cells = vec![Cell::from(format!("{num:>3}{marker}"))];
+        cells.extend(self.columns.iter().map(|column| {
+            let cell = Cell::from(column.cell(entry, users));
+            match (column, entry.git) {
+                (Column::Git, Some(git)) => cell.style(Style::default().fg(git.color())),
+                _ => cell,
+            }
+        }));
+        let row = Row::new(cells);
+        if entry.is_broken_link() {
+            row.style(Style::default().fg(Color::Red))
+        } else {
+            row
+        }
+    }
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["B", "K", "M", "G", "T", "P"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes}{}", UNITS[0])
+    } else if size < 10.0 {
+        format!("{size:.1}{}", UNITS[unit])
+    } else {
+        format!("{size:.0}{}", UNITS[unit])
+    }
+}
+
+fn format_time(time: Option<SystemTime>) -> String {
+    time.map(|time| {
+        DateTime::<Local>::from(time)
+            .format("%Y-%m-%d %H:%M")
+            .to_string()
+    })
+    .unwrap_or_else(|| "-".to_string())
+}
+
+pub fn symbolic_mode(mode: u32) -> String {
+    let special = |bit: u32, exec: bool, set: char, unset: char| match (mode & bit != 0, exec) {
+        (true, true) => set,
+        (true, false) => unset,
+        (false, true) => 'x',
+        (false, false) => '-',
+    };
+
+    let kind = match mode & 0o170000 {
+        0o040000 => 'd',
+        0o120000 => 'l',
+        0o020000 => 'c',
+        0o060000 => 'b',
+        0o010000 => 'p',
+        0o140000 => 's',
+        _ => '-',
+    };
+
+    let bit = |mask: u32, c: char| if mode & mask != 0 { c } else { '-' };
+
+    [
+        kind,
+        bit(0o400, 'r'),
+        bit(0o200, 'w'),
+        special(0o4000, mode & 0o100 != 0, 's', 'S'),
+        bit(0o040, 'r'),
+        bit(0o020, 'w'),
+        special(0o2000, mode & 0o010 != 0, 's', 'S'),
+        bit(0o004, 'r'),
+        bit(0o002, 'w'),
+        special(0o1000, mode & 0o001 != 0, 't', 'T'),
+    ]
+    .iter()
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512B");
+        assert_eq!(format_size(1536), "1.5K");
+        assert_eq!(format_size(20 * 1024 * 1024), "20M");
+    }
+
+    #[test]
+    fn test_symbolic_mode() {
+        assert_eq!(symbolic_mode(0o100644), "-rw-r--r--");
+        assert_eq!(symbolic_mode(0o041777), "drwxrwxrwt");
+        assert_eq!(symbolic_mode(0o104644), "-rwSr--r--");
+    }
+}
@@ -0,0 +1,2409 @@
+use ratatui::{
+    layout::{Constraint, Layout, Position, Rect},
+    style::{Color, Style, Stylize},
+    widgets::{Block, Clear, List, ListState, Paragraph, Row, Table, TableState},
+    Frame,
+};
+
+use log::{error, info};
+use std::collections::{HashMap, HashSet};
+use std::ffi::{CString, OsString};
+use std::io::Write;
+use std::os::unix::{
+    ffi::OsStringExt,
+    fs::{MetadataExt, OpenOptionsExt},
+};
+use std::path::PathBuf;
+use std::process::{Command, ExitStatus};
+use std::{env, fmt, fs, io};
+use std::{fmt::Debug, path};
+
+use crate::app::{App, AppInfo, AppMessage};
+use crate::components::{optionlist::OptionListState, OptionList};
+
+use crossterm::event::KeyCode;
+use crossterm::event::{self, KeyModifiers};
+
+mod archive;
+mod audit;
+mod batch;
+mod checksum;
+mod diff;
+mod diskusage;
+mod duplicates;
+mod elf;
+mod filter;
+mod git;
+mod grep;
+mod hexview;
+mod inspect;
+mod listing;
+mod mounts;
+mod navigation;
+mod opener;
+mod pathinput;
+mod permissions;
+mod rename;
+mod transfer;
+mod treemap;
+mod users;
+mod vfs;
+mod watcher;
+
+use archive::ArchiveFormat;
+use audit::AuditView;
+use batch::{BatchOp, BatchPrompt, BatchReport};
+use checksum::{Algorithm, ChecksumView};
+use diff::{DiffView, Side};
+use diskusage::{DuNode, DuView, SizeScan};
+use duplicates::{DupAction, DupScan, DupView};
+use elf::ElfView;
+use filter::{Expression, Filter, FilterScope};
+use git::GitRepo;
+use grep::GrepState;
+use hexview::{HexAction, HexView};
+use inspect::Details;
+use listing::{Column, FileEntry, ListingConfig};
+use mounts::MountView;
+use navigation::{Bookmarks, Frecency, History};
+use opener::Openers;
+use pathinput::{Edit, PathInput};
+use permissions::{EditorKind, PermissionsEditor};
+use rename::{Pattern, Renames};
+use transfer::{Transfer, TransferQueue};
+use users::UserDb;
+use vfs::{FileSystem, LocalFs};
+use watcher::{Changes, DirWatcher};
+
+const PREVIEW_LIMIT: u64 = 64 * 1024;
+
+#[derive(Debug)]
+pub struct FileTreeApp {
+    info: AppInfo,
+    text: std::string::String,
+    input: PathInput,
+    open_path: String,
+    entries: Vec<FileEntry>,
+    all_entries: Vec<FileEntry>,
+    input_mode: InputMode,
+    select_state: TableState,
+    confirm_action: ConfirmAction,
+    grep: GrepState,
+    listing: ListingConfig,
+    column_picker: OptionListState,
+    users: UserDb,
+    filter_scope: FilterScope,
+    session_filter: Filter,
+    dir_filters: HashMap<String, Filter>,
+    filter_input: String,
+    filter_error: Option<String>,
+    marked: HashSet<PathBuf>,
+    visual_anchor: Option<usize>,
+    prompt: Option<(PromptKind, String)>,
+    prompt_error: Option<String>,
+    batch_report: Option<BatchReport>,
+    bookmarks: Bookmarks,
+    history: History,
+    /// The directory open when typing a path started, which is recorded in
+    /// the history once the typed path is accepted.
+    typed_from: String,
+    frecency: Frecency,
+    picker_items: Vec<(String, String)>,
+    picker_state: ListState,
+    size_scan: Option<SizeScan>,
+    du_tree: Option<DuNode>,
+    du_view: DuView,
+    auto_sizes: bool,
+    watcher: Option<DirWatcher>,
+    permissions: Option<PermissionsEditor>,
+    vfs: Box<dyn FileSystem>,
+    preview: Option<(String, String)>,
+    openers: Openers,
+    command: Option<Command>,
+    /// The entries being renamed in `$EDITOR` and the file holding their names.
+    pending_rename: Option<(Vec<PathBuf>, PathBuf)>,
+    dup_scan: Option<DupScan>,
+    dup_view: DupView,
+    mounts: MountView,
+    /// The last security audit, kept after jumping to a finding.
+    audit: Option<AuditView>,
+    /// The detail pane of the highlighted entry, when open.
+    details: Option<Details>,
+    checksums: Option<ChecksumView>,
+    diff: Option<DiffView>,
+    hex: Option<HexView>,
+    elf: Option<ElfView>,
+    git: Option<GitRepo>,
+    /// Whether copy and delete act on what symlinks point to.
+    follow_links: bool,
+    /// The safety mode that refuses every change to the filesystem.
+    read_only: bool,
+    transfers: TransferQueue,
+    /// The private directory for files handed to other programs, created
+    /// on first use.
+    temp_dir: Option<PathBuf>,
+}
+
+pub enum FileTreeMsg {
+    EditInput(Edit),
+    CompletePath,
+    CompletePathBack,
+    OpenPath,
+    CursorDown,
+    CursorUp,
+    CreateFile,
+    CreateDir,
+    Delete,
+    Rename,
+    Move,
+    Copy,
+    Confirm,
+    Cancel,
+    OpenGrep,
+    CloseGrep,
+    GrepChar(char),
+    GrepDeleteChar,
+    GrepRun,
+    GrepToggleLiteral,
+    GrepNext,
+    GrepPrev,
+    NextSortColumn,
+    ReverseSort,
+    ToggleDirsFirst,
+    OpenColumnPicker,
+    CloseColumnPicker,
+    ColumnPickerDown,
+    ColumnPickerUp,
+    ToggleColumn,
+    ToggleHidden,
+    CycleTypeFilter,
+    ToggleFilterScope,
+    OpenFilter,
+    FilterChar(char),
+    FilterDeleteChar,
+    ApplyFilter,
+    CloseFilter,
+    ToggleMark,
+    ToggleVisual,
+    ClearMarks,
+    OpenChmod,
+    OpenChown,
+    PermissionsDown,
+    PermissionsUp,
+    PermissionsToggleBit,
+    PermissionsDigit(char),
+    PermissionsDeleteDigit,
+    PermissionsSwitchList,
+    PermissionsToggleRecursive,
+    PermissionsApply,
+    PermissionsClose,
+    Archive,
+    PromptChar(char),
+    PromptDeleteChar,
+    SubmitPrompt,
+    CancelPrompt,
+    DismissReport,
+    Extract,
+    Preview,
+    DismissPreview,
+    OpenHex,
+    Hex(HexAction),
+    OpenElf,
+    CloseElf,
+    ElfDown,
+    ElfUp,
+    ElfSwitchTab(bool),
+    OpenWith,
+    BulkRename,
+    PatternRename,
+    OpenHighlighted,
+    ParentDir,
+    HistoryBack,
+    HistoryForward,
+    AddBookmark,
+    OpenBookmarks,
+    OpenRecent,
+    PickerDown,
+    PickerUp,
+    PickerOpen,
+    PickerDelete,
+    ClosePicker,
+    ToggleAutoSizes,
+    OpenDiskUsage,
+    CloseDiskUsage,
+    DuDown,
+    DuUp,
+    DuEnter,
+    DuLeave,
+    DuDelete,
+    DuConfirmDelete,
+    DuCancelDelete,
+    DuToggleMode,
+    DuToggleTreemap,
+    DuToggleColors,
+    OpenDuplicates,
+    CloseDuplicates,
+    ToggleDetails,
+    OpenMounts,
+    CloseMounts,
+    MountDown,
+    MountUp,
+    MountOpen,
+    MountToggleAll,
+    MountRefresh,
+    OpenAudit,
+    CloseAudit,
+    AuditDown,
+    AuditUp,
+    AuditJump,
+    AuditExport,
+    DupDown,
+    DupUp,
+    DupToggleMark,
+    DupMarkCopies,
+    DupAsk(DupAction),
+    DupApply,
+    DupCancel,
+    OpenChecksums,
+    VerifyChecksums,
+    CloseChecksums,
+    ChecksumDown,
+    ChecksumUp,
+    ChecksumNextAlgorithm,
+    ChecksumCopy,
+    ChecksumCopyAll,
+    ChecksumWrite,
+    OpenDiff,
+    CloseDiff,
+    DiffDown,
+    DiffUp,
+    DiffNextHunk,
+    DiffPrevHunk,
+    DiffToggleSplit,
+    DiffOpen,
+    DiffCopy(Side),
+    DiffConfirm,
+    DiffCancel,
+    GitStage,
+    GitUnstage,
+    GitDiscard,
+    Symlink,
+    Hardlink,
+    ToggleFollowLinks,
+    ToggleReadOnly,
+    PauseTransfer,
+    CancelTransfer,
+    NoneMsg,
+}
+
+#[derive(Debug, Clone)]
+pub enum ConfirmAction {
+    Batch(BatchOp, Vec<FileEntry>),
+    Rename(Renames),
+    GitDiscard(Vec<FileEntry>),
+    None,
+}
+
+impl fmt::Display for ConfirmAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfirmAction::Batch(op, targets) => write!(f, "{op} ({} items)", targets.len()),
+            ConfirmAction::Rename(renames) => write!(f, "Rename {} items", renames.len()),
+            ConfirmAction::GitDiscard(targets) => write!(f, "Discard {} items", targets.len()),
+            ConfirmAction::None => write!(f, "None"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum PromptKind {
+    Batch(BatchPrompt),
+    Bookmark,
+    OpenWith,
+    Rename,
+    PatternRename,
+}
+
+impl PromptKind {
+    fn title(&self) -> &'static str {
+        match self {
+            PromptKind::Batch(batch) => batch.title(),
+            PromptKind::Bookmark => "Bookmark name",
+            PromptKind::OpenWith => "Open with command ({} - file)",
+            PromptKind::Rename => "New name",
+            PromptKind::PatternRename => {
+                "Rename pattern (s/regex/replacement/, lower, upper, {name}_{n:3}.{ext})"
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PickerKind {
+    Bookmarks,
+    Recent,
+}
+
+#[derive(Debug, Clone)]
+enum InputMode {
+    Search,
+    Modify,
+    Grep,
+    Columns,
+    Filter,
+    Prompt,
+    Picker(PickerKind),
+    DiskUsage,
+    Duplicates,
+    Checksums,
+    Diff,
+    Mounts,
+    Audit,
+    Hex,
+    Elf,
+    Permissions,
+}
+
+impl AppMessage for FileTreeMsg {}
+
+impl App for FileTreeApp {
+    type Msg = FileTreeMsg;
+    fn view(&mut self, layout: &Layout, frame: &mut Frame, style: Style) {
+        let app_area = layout.split(frame.area())[1];
+
+        if let InputMode::Grep = self.input_mode {
+            grep::render(&mut self.grep, frame, app_area, style);
+            return;
+        }
+
+        if let InputMode::DiskUsage = self.input_mode {
+            diskusage::render(
+                &mut self.du_view,
+                self.du_tree.as_ref(),
+                self.size_scan.as_ref(),
+                frame,
+                app_area,
+                style,
+            );
+            return;
+        }
+
+        if let InputMode::Mounts = self.input_mode {
+            mounts::render(&mut self.mounts, frame, app_area, style);
+            return;
+        }
+
+        if let (InputMode::Audit, Some(view)) = (&self.input_mode, &mut self.audit) {
+            audit::render(view, frame, app_area, style);
+            return;
+        }
+
+        if let InputMode::Duplicates = self.input_mode {
+            duplicates::render(
+                &mut self.dup_view,
+                self.dup_scan.as_ref(),
+                frame,
+                app_area,
+                style,
+            );
+            return;
+        }
+
+        if let (InputMode::Checksums, Some(view)) = (&self.input_mode, &mut self.checksums) {
+            checksum::render(view, frame, app_area, style);
+            return;
+        }
+
+        if let (InputMode::Diff, Some(view)) = (&self.input_mode, &mut self.diff) {
+            diff::render(view, frame, app_area, style);
+            return;
+        }
+
+        if let (InputMode::Hex, Some(view)) = (&self.input_mode, &mut self.hex) {
+            hexview::render(view, frame, app_area, style);
+            return;
+        }
+
+        if let (InputMode::Elf, Some(view)) = (&self.input_mode, &mut self.elf) {
+            elf::render(view, frame, app_area, style);
+            return;
+        }
+
+        let vertical = Layout::vertical([Constraint::Length(3), Constraint::Min(1)]);
+
+        let [input_area, path_area] = vertical.areas(app_area);
+
+        let mut input_style = style;
+        let mut path_style = style;
+
+        match self.input_mode {
+            InputMode::Modify
+            | InputMode::Columns
+            | InputMode::Picker(_)
+            | InputMode::Permissions => {
+                input_style = Style::default().fg(Color::White);
+            }
+            InputMode::Search | InputMode::Filter | InputMode::Prompt => {
+                path_style = Style::default().fg(Color::White);
+            }
+            InputMode::Grep
+            | InputMode::DiskUsage
+            | InputMode::Duplicates
+            | InputMode::Checksums
+            | InputMode::Diff
+            | InputMode::Mounts
+            | InputMode::Audit
+            | InputMode::Hex
+            | InputMode::Elf => {}
+        }
+
+        let (input_text, input_title, cursor) = match (&self.input_mode, &self.prompt) {
+            (InputMode::Filter, _) => {
+                let title = match &self.filter_error {
+                    Some(err) => format!("Filter error: {err}"),
+                    None => "Filter (*.glob, /regex/, size>1M, age<7d)".to_string(),
+                };
+                let cursor = self.filter_input.chars().count();
+                (self.filter_input.as_str(), title, cursor)
+            }
+            (InputMode::Prompt, Some((kind, text))) => {
+                let title = match &self.prompt_error {
+                    Some(err) => format!("{}: {err}", kind.title()),
+                    None => kind.title().to_string(),
+                };
+                (text.as_str(), title, text.chars().count())
+            }
+            _ => (
+                self.input.text.as_str(),
+                "Enter path".to_string(),
+                self.input.cursor,
+            ),
+        };
+
+        let input = Paragraph::new(input_text)
+            .style(input_style)
+            .block(Block::bordered().title(input_title));
+
+        frame.render_widget(input, input_area);
+
+        #[allow(clippy::cast_possible_truncation)]
+        frame.set_cursor_position(Position::new(
+            input_area.x + cursor as u16 + 1,
+            input_area.y + 1,
+        ));
+
+        let mut rows: Vec<Row> = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let marked = self.is_marked(i);
+                let row = self.listing.row(i, entry, marked, &self.users);
+                if marked {
+                    row.style(Style::default().fg(Color::Yellow))
+                } else {
+                    row
+                }
+            })
+            .collect();
+        rows.push(Row::new(vec![format!("{:>3}: ", self.entries.len())]));
+
+        let filters = self.active_filter().title();
+        let scope = match self.filter_scope {
+            FilterScope::Session => "session",
+            FilterScope::Directory => "directory",
+        };
+        let filter_title = if filters.is_empty() {
+            format!("no filters [{scope}]")
+        } else {
+            format!("filters: {filters} [{scope}]")
+        };
+        let mark_title = match (self.marked.len(), self.visual_anchor) {
+            (_, Some(_)) => " [visual]".to_string(),
+            (0, None) => String::new(),
+            (count, None) => format!(" [{count} marked]"),
+        };
+        let scan_title = match &self.size_scan {
+            Some(scan) => format!(" [{}]", scan.progress()),
+            None => String::new(),
+        };
+        let git_title = match &self.git {
+            Some(git) => format!(" [git: {}]", git.branch),
+            None => String::new(),
+        };
+        let links_title = if self.follow_links {
+            " [follow links]"
+        } else {
+            ""
+        };
+        let read_only_title = if self.read_only { " [read-only]" } else { "" };
+        let mut block = Block::bordered().title(format!(
+            "Directory Contents ({}; {filter_title}){mark_title}{scan_title}{git_title}{links_title}{read_only_title}:",
+            self.listing.title()
+        ));
+
+        if let InputMode::Modify = self.input_mode {
+            match &self.confirm_action {
+                ConfirmAction::Batch(op, targets) => {
+                    block = block.title_bottom(batch::summary(op, targets, self.follow_links));
+                }
+                ConfirmAction::GitDiscard(targets) => {
+                    block = block
+                        .title_bottom(format!("Discard changes to {} items? (Y/N)", targets.len()));
+                }
+                _ => {}
+            }
+        }
+
+        // The detail pane follows the highlighted entry.
+        let highlighted = self.highlighted_entry().map(|entry| entry.path.clone());
+        let table_area = match (&self.details, highlighted) {
+            (Some(details), Some(path)) => {
+                if details.path != path {
+                    self.details = Some(Details::read(&path, &self.users));
+                }
+                let [table_area, details_area] =
+                    Layout::horizontal([Constraint::Percentage(55), Constraint::Percentage(45)])
+                        .areas(path_area);
+                if let Some(details) = &self.details {
+                    inspect::render(details, frame, details_area, style);
+                }
+                table_area
+            }
+            _ => path_area,
+        };
+
+        let table = Table::new(rows, self.listing.widths())
+            .header(self.listing.header().bold())
+            .block(block)
+            .style(path_style)
+            .highlight_style(Style::default().bg(Color::LightGreen).fg(Color::White));
+        frame.render_stateful_widget(table, table_area, &mut self.select_state);
+
+        if let InputMode::Search = self.input_mode {
+            pathinput::render_completion(&mut self.input, frame, input_area, style);
+        }
+
+        if let InputMode::Columns = self.input_mode {
+            let picker = OptionList::new(
+                Column::ALL.iter().map(|c| c.title().to_string()).collect(),
+                "[*]".to_string(),
+                "[ ]".to_string(),
+                "Columns (space - toggle, Esc - close)".to_string(),
+                Style::default().bg(Color::Green).fg(Color::White),
+                style,
+            );
+            let [_, picker_area] =
+                Layout::horizontal([Constraint::Min(0), Constraint::Length(30)]).areas(path_area);
+            let [picker_area, _] = Layout::vertical([
+                Constraint::Length(Column::ALL.len() as u16 + 2),
+                Constraint::Min(0),
+            ])
+            .areas(picker_area);
+            frame.render_widget(Clear, picker_area);
+            frame.render_stateful_widget(picker, picker_area, &mut self.column_picker);
+        }
+
+        if let ConfirmAction::Rename(renames) = &self.confirm_action {
+            let lines: Vec<String> = renames
+                .iter()
+                .map(|(source, dest)| {
+                    let name = |path: &PathBuf| {
+                        path.file_name()
+                            .unwrap_or_default()
+                            .to_string_lossy()
+                            .to_string()
+                    };
+                    format!("{} -> {}", name(source), name(dest))
+                })
+                .collect();
+            let [_, preview_area, _] = Layout::vertical([
+                Constraint::Min(0),
+                Constraint::Length(lines.len().min(20) as u16 + 2),
+                Constraint::Min(0),
+            ])
+            .areas(path_area);
+            let preview =
+                Paragraph::new(lines.join("\n"))
+                    .style(style)
+                    .block(Block::bordered().title(format!(
+                        "Rename {} items? (y - apply, n - cancel)",
+                        renames.len()
+                    )));
+            frame.render_widget(Clear, preview_area);
+            frame.render_widget(preview, preview_area);
+        }
+
+        self.transfers.render(frame, path_area, style);
+
+        if let Some(report) = &self.batch_report {
+            let lines = report.lines();
+            let [_, report_area, _] = Layout::vertical([
+                Constraint::Min(0),
+                Constraint::Length(lines.len().min(15) as u16 + 2),
+                Constraint::Min(0),
+            ])
+            .areas(path_area);
+            let report_box = Paragraph::new(lines.join("\n"))
+                .style(Style::default().fg(Color::Red))
+                .block(Block::bordered().title("Batch report (any key - close)"));
+            frame.render_widget(Clear, report_area);
+            frame.render_widget(report_box, report_area);
+        }
+
+        if let InputMode::Picker(kind) = self.input_mode {
+            self.render_picker(kind, frame, path_area, style);
+        }
+
+        if let Some((name, text)) = &self.preview {
+            let preview = Paragraph::new(text.as_str())
+                .style(style)
+                .block(Block::bordered().title(format!("{name} (any key - close)")));
+            frame.render_widget(Clear, path_area);
+            frame.render_widget(preview, path_area);
+        }
+
+        if let Some(editor) = &mut self.permissions {
+            permissions::render(editor, &self.users, frame, path_area, style);
+        }
+    }
+
+    fn update(&mut self, msg: &Self::Msg) {
+        match msg {
+            FileTreeMsg::OpenPath => match self.input_mode {
+                // Enter first picks the highlighted completion.
+                InputMode::Search if self.input.accept_completion() => {}
+                InputMode::Search => {
+                    self.input.push_history();
+                    let previous = std::mem::take(&mut self.typed_from);
+                    self.record_visit(previous);
+                    self.input_mode = InputMode::Modify
+                }
+                _ => {
+                    self.typed_from = self.open_path.clone();
+                    self.input_mode = InputMode::Search
+                }
+            },
+            FileTreeMsg::EditInput(edit) => {
+                let changed = self.input.edit(*edit);
+                if changed {
+                    self.read_path(self.input.expanded());
+                }
+            }
+            FileTreeMsg::CompletePath | FileTreeMsg::CompletePathBack => {
+                let forward = matches!(msg, FileTreeMsg::CompletePath);
+                let base = PathBuf::from(&self.open_path);
+                if self.input.complete(&base, forward) {
+                    self.read_path(self.input.expanded());
+                }
+            }
+            FileTreeMsg::CursorDown => self.select_state.select_next(),
+            FileTreeMsg::CursorUp => self.select_state.select_previous(),
+            FileTreeMsg::CreateFile if self.writable() => {
+                let filepath = self.input.expanded();
+                let result = self.vfs.create_file(path::Path::new(&filepath));
+
+                match result {
+                    Ok(()) => {
+                        info!("Created file {filepath}");
+                        self.read_path(self.open_path.clone());
+                    }
+                    Err(err) => error!("Could not create file: {err} at {filepath}"),
+                }
+            }
+            FileTreeMsg::CreateDir if self.writable() => {
+                let dirpath = self.input.expanded();
+                let result = self.vfs.create_dir(path::Path::new(&dirpath));
+
+                match result {
+                    Ok(()) => {
+                        info!("Created directory {}", dirpath);
+                        self.read_path(self.open_path.clone());
+                    }
+                    Err(err) => error!("Could not create directory: {err} at {dirpath}"),
+                }
+            }
+            FileTreeMsg::Delete => {
+                let targets = self.targets();
+                if !targets.is_empty() && self.writable() {
+                    self.confirm_action = ConfirmAction::Batch(BatchOp::Delete, targets)
+                }
+            }
+            FileTreeMsg::Copy if self.local_writable() => {
+                self.open_prompt(PromptKind::Batch(BatchPrompt::Copy))
+            }
+            FileTreeMsg::Move if self.writable() => {
+                self.open_prompt(PromptKind::Batch(BatchPrompt::Move))
+            }
+            FileTreeMsg::OpenChmod if self.writable() => self.open_permissions(EditorKind::Chmod),
+            FileTreeMsg::OpenChown if self.writable() => self.open_permissions(EditorKind::Chown),
+            FileTreeMsg::Archive if self.writable() => {
+                self.open_prompt(PromptKind::Batch(BatchPrompt::Archive))
+            }
+            FileTreeMsg::Extract if self.local_writable() => {
+                self.open_prompt(PromptKind::Batch(BatchPrompt::Extract))
+            }
+            FileTreeMsg::Confirm => {
+                let action = self.confirm_action.clone();
+                let result = self.confirm_action();
+
+                match result {
+                    Ok(()) => {
+                        info!("Confirmed action {}!", action)
+                    }
+                    Err(err) => {
+                        error!("Could not confirm action {}! Error: {}", action, err)
+                    }
+                }
+            }
+            FileTreeMsg::Cancel => self.cancel_action(),
+            FileTreeMsg::OpenGrep => self.input_mode = InputMode::Grep,
+            FileTreeMsg::CloseGrep => {
+                if self.grep.is_running() {
+                    self.grep.cancel();
+                } else {
+                    self.input_mode = InputMode::Modify;
+                }
+            }
+            FileTreeMsg::GrepChar(to_insert) => self.grep.query.push(*to_insert),
+            FileTreeMsg::GrepDeleteChar => {
+                self.grep.query.pop();
+            }
+            FileTreeMsg::GrepRun => self.grep.start(path::Path::new(&self.open_path)),
+            FileTreeMsg::GrepToggleLiteral => self.grep.literal = !self.grep.literal,
+            FileTreeMsg::GrepNext => self.grep.select_next(),
+            FileTreeMsg::GrepPrev => self.grep.select_previous(),
+            FileTreeMsg::NextSortColumn => {
+                self.listing.next_sort_column();
+                self.sort_entries();
+            }
+            FileTreeMsg::ReverseSort => {
+                self.listing.descending = !self.listing.descending;
+                self.sort_entries();
+            }
+            FileTreeMsg::ToggleDirsFirst => {
+                self.listing.dirs_first = !self.listing.dirs_first;
+                self.sort_entries();
+            }
+            FileTreeMsg::OpenColumnPicker => self.input_mode = InputMode::Columns,
+            FileTreeMsg::CloseColumnPicker => self.input_mode = InputMode::Modify,
+            FileTreeMsg::ColumnPickerDown => self.column_picker.highlight_next(),
+            FileTreeMsg::ColumnPickerUp => self.column_picker.highlight_prev(),
+            FileTreeMsg::ToggleColumn => {
+                if let Some(index) = self.column_picker.highlighted {
+                    self.listing.toggle_column(Column::ALL[index]);
+                    self.sync_column_picker();
+                    self.sort_entries();
+                }
+            }
+            FileTreeMsg::ToggleHidden => {
+                let filter = self.active_filter_mut();
+                filter.show_hidden = !filter.show_hidden;
+                self.apply_filter();
+            }
+            FileTreeMsg::CycleTypeFilter => {
+                let filter = self.active_filter_mut();
+                filter.types = filter.types.next();
+                self.apply_filter();
+            }
+            FileTreeMsg::ToggleFilterScope => {
+                self.filter_scope = match self.filter_scope {
+                    FilterScope::Session => FilterScope::Directory,
+                    FilterScope::Directory => FilterScope::Session,
+                };
+                self.apply_filter();
+            }
+            FileTreeMsg::OpenFilter => {
+                self.filter_input = self.active_filter().expression.source.clone();
+                self.filter_error = None;
+                self.input_mode = InputMode::Filter;
+            }
+            FileTreeMsg::FilterChar(to_insert) => self.filter_input.push(*to_insert),
+            FileTreeMsg::FilterDeleteChar => {
+                self.filter_input.pop();
+            }
+            FileTreeMsg::ApplyFilter => match Expression::parse(&self.filter_input) {
+                Ok(expression) => {
+                    self.active_filter_mut().expression = expression;
+                    self.apply_filter();
+                    self.input_mode = InputMode::Modify;
+                }
+                Err(err) => self.filter_error = Some(err),
+            },
+            FileTreeMsg::CloseFilter => self.input_mode = InputMode::Modify,
+            FileTreeMsg::ToggleMark => {
+                if let Some(entry) = self.highlighted_entry() {
+                    let path = entry.path.clone();
+                    if !self.marked.remove(&path) {
+                        self.marked.insert(path);
+                    }
+                    self.select_state.select_next();
+                }
+            }
+            FileTreeMsg::ToggleVisual => match self.visual_anchor.take() {
+                Some(anchor) => {
+                    let cursor = self.select_state.selected().unwrap_or(anchor);
+                    let (start, end) = (anchor.min(cursor), anchor.max(cursor));
+                    for entry in self.entries.iter().take(end + 1).skip(start) {
+                        self.marked.insert(entry.path.clone());
+                    }
+                }
+                None => self.visual_anchor = self.select_state.selected(),
+            },
+            FileTreeMsg::ClearMarks => {
+                self.marked.clear();
+                self.visual_anchor = None;
+            }
+            FileTreeMsg::PromptChar(to_insert) => {
+                if let Some((_, text)) = &mut self.prompt {
+                    text.push(*to_insert);
+                }
+            }
+            FileTreeMsg::PromptDeleteChar => {
+                if let Some((_, text)) = &mut self.prompt {
+                    text.pop();
+                }
+            }
+            FileTreeMsg::SubmitPrompt => self.submit_prompt(),
+            FileTreeMsg::CancelPrompt => {
+                self.prompt = None;
+                self.input_mode = InputMode::Modify;
+            }
+            FileTreeMsg::DismissReport => self.batch_report = None,
+            FileTreeMsg::Preview => self.open_preview(),
+            FileTreeMsg::OpenWith => self.open_prompt(PromptKind::OpenWith),
+            FileTreeMsg::Rename if self.writable() => {
+                if let Some(entry) = self.highlighted_entry() {
+                    self.prompt = Some((PromptKind::Rename, entry.name()));
+                    self.prompt_error = None;
+                    self.input_mode = InputMode::Prompt;
+                }
+            }
+            FileTreeMsg::BulkRename if self.writable() => self.edit_names(),
+            FileTreeMsg::PatternRename if self.writable() => {
+                self.open_prompt(PromptKind::PatternRename)
+            }
+            FileTreeMsg::DismissPreview => self.preview = None,
+            FileTreeMsg::OpenHex if self.vfs.archive().is_none() => {
+                let Some(path) = self
+                    .highlighted_entry()
+                    .map(|entry| entry.path.clone())
+                    .filter(|path| path.is_file())
+                else {
+                    return;
+                };
+                match HexView::open(&path, !self.read_only) {
+                    Ok(view) => {
+                        self.hex = Some(view);
+                        self.input_mode = InputMode::Hex;
+                    }
+                    Err(err) => error!("Could not open {}: {err}", path.display()),
+                }
+            }
+            FileTreeMsg::OpenElf if self.vfs.archive().is_none() => {
+                let Some(path) = self
+                    .highlighted_entry()
+                    .map(|entry| entry.path.clone())
+                    .filter(|path| path.is_file())
+                else {
+                    return;
+                };
+                match ElfView::open(&path) {
+                    Ok(view) => {
+                        self.elf = Some(view);
+                        self.input_mode = InputMode::Elf;
+                    }
+                    Err(err) => error!("Could not inspect {}: {err}", path.display()),
+                }
+            }
+            FileTreeMsg::CloseElf => {
+                self.elf = None;
+                self.input_mode = InputMode::Modify;
+            }
+            FileTreeMsg::ElfDown | FileTreeMsg::ElfUp | FileTreeMsg::ElfSwitchTab(_) => {
+                let Some(view) = &mut self.elf else {
+                    return;
+                };
+                match msg {
+                    FileTreeMsg::ElfDown => view.table_state.select_next(),
+                    FileTreeMsg::ElfUp => view.table_state.select_previous(),
+                    FileTreeMsg::ElfSwitchTab(forward) => view.switch_tab(*forward),
+                    _ => {}
+                }
+            }
+            FileTreeMsg::Hex(action) => {
+                let close = self.hex.as_mut().is_none_or(|view| view.apply(*action));
+                if close {
+                    self.hex = None;
+                    self.input_mode = InputMode::Modify;
+                    self.read_path(self.open_path.clone());
+                }
+            }
+            FileTreeMsg::OpenHighlighted => {
+                let local = self.vfs.archive().is_none();
+                let openable = |entry: &FileEntry| {
+                    entry.points_to_dir() || local && ArchiveFormat::detect(&entry.path).is_some()
+                };
+                match self.highlighted_entry() {
+                    Some(entry) if openable(entry) => {
+                        self.navigate(entry.path.display().to_string())
+                    }
+                    Some(_) => self.open_file(None),
+                    None => {}
+                }
+            }
+            FileTreeMsg::ParentDir => {
+                if let Some(parent) = path::Path::new(&self.open_path).parent() {
+                    self.navigate(parent.display().to_string());
+                }
+            }
+            FileTreeMsg::HistoryBack => {
+                if let Some(target) = self.history.back(self.open_path.clone()) {
+                    self.read_path(target);
+                    self.sync_input();
+                }
+            }
+            FileTreeMsg::HistoryForward => {
+                if let Some(target) = self.history.forward(self.open_path.clone()) {
+                    self.read_path(target);
+                    self.sync_input();
+                }
+            }
+            FileTreeMsg::AddBookmark => {
+                self.prompt = Some((PromptKind::Bookmark, String::new()));
+                self.prompt_error = None;
+                self.input_mode = InputMode::Prompt;
+            }
+            FileTreeMsg::OpenBookmarks => self.open_picker(PickerKind::Bookmarks),
+            FileTreeMsg::OpenRecent => self.open_picker(PickerKind::Recent),
+            FileTreeMsg::PickerDown => self.picker_state.select_next(),
+            FileTreeMsg::PickerUp => self.picker_state.select_previous(),
+            FileTreeMsg::PickerOpen => {
+                let selected = self.picker_state.selected();
+                if let Some((_, target)) = selected.and_then(|index| self.picker_items.get(index)) {
+                    let target = target.clone();
+                    self.input_mode = InputMode::Modify;
+                    self.navigate(target);
+                }
+            }
+            FileTreeMsg::PickerDelete => {
+                let selected = self.picker_state.selected();
+                if let Some((name, _)) = selected.and_then(|index| self.picker_items.get(index)) {
+                    self.bookmarks.remove(&name.clone());
+                    self.open_picker(PickerKind::Bookmarks);
+                }
+            }
+            FileTreeMsg::ClosePicker => self.input_mode = InputMode::Modify,
+            FileTreeMsg::ToggleAutoSizes => {
+                self.auto_sizes = !self.auto_sizes;
+                if self.auto_sizes {
+                    self.scan_sizes();
+                } else if let Some(scan) = self.size_scan.take() {
+                    scan.cancel();
+                }
+            }
+            FileTreeMsg::OpenDiskUsage => {
+                self.du_tree = None;
+                self.scan_sizes();
+                self.du_view = DuView {
+                    select_state: ListState::default().with_selected(Some(0)),
+                    treemap: self.du_view.treemap,
+                    color_by: self.du_view.color_by,
+                    ..Default::default()
+                };
+                self.input_mode = InputMode::DiskUsage;
+            }
+            FileTreeMsg::CloseDiskUsage => self.input_mode = InputMode::Modify,
+            FileTreeMsg::DuDown => self.du_view.select_state.select_next(),
+            FileTreeMsg::DuUp => self.du_view.select_state.select_previous(),
+            FileTreeMsg::DuEnter => {
+                if let Some(tree) = &self.du_tree {
+                    self.du_view.enter(tree);
+                }
+            }
+            FileTreeMsg::DuLeave => self.du_view.leave(),
+            FileTreeMsg::DuDelete if self.writable() => {
+                self.du_view.confirm_delete = self.du_tree.is_some()
+            }
+            FileTreeMsg::DuCancelDelete => self.du_view.confirm_delete = false,
+            FileTreeMsg::DuConfirmDelete if self.writable() => {
+                if let Some(tree) = &mut self.du_tree {
+                    match self.du_view.delete(tree, self.vfs.as_ref()) {
+                        Ok(path) => {
+                            info!("Deleted {}", path.display());
+                            self.read_path(self.open_path.clone());
+                        }
+                        Err(err) => error!("Could not delete: {err}"),
+                    }
+                }
+            }
+            FileTreeMsg::DuToggleMode => {
+                if let Some(tree) = &mut self.du_tree {
+                    self.du_view.toggle_mode(tree);
+                }
+            }
+            FileTreeMsg::DuToggleTreemap => self.du_view.treemap = !self.du_view.treemap,
+            FileTreeMsg::DuToggleColors => self.du_view.color_by = self.du_view.color_by.next(),
+            FileTreeMsg::OpenDuplicates if self.vfs.archive().is_none() => {
+                self.dup_scan = Some(DupScan::start(path::Path::new(&self.open_path)));
+                self.dup_view = DupView {
+                    select_state: ListState::default().with_selected(Some(0)),
+                    ..Default::default()
+                };
+                self.input_mode = InputMode::Duplicates;
+            }
+            FileTreeMsg::ToggleDetails => {
+                self.details = match (&self.details, self.highlighted_entry()) {
+                    (None, Some(entry)) => Some(Details::read(&entry.path, &self.users)),
+                    _ => None,
+                }
+            }
+            FileTreeMsg::OpenMounts => {
+                self.mounts.refresh();
+                self.input_mode = InputMode::Mounts;
+            }
+            FileTreeMsg::CloseMounts => self.input_mode = InputMode::Modify,
+            FileTreeMsg::OpenAudit => {
+                if self.audit.is_none() {
+                    self.audit = Some(AuditView::start(
+                        path::Path::new(&self.open_path),
+                        self.users.clone(),
+                    ));
+                }
+                self.input_mode = InputMode::Audit;
+            }
+            FileTreeMsg::CloseAudit => match &mut self.audit {
+                Some(view) if view.running() => view.cancel(),
+                _ => {
+                    self.audit = None;
+                    self.input_mode = InputMode::Modify;
+                }
+            },
+            FileTreeMsg::AuditDown => {
+                if let Some(view) = &mut self.audit {
+                    view.table_state.select_next();
+                }
+            }
+            FileTreeMsg::AuditUp => {
+                if let Some(view) = &mut self.audit {
+                    view.table_state.select_previous();
+                }
+            }
+            FileTreeMsg::AuditJump => {
+                let path = self
+                    .audit
+                    .as_ref()
+                    .and_then(AuditView::highlighted)
+                    .map(|finding| finding.path.clone());
+                if let Some(path) = path {
+                    self.input_mode = InputMode::Modify;
+                    self.reveal(&path);
+                }
+            }
+            FileTreeMsg::AuditExport => {
+                if let Some(view) = &mut self.audit {
+                    view.status = Some(match view.export() {
+                        Ok(file) => format!("Exported to {}", file.display()),
+                        Err(err) => format!("Could not export: {err}"),
+                    });
+                }
+            }
+            FileTreeMsg::MountDown => self.mounts.table_state.select_next(),
+            FileTreeMsg::MountUp => self.mounts.table_state.select_previous(),
+            FileTreeMsg::MountToggleAll => self.mounts.toggle_all(),
+            FileTreeMsg::MountRefresh => self.mounts.refresh(),
+            FileTreeMsg::MountOpen => {
+                if let Some(path) = self.mounts.highlighted() {
+                    self.input_mode = InputMode::Modify;
+                    self.navigate(path.display().to_string());
+                }
+            }
+            FileTreeMsg::CloseDuplicates => match self.dup_scan.take() {
+                Some(scan) => {
+                    scan.cancel();
+                    self.dup_view.status = Some("Scan cancelled".to_string());
+                }
+                None => self.input_mode = InputMode::Modify,
+            },
+            FileTreeMsg::DupDown => self.dup_view.select_state.select_next(),
+            FileTreeMsg::DupUp => self.dup_view.select_state.select_previous(),
+            FileTreeMsg::DupToggleMark => self.dup_view.toggle_mark(),
+            FileTreeMsg::DupMarkCopies => self.dup_view.mark_copies(),
+            FileTreeMsg::DupAsk(action)
+                if self.dup_scan.is_none()
+                    && !self.dup_view.marked.is_empty()
+                    && self.writable() =>
+            {
+                self.dup_view.confirm = Some(*action)
+            }
+            FileTreeMsg::DupCancel => self.dup_view.confirm = None,
+            FileTreeMsg::DupApply if self.writable() => {
+                if let Some(action) = self.dup_view.confirm {
+                    let report = self.dup_view.apply(action);
+                    self.dup_view.status = Some(format!(
+                        "{}: {} done, {} failed",
+                        report.op,
+                        report.succeeded,
+                        report.failures.len()
+                    ));
+                    self.read_path(self.open_path.clone());
+                    if !report.failures.is_empty() {
+                        error!("{}: {} items failed", report.op, report.failures.len());
+                        self.batch_report = Some(report);
+                    }
+                }
+            }
+            FileTreeMsg::OpenChecksums if self.vfs.archive().is_none() => {
+                let files: Vec<PathBuf> =
+                    self.targets().into_iter().map(|entry| entry.path).collect();
+                if !files.is_empty() {
+                    let base = path::Path::new(&self.open_path);
+                    self.checksums = Some(ChecksumView::compute(Algorithm::Sha256, base, &files));
+                    self.input_mode = InputMode::Checksums;
+                }
+            }
+            FileTreeMsg::VerifyChecksums if self.vfs.archive().is_none() => {
+                let Some(entry) = self.highlighted_entry().filter(|entry| !entry.is_dir()) else {
+                    return;
+                };
+                match ChecksumView::verify(&entry.path) {
+                    Ok(view) => {
+                        self.checksums = Some(view);
+                        self.input_mode = InputMode::Checksums;
+                    }
+                    Err(err) => error!("Could not read {}: {err}", entry.path.display()),
+                }
+            }
+            FileTreeMsg::CloseChecksums => match &mut self.checksums {
+                Some(view) if view.is_running() => view.cancel(),
+                _ => {
+                    self.checksums = None;
+                    self.input_mode = InputMode::Modify;
+                }
+            },
+            FileTreeMsg::ChecksumDown
+            | FileTreeMsg::ChecksumUp
+            | FileTreeMsg::ChecksumNextAlgorithm
+            | FileTreeMsg::ChecksumCopy
+            | FileTreeMsg::ChecksumCopyAll => {
+                let Some(view) = &mut self.checksums else {
+                    return;
+                };
+                match msg {
+                    FileTreeMsg::ChecksumDown => view.select_state.select_next(),
+                    FileTreeMsg::ChecksumUp => view.select_state.select_previous(),
+                    FileTreeMsg::ChecksumNextAlgorithm => view.next_algorithm(),
+                    FileTreeMsg::ChecksumCopy => view.copy_highlighted(),
+                    _ => view.copy_all(),
+                }
+            }
+            FileTreeMsg::ChecksumWrite if self.writable() => {
+                let Some(view) = &mut self.checksums else {
+                    return;
+                };
+                view.status = Some(match view.write_sums() {
+                    Ok(path) => format!("Wrote {}", path.display()),
+                    Err(err) => format!("Could not write checksum file: {err}"),
+                });
+                self.read_path(self.open_path.clone());
+            }
+            FileTreeMsg::OpenDiff if self.vfs.archive().is_none() => {
+                let targets = self.targets();
+                let [left, right] = targets.as_slice() else {
+                    error!("Mark two files or two directories to compare");
+                    return;
+                };
+                match DiffView::new(&left.path, &right.path) {
+                    Ok(view) => {
+                        self.diff = Some(view);
+                        self.input_mode = InputMode::Diff;
+                    }
+                    Err(err) => error!("Could not compare: {err}"),
+                }
+            }
+            FileTreeMsg::CloseDiff => {
+                let close = self.diff.as_mut().is_none_or(DiffView::back);
+                if close {
+                    self.diff = None;
+                    self.input_mode = InputMode::Modify;
+                    self.read_path(self.open_path.clone());
+                }
+            }
+            FileTreeMsg::DiffCopy(_) if !self.writable() => {}
+            FileTreeMsg::DiffDown
+            | FileTreeMsg::DiffUp
+            | FileTreeMsg::DiffNextHunk
+            | FileTreeMsg::DiffPrevHunk
+            | FileTreeMsg::DiffToggleSplit
+            | FileTreeMsg::DiffOpen
+            | FileTreeMsg::DiffCopy(_)
+            | FileTreeMsg::DiffConfirm
+            | FileTreeMsg::DiffCancel => {
+                let Some(view) = &mut self.diff else {
+                    return;
+                };
+                match msg {
+                    FileTreeMsg::DiffDown => view.down(),
+                    FileTreeMsg::DiffUp => view.up(),
+                    FileTreeMsg::DiffNextHunk => view.next_hunk(),
+                    FileTreeMsg::DiffPrevHunk => view.previous_hunk(),
+                    FileTreeMsg::DiffToggleSplit => view.toggle_split(),
+                    FileTreeMsg::DiffOpen => view.open(),
+                    FileTreeMsg::DiffCopy(side) => view.ask_copy(*side),
+                    FileTreeMsg::DiffConfirm => view.confirm(),
+                    _ => view.cancel(),
+                }
+            }
+            FileTreeMsg::GitStage | FileTreeMsg::GitUnstage if self.writable() => {
+                let Some(git) = &self.git else {
+                    return;
+                };
+                let paths: Vec<PathBuf> =
+                    self.targets().into_iter().map(|entry| entry.path).collect();
+                let report = match msg {
+                    FileTreeMsg::GitStage => git.stage(&paths),
+                    _ => git.unstage(&paths),
+                };
+                self.read_path(self.open_path.clone());
+                if report.failures.is_empty() {
+                    info!("{}: {} items", report.op, report.succeeded);
+                } else {
+                    error!("{}: {} items failed", report.op, report.failures.len());
+                    self.batch_report = Some(report);
+                }
+            }
+            FileTreeMsg::Symlink if self.writable() => {
+                self.open_prompt(PromptKind::Batch(BatchPrompt::Symlink))
+            }
+            FileTreeMsg::Hardlink if self.writable() => {
+                self.open_prompt(PromptKind::Batch(BatchPrompt::Hardlink))
+            }
+            FileTreeMsg::ToggleFollowLinks => self.follow_links = !self.follow_links,
+            FileTreeMsg::PauseTransfer => self.transfers.toggle_pause(),
+            FileTreeMsg::CancelTransfer => self.transfers.cancel(),
+            FileTreeMsg::ToggleReadOnly => {
+                self.read_only = !self.read_only;
+                self.read_path(self.open_path.clone());
+            }
+            FileTreeMsg::GitDiscard if self.writable() => {
+                let targets = self.targets();
+                if self.git.is_some() && !targets.is_empty() {
+                    self.confirm_action = ConfirmAction::GitDiscard(targets);
+                }
+            }
+            FileTreeMsg::PermissionsDown
+            | FileTreeMsg::PermissionsUp
+            | FileTreeMsg::PermissionsToggleBit
+            | FileTreeMsg::PermissionsDigit(_)
+            | FileTreeMsg::PermissionsDeleteDigit
+            | FileTreeMsg::PermissionsSwitchList
+            | FileTreeMsg::PermissionsToggleRecursive => {
+                let Some(editor) = &mut self.permissions else {
+                    return;
+                };
+                match msg {
+                    FileTreeMsg::PermissionsDown => editor.next(),
+                    FileTreeMsg::PermissionsUp => editor.previous(),
+                    FileTreeMsg::PermissionsToggleBit => editor.toggle_bit(),
+                    FileTreeMsg::PermissionsDigit(digit) => editor.push_octal(*digit),
+                    FileTreeMsg::PermissionsDeleteDigit => editor.pop_octal(),
+                    FileTreeMsg::PermissionsSwitchList => editor.group_focus = !editor.group_focus,
+                    _ => editor.toggle_recursive(),
+                }
+            }
+            FileTreeMsg::PermissionsApply => {
+                if let Some(editor) = self.permissions.take() {
+                    let report = editor.apply();
+                    self.input_mode = InputMode::Modify;
+                    self.marked.clear();
+                    self.visual_anchor = None;
+                    self.read_path(self.open_path.clone());
+
+                    if report.failures.is_empty() {
+                        info!("{}: {} items changed", report.op, report.succeeded);
+                    } else {
+                        error!("{}: {} items failed", report.op, report.failures.len());
+                        self.batch_report = Some(report);
+                    }
+                }
+            }
+            FileTreeMsg::PermissionsClose => {
+                self.permissions = None;
+                self.input_mode = InputMode::Modify;
+            }
+            _ => {}
+        }
+    }
+
+    fn info(&self) -> AppInfo {
+        return self.info.clone();
+    }
+
+    fn take_command(&mut self) -> Option<Command> {
+        self.command.take()
+    }
+
+    fn resume(&mut self, status: io::Result<ExitStatus>) {
+        let success = match status {
+            Ok(status) if status.success() => true,
+            Ok(status) => {
+                error!("Opener exited with {status}");
+                false
+            }
+            Err(err) => {
+                error!("Could not run opener: {err}");
+                false
+            }
+        };
+
+        if let Some((sources, names_file)) = self.pending_rename.take() {
+            let names = fs::read_to_string(&names_file);
+            let _ = fs::remove_file(&names_file);
+            match names {
+                Ok(names) if success => {
+                    let names: Vec<String> = names
+                        .trim_end_matches('\n')
+                        .lines()
+                        .map(str::to_string)
+                        .collect();
+                    self.plan_renames(&sources, &names);
+                }
+                Ok(_) => {}
+                Err(err) => error!("Could not read {}: {err}", names_file.display()),
+            }
+        }
+        self.read_path(self.open_path.clone());
+    }
+
+    fn tick(&mut self) {
+        self.grep.poll();
+
+        if let Some(tree) = self.size_scan.as_mut().and_then(SizeScan::poll) {
+            self.size_scan = None;
+            self.du_tree = Some(tree);
+            self.apply_dir_sizes();
+        }
+
+        if let Some(view) = &mut self.checksums {
+            view.poll();
+        }
+
+        if let Some(groups) = self.dup_scan.as_mut().and_then(DupScan::poll) {
+            self.dup_scan = None;
+            self.dup_view.groups = groups;
+            self.dup_view.select_state.select(Some(0));
+        }
+
+        if let Some(view) = &mut self.audit {
+            view.poll();
+        }
+
+        if let Some(view) = &mut self.diff {
+            view.poll();
+        }
+
+        if let InputMode::Mounts = self.input_mode {
+            self.mounts.poll();
+        }
+
+        if let Some(report) = self.transfers.poll() {
+            self.read_path(self.open_path.clone());
+            if report.failures.is_empty() {
+                info!("{}: {} items", report.op, report.succeeded);
+            } else {
+                error!("{}: {} items failed", report.op, report.failures.len());
+                self.batch_report = Some(report);
+            }
+        }
+
+        match self.watcher.as_mut().and_then(DirWatcher::poll) {
+            Some(Changes::Paths(paths)) => self.apply_changes(paths),
+            Some(Changes::Rescan) => self.read_path(self.open_path.clone()),
+            None => {}
+        }
+    }
+
+    fn captures_key(&self, key: &event::KeyEvent) -> bool {
+        matches!(self.input_mode, InputMode::Search) && key.code == KeyCode::Tab
+    }
+
+    fn generate_msg(&self, key_event: event::KeyEvent) -> Option<Self::Msg> {
+        match self.input_mode {
+            InputMode::Search => match key_event.code {
+                KeyCode::Enter => Some(FileTreeMsg::OpenPath),
+                KeyCode::Char('f') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                    Some(FileTreeMsg::CreateFile)
+                }
+                KeyCode::Char('d') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                    Some(FileTreeMsg::CreateDir)
+                }
+                KeyCode::Char('g') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                    Some(FileTreeMsg::OpenGrep)
+                }
+                KeyCode::Tab => Some(FileTreeMsg::CompletePath),
+                KeyCode::BackTab => Some(FileTreeMsg::CompletePathBack),
+                _ => Some(match Edit::from_key(key_event) {
+                    Some(edit) => FileTreeMsg::EditInput(edit),
+                    None => FileTreeMsg::NoneMsg,
+                }),
+            },
+            InputMode::Modify if self.batch_report.is_some() => Some(FileTreeMsg::DismissReport),
+            InputMode::Modify if self.preview.is_some() => Some(FileTreeMsg::DismissPreview),
+            InputMode::Modify => match key_event.code {
+                KeyCode::Backspace => Some(FileTreeMsg::OpenPath),
+                KeyCode::Char('j') => Some(FileTreeMsg::CursorDown),
+                KeyCode::Char('k') => Some(FileTreeMsg::CursorUp),
+                KeyCode::Char('d') => Some(FileTreeMsg::Delete),
+                KeyCode::Char('r') => Some(FileTreeMsg::Rename),
+                KeyCode::Char('R') => Some(FileTreeMsg::BulkRename),
+                KeyCode::Char('e') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                    Some(FileTreeMsg::OpenElf)
+                }
+                KeyCode::Char('e') => Some(FileTreeMsg::PatternRename),
+                KeyCode::Char('m') => Some(FileTreeMsg::Move),
+                KeyCode::Char('c') => Some(FileTreeMsg::Copy),
+                KeyCode::Char('y') | KeyCode::Char('Y') => Some(FileTreeMsg::Confirm),
+                KeyCode::Char('n') | KeyCode::Char('N') => Some(FileTreeMsg::Cancel),
+                KeyCode::Char('/') => Some(FileTreeMsg::OpenGrep),
+                KeyCode::Char('s') => Some(FileTreeMsg::NextSortColumn),
+                KeyCode::Char('S') => Some(FileTreeMsg::ReverseSort),
+                KeyCode::Char('g') => Some(FileTreeMsg::ToggleDirsFirst),
+                KeyCode::Char('v') => Some(FileTreeMsg::OpenColumnPicker),
+                KeyCode::Char('.') => Some(FileTreeMsg::ToggleHidden),
+                KeyCode::Char('t') => Some(FileTreeMsg::CycleTypeFilter),
+                KeyCode::Char('F') => Some(FileTreeMsg::ToggleFilterScope),
+                KeyCode::Char('f') => Some(FileTreeMsg::OpenFilter),
+                KeyCode::Char(' ') => Some(FileTreeMsg::ToggleMark),
+                KeyCode::Char('V') => Some(FileTreeMsg::ToggleVisual),
+                KeyCode::Char('U') | KeyCode::Esc => Some(FileTreeMsg::ClearMarks),
+                KeyCode::Char('M') => Some(FileTreeMsg::OpenChmod),
+                KeyCode::Char('O') => Some(FileTreeMsg::OpenChown),
+                KeyCode::Char('A') => Some(FileTreeMsg::Archive),
+                KeyCode::Char('x') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                    Some(FileTreeMsg::OpenHex)
+                }
+                KeyCode::Char('x') => Some(FileTreeMsg::Extract),
+                KeyCode::Char('p') => Some(FileTreeMsg::Preview),
+                KeyCode::Char('o') => Some(FileTreeMsg::OpenWith),
+                KeyCode::Enter | KeyCode::Char('l') => Some(FileTreeMsg::OpenHighlighted),
+                KeyCode::Char('h') | KeyCode::Char('-') => Some(FileTreeMsg::ParentDir),
+                KeyCode::Char('[') => Some(FileTreeMsg::HistoryBack),
+                KeyCode::Char(']') => Some(FileTreeMsg::HistoryForward),
+                KeyCode::Char('b') => Some(FileTreeMsg::AddBookmark),
+                KeyCode::Char('B') => Some(FileTreeMsg::OpenBookmarks),
+                KeyCode::Char('z') => Some(FileTreeMsg::OpenRecent),
+                KeyCode::Char('D') => Some(FileTreeMsg::ToggleAutoSizes),
+                KeyCode::Char('u') => Some(FileTreeMsg::OpenDiskUsage),
+                KeyCode::Char('=') => Some(FileTreeMsg::OpenDuplicates),
+                KeyCode::Char('#') => Some(FileTreeMsg::OpenChecksums),
+                KeyCode::Char('K') => Some(FileTreeMsg::VerifyChecksums),
+                KeyCode::Char('C') => Some(FileTreeMsg::OpenDiff),
+                KeyCode::Char('a') => Some(FileTreeMsg::GitStage),
+                KeyCode::Char('I') => Some(FileTreeMsg::GitUnstage),
+                KeyCode::Char('X') => Some(FileTreeMsg::GitDiscard),
+                KeyCode::Char('L') => Some(FileTreeMsg::Symlink),
+                KeyCode::Char('H') => Some(FileTreeMsg::Hardlink),
+                KeyCode::Char('T') => Some(FileTreeMsg::ToggleFollowLinks),
+                KeyCode::Char('G') => Some(FileTreeMsg::OpenMounts),
+                KeyCode::Char('i') => Some(FileTreeMsg::ToggleDetails),
+                KeyCode::Char('W') => Some(FileTreeMsg::ToggleReadOnly),
+                KeyCode::Char('P') => Some(FileTreeMsg::PauseTransfer),
+                KeyCode::Char('Z') => Some(FileTreeMsg::CancelTransfer),
+                KeyCode::Char('E') => Some(FileTreeMsg::OpenAudit),
+                _ => Some(FileTreeMsg::NoneMsg),
+            },
+            InputMode::DiskUsage if self.du_view.confirm_delete => match key_event.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => Some(FileTreeMsg::DuConfirmDelete),
+                _ => Some(FileTreeMsg::DuCancelDelete),
+            },
+            InputMode::DiskUsage => match key_event.code {
+                KeyCode::Esc | KeyCode::Char('q') => Some(FileTreeMsg::CloseDiskUsage),
+                KeyCode::Char('j') | KeyCode::Down => Some(FileTreeMsg::DuDown),
+                KeyCode::Char('k') | KeyCode::Up => Some(FileTreeMsg::DuUp),
+                KeyCode::Char('l') | KeyCode::Enter => Some(FileTreeMsg::DuEnter),
+                KeyCode::Char('h') | KeyCode::Backspace => Some(FileTreeMsg::DuLeave),
+                KeyCode::Char('d') => Some(FileTreeMsg::DuDelete),
+                KeyCode::Char('a') => Some(FileTreeMsg::DuToggleMode),
+                KeyCode::Char('t') => Some(FileTreeMsg::DuToggleTreemap),
+                KeyCode::Char('c') => Some(FileTreeMsg::DuToggleColors),
+                _ => Some(FileTreeMsg::NoneMsg),
+            },
+            InputMode::Mounts => match key_event.code {
+                KeyCode::Esc | KeyCode::Char('q') => Some(FileTreeMsg::CloseMounts),
+                KeyCode::Char('j') | KeyCode::Down => Some(FileTreeMsg::MountDown),
+                KeyCode::Char('k') | KeyCode::Up => Some(FileTreeMsg::MountUp),
+                KeyCode::Char('l') | KeyCode::Enter => Some(FileTreeMsg::MountOpen),
+                KeyCode::Char('a') => Some(FileTreeMsg::MountToggleAll),
+                KeyCode::Char('r') => Some(FileTreeMsg::MountRefresh),
+                _ => Some(FileTreeMsg::NoneMsg),
+            },
+            InputMode::Hex => Some(
+                match self
+                    .hex
+                    .as_ref()
+                    .and_then(|view| view.key_action(key_event))
+                {
+                    Some(action) => FileTreeMsg::Hex(action),
+                    None => FileTreeMsg::NoneMsg,
+                },
+            ),
+            InputMode::Elf => match key_event.code {
+                KeyCode::Esc | KeyCode::Char('q') => Some(FileTreeMsg::CloseElf),
+                KeyCode::Char('j') | KeyCode::Down => Some(FileTreeMsg::ElfDown),
+                KeyCode::Char('k') | KeyCode::Up => Some(FileTreeMsg::ElfUp),
+                KeyCode::Char('l') | KeyCode::Right => Some(FileTreeMsg::ElfSwitchTab(true)),
+                KeyCode::Char('h') | KeyCode::Left => Some(FileTreeMsg::ElfSwitchTab(false)),
+                _ => Some(FileTreeMsg::NoneMsg),
+            },
+            InputMode::Audit => match key_event.code {
+                KeyCode::Esc | KeyCode::Char('q') => Some(FileTreeMsg::CloseAudit),
+                KeyCode::Char('j') | KeyCode::Down => Some(FileTreeMsg::AuditDown),
+                KeyCode::Char('k') | KeyCode::Up => Some(FileTreeMsg::AuditUp),
+                KeyCode::Char('l') | KeyCode::Enter => Some(FileTreeMsg::AuditJump),
+                KeyCode::Char('e') => Some(FileTreeMsg::AuditExport),
+                _ => Some(FileTreeMsg::NoneMsg),
+            },
+            InputMode::Duplicates if self.dup_view.confirm.is_some() => match key_event.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => Some(FileTreeMsg::DupApply),
+                _ => Some(FileTreeMsg::DupCancel),
+            },
+            InputMode::Duplicates => match key_event.code {
+                KeyCode::Esc | KeyCode::Char('q') => Some(FileTreeMsg::CloseDuplicates),
+                KeyCode::Char('j') | KeyCode::Down => Some(FileTreeMsg::DupDown),
+                KeyCode::Char('k') | KeyCode::Up => Some(FileTreeMsg::DupUp),
+                KeyCode::Char(' ') => Some(FileTreeMsg::DupToggleMark),
+                KeyCode::Char('a') => Some(FileTreeMsg::DupMarkCopies),
+                KeyCode::Char('d') => Some(FileTreeMsg::DupAsk(DupAction::Delete)),
+                KeyCode::Char('L') => Some(FileTreeMsg::DupAsk(DupAction::Hardlink)),
+                _ => Some(FileTreeMsg::NoneMsg),
+            },
+            InputMode::Checksums => match key_event.code {
+                KeyCode::Esc | KeyCode::Char('q') => Some(FileTreeMsg::CloseChecksums),
+                KeyCode::Char('j') | KeyCode::Down => Some(FileTreeMsg::ChecksumDown),
+                KeyCode::Char('k') | KeyCode::Up => Some(FileTreeMsg::ChecksumUp),
+                KeyCode::Char('a') => Some(FileTreeMsg::ChecksumNextAlgorithm),
+                KeyCode::Char('c') => Some(FileTreeMsg::ChecksumCopy),
+                KeyCode::Char('C') => Some(FileTreeMsg::ChecksumCopyAll),
+                KeyCode::Char('w') => Some(FileTreeMsg::ChecksumWrite),
+                _ => Some(FileTreeMsg::NoneMsg),
+            },
+            InputMode::Diff if self.diff.as_ref().is_some_and(DiffView::confirming) => {
+                match key_event.code {
+                    KeyCode::Char('y') | KeyCode::Char('Y') => Some(FileTreeMsg::DiffConfirm),
+                    _ => Some(FileTreeMsg::DiffCancel),
+                }
+            }
+            InputMode::Diff => match key_event.code {
+                KeyCode::Esc | KeyCode::Char('q') => Some(FileTreeMsg::CloseDiff),
+                KeyCode::Char('j') | KeyCode::Down => Some(FileTreeMsg::DiffDown),
+                KeyCode::Char('k') | KeyCode::Up => Some(FileTreeMsg::DiffUp),
+                KeyCode::Char('n') => Some(FileTreeMsg::DiffNextHunk),
+                KeyCode::Char('N') => Some(FileTreeMsg::DiffPrevHunk),
+                KeyCode::Char('s') => Some(FileTreeMsg::DiffToggleSplit),
+                KeyCode::Char('l') | KeyCode::Enter => Some(FileTreeMsg::DiffOpen),
+                KeyCode::Char('>') => Some(FileTreeMsg::DiffCopy(Side::Left)),
+                KeyCode::Char('<') => Some(FileTreeMsg::DiffCopy(Side::Right)),
+                _ => Some(FileTreeMsg::NoneMsg),
+            },
+            InputMode::Permissions => match key_event.code {
+                KeyCode::Esc => Some(FileTreeMsg::PermissionsClose),
+                KeyCode::Enter => Some(FileTreeMsg::PermissionsApply),
+                KeyCode::Char('j') | KeyCode::Down => Some(FileTreeMsg::PermissionsDown),
+                KeyCode::Char('k') | KeyCode::Up => Some(FileTreeMsg::PermissionsUp),
+                KeyCode::Char('h') | KeyCode::Char('l') | KeyCode::Left | KeyCode::Right => {
+                    Some(FileTreeMsg::PermissionsSwitchList)
+                }
+                KeyCode::Char(' ') => Some(FileTreeMsg::PermissionsToggleBit),
+                KeyCode::Char('R') => Some(FileTreeMsg::PermissionsToggleRecursive),
+                KeyCode::Char(digit @ '0'..='7') => Some(FileTreeMsg::PermissionsDigit(digit)),
+                KeyCode::Backspace => Some(FileTreeMsg::PermissionsDeleteDigit),
+                _ => Some(FileTreeMsg::NoneMsg),
+            },
+            InputMode::Picker(kind) => match key_event.code {
+                KeyCode::Esc => Some(FileTreeMsg::ClosePicker),
+                KeyCode::Char('j') | KeyCode::Down => Some(FileTreeMsg::PickerDown),
+                KeyCode::Char('k') | KeyCode::Up => Some(FileTreeMsg::PickerUp),
+                KeyCode::Enter => Some(FileTreeMsg::PickerOpen),
+                KeyCode::Char('d') if kind == PickerKind::Bookmarks => {
+                    Some(FileTreeMsg::PickerDelete)
+                }
+                _ => Some(FileTreeMsg::NoneMsg),
+            },
+            InputMode::Prompt => match key_event.code {
+                KeyCode::Esc => Some(FileTreeMsg::CancelPrompt),
+                KeyCode::Enter => Some(FileTreeMsg::SubmitPrompt),
+                KeyCode::Backspace => Some(FileTreeMsg::PromptDeleteChar),
+                KeyCode::Char(to_insert) => Some(FileTreeMsg::PromptChar(to_insert)),
+                _ => Some(FileTreeMsg::NoneMsg),
+            },
+            InputMode::Filter => match key_event.code {
+                KeyCode::Esc => Some(FileTreeMsg::CloseFilter),
+                KeyCode::Enter => Some(FileTreeMsg::ApplyFilter),
+                KeyCode::Backspace => Some(FileTreeMsg::FilterDeleteChar),
+                KeyCode::Char(to_insert) => Some(FileTreeMsg::FilterChar(to_insert)),
+                _ => Some(FileTreeMsg::NoneMsg),
+            },
+            InputMode::Columns => match key_event.code {
+                KeyCode::Esc | KeyCode::Char('v') => Some(FileTreeMsg::CloseColumnPicker),
+                KeyCode::Char('j') | KeyCode::Down => Some(FileTreeMsg::ColumnPickerDown),
+                KeyCode::Char('k') | KeyCode::Up => Some(FileTreeMsg::ColumnPickerUp),
+                KeyCode::Char(' ') | KeyCode::Enter => Some(FileTreeMsg::ToggleColumn),
+                _ => Some(FileTreeMsg::NoneMsg),
+            },
+            InputMode::Grep => match key_event.code {
+                KeyCode::Esc => Some(FileTreeMsg::CloseGrep),
+                KeyCode::Char('c') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                    Some(FileTreeMsg::CloseGrep)
+                }
+                KeyCode::Char('l') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                    Some(FileTreeMsg::GrepToggleLiteral)
+                }
+                KeyCode::Char(to_insert) => Some(FileTreeMsg::GrepChar(to_insert)),
+                KeyCode::Backspace => Some(FileTreeMsg::GrepDeleteChar),
+                KeyCode::Enter => Some(FileTreeMsg::GrepRun),
+                KeyCode::Down => Some(FileTreeMsg::GrepNext),
+                KeyCode::Up => Some(FileTreeMsg::GrepPrev),
+                _ => Some(FileTreeMsg::NoneMsg),
+            },
+        }
+    }
+}
+
+impl FileTreeApp {
+    pub fn new() -> FileTreeApp {
+        let mut main_app = FileTreeApp {
+            info: AppInfo {
+                title: "File Tree".to_string(),
+                version: "v1.0".to_string(),
+            },
+            text: "File Tree App".to_string(),
+            input: PathInput::default(),
+            open_path: "/".to_string(),
+            entries: vec![],
+            all_entries: vec![],
+            input_mode: InputMode::Search,
+            select_state: TableState::default(),
+            confirm_action: ConfirmAction::None,
+            grep: GrepState::default(),
+            listing: ListingConfig::default(),
+            column_picker: OptionListState::new(Column::ALL.len()),
+            users: UserDb::load(),
+            filter_scope: FilterScope::Session,
+            session_filter: Filter::default(),
+            dir_filters: HashMap::new(),
+            filter_input: String::new(),
+            filter_error: None,
+            marked: HashSet::new(),
+            visual_anchor: None,
+            prompt: None,
+            prompt_error: None,
+            batch_report: None,
+            bookmarks: Bookmarks::load(),
+            history: History::default(),
+            typed_from: "/".to_string(),
+            frecency: Frecency::load(),
+            picker_items: vec![],
+            picker_state: ListState::default(),
+            size_scan: None,
+            du_tree: None,
+            du_view: DuView::default(),
+            auto_sizes: false,
+            watcher: DirWatcher::new()
+                .inspect_err(|err| error!("Could not start file watcher: {err}"))
+                .ok(),
+            permissions: None,
+            vfs: Box::new(LocalFs),
+            preview: None,
+            openers: Openers::load(),
+            command: None,
+            pending_rename: None,
+            dup_scan: None,
+            dup_view: DupView::default(),
+            mounts: MountView::default(),
+            audit: None,
+            details: None,
+            checksums: None,
+            diff: None,
+            hex: None,
+            elf: None,
+            git: None,
+            follow_links: false,
+            read_only: false,
+            transfers: TransferQueue::default(),
+            temp_dir: None,
+        };
+        main_app.sync_column_picker();
+        return main_app;
+    }
+
+    fn sync_column_picker(&mut self) {
+        let highlighted = self.column_picker.highlighted;
+        for (index, column) in Column::ALL.iter().enumerate() {
+            self.column_picker.highlighted = Some(index);
+            if self.listing.columns.contains(column) {
+                self.column_picker.select();
+            } else {
+                self.column_picker.unselect();
+            }
+        }
+        self.column_picker.highlighted = highlighted;
+    }
+
+    fn sort_entries(&mut self) {
+        self.listing.sort(&mut self.entries, &self.users);
+    }
+
+    fn active_filter(&self) -> &Filter {
+        match self.filter_scope {
+            FilterScope::Session => &self.session_filter,
+            FilterScope::Directory => self
+                .dir_filters
+                .get(&self.open_path)
+                .unwrap_or(&self.session_filter),
+        }
+    }
+
+    fn active_filter_mut(&mut self) -> &mut Filter {
+        match self.filter_scope {
+            FilterScope::Session => &mut self.session_filter,
+            FilterScope::Directory => self
+                .dir_filters
+                .entry(self.open_path.clone())
+                .or_insert_with(|| self.session_filter.clone()),
+        }
+    }
+
+    /// Rebuilds the visible entries, keeping the highlighted entry selected
+    /// if it is still listed.
+    fn apply_filter(&mut self) {
+        let highlighted = self.highlighted_entry().map(|entry| entry.path.clone());
+
+        self.entries = self.active_filter().apply(&self.all_entries);
+        self.sort_entries();
+
+        if let Some(index) =
+            highlighted.and_then(|path| self.entries.iter().position(|entry| entry.path == path))
+        {
+            self.select_state.select(Some(index));
+        }
+    }
+
+    /// Applies watcher events for entries of the open directory without
+    /// re-reading the whole directory.
+    fn apply_changes(&mut self, paths: HashSet<PathBuf>) {
+        let open_path = PathBuf::from(&self.open_path);
+
+        for path in paths
+            .iter()
+            .filter(|path| path.parent() == Some(&open_path))
+        {
+            let position = self
+                .all_entries
+                .iter()
+                .position(|entry| &entry.path == path);
+            match (self.vfs.metadata(path), position) {
+                (Ok(entry), Some(index)) => self.all_entries[index] = entry,
+                (Ok(entry), None) => self.all_entries.push(entry),
+                (Err(_), Some(index)) => {
+                    self.all_entries.remove(index);
+                    self.marked.remove(path);
+                }
+                (Err(_), None) => {}
+            }
+        }
+
+        self.apply_git_status();
+        self.apply_dir_sizes();
+    }
+
+    fn read_path(&mut self, path: String) {
+        let dir = path::Path::new(&path);
+        let result = vfs::open(dir, self.vfs.as_ref(), self.read_only).and_then(|vfs| {
+            let entries = vfs.as_deref().unwrap_or(self.vfs.as_ref()).read_dir(dir)?;
+            Ok((vfs, entries))
+        });
+
+        match result {
+            Ok((vfs, entries)) => {
+                self.open_path = path.clone();
+                self.all_entries = entries;
+                if let Some(vfs) = vfs {
+                    self.vfs = vfs;
+                }
+
+                if self.auto_sizes {
+                    self.scan_sizes();
+                }
+                if let Some(watcher) = self
+                    .watcher
+                    .as_mut()
+                    .filter(|_| self.vfs.archive().is_none())
+                {
+                    if let Err(err) = watcher.watch(path::Path::new(&self.open_path)) {
+                        error!("Could not watch {}: {err}", self.open_path);
+                    }
+                }
+                self.git = match self.vfs.archive() {
+                    Some(_) => None,
+                    None => GitRepo::discover(path::Path::new(&self.open_path)),
+                };
+                self.apply_git_status();
+                self.apply_dir_sizes();
+            }
+            Err(err) => {
+                error!("Couldn't open directory: {} Error: {}", path, err)
+            }
+        }
+    }
+
+    /// Starts a background size scan of the open directory unless one for it
+    /// is already running or finished.
+    fn scan_sizes(&mut self) {
+        if self.vfs.archive().is_some() {
+            return;
+        }
+
+        let root = PathBuf::from(&self.open_path);
+        let scanned = self.du_tree.as_ref().is_some_and(|tree| tree.path == root);
+        let scanning = self
+            .size_scan
+            .as_ref()
+            .is_some_and(|scan| scan.root == root);
+        if scanned || scanning {
+            return;
+        }
+
+        if let Some(scan) = self.size_scan.take() {
+            scan.cancel();
+        }
+        match SizeScan::start(&root) {
+            Ok(scan) => self.size_scan = Some(scan),
+            Err(err) => error!("Could not scan {}: {err}", root.display()),
+        }
+    }
+
+    /// Decorates the listing with the git status of its entries.
+    fn apply_git_status(&mut self) {
+        let Some(git) = &mut self.git else {
+            return;
+        };
+        if let Err(err) = git.refresh(path::Path::new(&self.open_path)) {
+            error!("Could not read git status: {err}");
+        }
+        for entry in &mut self.all_entries {
+            entry.git = git.status_of(&entry.path);
+        }
+    }
+
+    /// Replaces directory sizes in the listing with the recursive totals of
+    /// the last finished scan.
+    fn apply_dir_sizes(&mut self) {
+        if let Some(tree) = self
+            .du_tree
+            .as_ref()
+            .filter(|tree| tree.path == path::Path::new(&self.open_path))
+        {
+            for entry in self.all_entries.iter_mut().filter(|entry| entry.is_dir()) {
+                if let Some(node) = tree.child(&entry.path) {
+                    entry.size = node.apparent;
+                    entry.disk = node.disk;
+                }
+            }
+        }
+        self.apply_filter();
+    }
+
+    fn highlighted_entry(&self) -> Option<&FileEntry> {
+        self.select_state
+            .selected()
+            .and_then(|index| self.entries.get(index))
+    }
+
+    fn is_marked(&self, index: usize) -> bool {
+        let in_visual = match (self.visual_anchor, self.select_state.selected()) {
+            (Some(anchor), Some(cursor)) => {
+                (anchor.min(cursor)..=anchor.max(cursor)).contains(&index)
+            }
+            _ => false,
+        };
+        in_visual || self.marked.contains(&self.entries[index].path)
+    }
+
+    /// The entries a batch operation applies to: every marked entry, or the
+    /// highlighted one when nothing is marked.
+    fn targets(&self) -> Vec<FileEntry> {
+        let marked: Vec<FileEntry> = (0..self.entries.len())
+            .filter(|index| self.is_marked(*index))
+            .map(|index| self.entries[index].clone())
+            .collect();
+
+        if marked.is_empty() {
+            self.highlighted_entry().cloned().into_iter().collect()
+        } else {
+            marked
+        }
+    }
+
+    fn open_prompt(&mut self, kind: PromptKind) {
+        if self.targets().is_empty() {
+            return;
+        }
+        self.prompt = Some((kind, String::new()));
+        self.prompt_error = None;
+        self.input_mode = InputMode::Prompt;
+    }
+
+    /// Archives are browsed read-only, as is everything in the read-only
+    /// mode; logs why a change was refused.
+    fn writable(&self) -> bool {
+        match self.vfs.archive() {
+            Some(archive) => {
+                error!("{} is read-only", archive.display());
+                false
+            }
+            None => self.local_writable(),
+        }
+    }
+
+    /// Like `writable`, but for changes that only write to the local
+    /// filesystem, such as copying out of an archive.
+    fn local_writable(&self) -> bool {
+        if self.read_only {
+            error!("Read-only mode is on, press W to allow changes");
+        }
+        !self.read_only
+    }
+
+    /// The local directory relative prompt paths resolve against: the open
+    /// directory, or the directory holding the open archive.
+    fn local_dir(&self) -> &path::Path {
+        match self.vfs.archive() {
+            Some(archive) => archive.parent().unwrap_or(archive),
+            None => path::Path::new(&self.open_path),
+        }
+    }
+
+    fn open_preview(&mut self) {
+        let Some(entry) = self
+            .highlighted_entry()
+            .filter(|entry| !entry.points_to_dir())
+        else {
+            return;
+        };
+
+        let text = match self.vfs.read(&entry.path, PREVIEW_LIMIT) {
+            Ok(content) if content.contains(&0) => {
+                format!("Binary file, {}", listing::format_size(entry.size))
+            }
+            Ok(content) => String::from_utf8_lossy(&content).to_string(),
+            Err(err) => format!("Could not read {}: {err}", entry.path.display()),
+        };
+        self.preview = Some((entry.name(), text));
+    }
+
+    /// Queues the highlighted file to be opened with `command`, or the
+    /// matching opener rule. Archive members are extracted to a temporary
+    /// directory first.
+    fn open_file(&mut self, command: Option<&str>) {
+        let Some(entry) = self
+            .highlighted_entry()
+            .filter(|entry| !entry.is_dir())
+            .cloned()
+        else {
+            return;
+        };
+
+        let path = if self.vfs.archive().is_some() {
+            let result = self.temp_dir().and_then(|dir| {
+                let dest = dir.join(entry.name());
+                let _ = fs::remove_file(&dest);
+                self.vfs.copy_out(&entry.path, &dest).map(|_| dest)
+            });
+            match result {
+                Ok(dest) => dest,
+                Err(err) => {
+                    error!("Could not extract {}: {err}", entry.path.display());
+                    return;
+                }
+            }
+        } else {
+            entry.path.clone()
+        };
+
+        self.command = match command {
+            Some(command) => Some(opener::shell_command(command, &path)),
+            None => self.openers.command_for(&path),
+        };
+        if self.command.is_none() {
+            error!("No opener for {}", path.display());
+        }
+    }
+
+    /// The private temporary directory of this session. Its owner and mode
+    /// are checked on every use since it lives in a shared directory.
+    fn temp_dir(&mut self) -> io::Result<PathBuf> {
+        if let Some(dir) = &self.temp_dir {
+            match fs::symlink_metadata(dir) {
+                // SAFETY: geteuid has no preconditions.
+                Ok(metadata)
+                    if metadata.is_dir()
+                        && metadata.uid() == unsafe { libc::geteuid() }
+                        && metadata.mode() & 0o077 == 0 =>
+                {
+                    return Ok(dir.clone());
+                }
+                Ok(_) => {
+                    return Err(io::Error::other(format!(
+                        "{} is not private",
+                        dir.display()
+                    )))
+                }
+                // Cleaned up behind our back, so make a new one.
+                Err(_) => {}
+            }
+        }
+        let dir = private_temp_dir()?;
+        self.temp_dir = Some(dir.clone());
+        Ok(dir)
+    }
+
+    /// Writes the names of the targets to a file and opens it in `$EDITOR`;
+    /// the edited names are picked up in `resume`.
+    fn edit_names(&mut self) {
+        let sources: Vec<PathBuf> = self.targets().into_iter().map(|entry| entry.path).collect();
+        if sources.is_empty() {
+            return;
+        }
+
+        let names: String = sources
+            .iter()
+            .map(|source| {
+                format!(
+                    "{}\n",
+                    source.file_name().unwrap_or_default().to_string_lossy()
+                )
+            })
+            .collect();
+        let result = self.temp_dir().and_then(|dir| {
+            let names_file = dir.join("rename.txt");
+            let _ = fs::remove_file(&names_file);
+            fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .mode(0o600)
+                .open(&names_file)?
+                .write_all(names.as_bytes())?;
+            Ok(names_file)
+        });
+        let names_file = match result {
+            Ok(names_file) => names_file,
+            Err(err) => {
+                error!("Could not write the names to rename: {err}");
+                return;
+            }
+        };
+
+        self.command = Some(opener::shell_command("$EDITOR {}", &names_file));
+        self.pending_rename = Some((sources, names_file));
+    }
+
+    /// Validates renaming `sources` to `names` and asks for confirmation, or
+    /// lists the problems found.
+    fn plan_renames(&mut self, sources: &[PathBuf], names: &[String]) {
+        match rename::plan(sources, names) {
+            Ok(renames) if renames.is_empty() => info!("Nothing to rename"),
+            Ok(renames) => self.confirm_action = ConfirmAction::Rename(renames),
+            Err(failures) => {
+                self.batch_report = Some(BatchReport {
+                    op: "Rename".to_string(),
+                    succeeded: 0,
+                    failures,
+                })
+            }
+        }
+    }
+
+    fn open_permissions(&mut self, kind: EditorKind) {
+        let targets = self.targets();
+        if targets.is_empty() {
+            return;
+        }
+        self.permissions = Some(PermissionsEditor::new(kind, targets, &self.users));
+        self.input_mode = InputMode::Permissions;
+    }
+
+    fn submit_prompt(&mut self) {
+        let Some((kind, text)) = self.prompt.take() else {
+            return;
+        };
+
+        let result = match kind {
+            PromptKind::Batch(batch) => batch
+                .into_op(&text, self.local_dir())
+                .map(|op| self.confirm_action = ConfirmAction::Batch(op, self.targets())),
+            PromptKind::Bookmark | PromptKind::OpenWith if text.trim().is_empty() => {
+                Err("Input is empty".to_string())
+            }
+            PromptKind::OpenWith => {
+                self.input_mode = InputMode::Modify;
+                self.open_file(Some(text.trim()));
+                Ok(())
+            }
+            PromptKind::Rename => {
+                let sources: Vec<PathBuf> = self
+                    .highlighted_entry()
+                    .map(|entry| entry.path.clone())
+                    .into_iter()
+                    .collect();
+                self.plan_renames(&sources, std::slice::from_ref(&text));
+                if let ConfirmAction::Rename(_) = self.confirm_action {
+                    if let Err(err) = self.confirm_action() {
+                        error!("Could not rename: {err}");
+                    }
+                }
+                Ok(())
+            }
+            PromptKind::PatternRename => Pattern::parse(&text).map(|pattern| {
+                let sources: Vec<PathBuf> =
+                    self.targets().into_iter().map(|entry| entry.path).collect();
+                let names: Vec<String> = sources
+                    .iter()
+                    .enumerate()
+                    .map(|(index, source)| {
+                        let name = source.file_name().unwrap_or_default().to_string_lossy();
+                        pattern.apply(&name, index)
+                    })
+                    .collect();
+                self.plan_renames(&sources, &names);
+            }),
+            PromptKind::Bookmark => {
+                self.bookmarks.add(text.trim(), &self.open_path);
+                Ok(())
+            }
+        };
+
+        match result {
+            Ok(()) => self.input_mode = InputMode::Modify,
+            Err(err) => {
+                self.prompt = Some((kind, text));
+                self.prompt_error = Some(err);
+            }
+        }
+    }
+
+    fn sync_input(&mut self) {
+        self.input.set(&self.open_path);
+    }
+
+    /// Opens `path` and records the directory we left in the history.
+    fn navigate(&mut self, path: String) {
+        let previous = self.open_path.clone();
+        self.read_path(path);
+
+        if self.record_visit(previous) {
+            self.select_state.select(Some(0));
+            self.sync_input();
+        }
+    }
+
+    /// Records leaving `previous` for the open directory in the history and
+    /// the frecency ranking. Returns whether the directory changed.
+    fn record_visit(&mut self, previous: String) -> bool {
+        if self.open_path == previous {
+            return false;
+        }
+        self.history.visit(previous);
+        self.frecency.visit(&self.open_path);
+        true
+    }
+
+    /// Opens the directory holding `path` and highlights it there.
+    fn reveal(&mut self, path: &path::Path) {
+        let Some(parent) = path.parent() else { return };
+        self.navigate(parent.display().to_string());
+        match self.entries.iter().position(|entry| entry.path == path) {
+            Some(index) => self.select_state.select(Some(index)),
+            None => info!("{} is hidden by the filter", path.display()),
+        }
+    }
+
+    fn open_picker(&mut self, kind: PickerKind) {
+        self.picker_items = match kind {
+            PickerKind::Bookmarks => self
+                .bookmarks
+                .entries
+                .iter()
+                .map(|(name, path)| (name.clone(), path.clone()))
+                .collect(),
+            PickerKind::Recent => self
+                .frecency
+                .ranked()
+                .into_iter()
+                .map(|(path, score)| (format!("{score:>7.1}"), path))
+                .collect(),
+        };
+        self.picker_state = ListState::default().with_selected(Some(0));
+        self.input_mode = InputMode::Picker(kind);
+    }
+
+    fn render_picker(&mut self, kind: PickerKind, frame: &mut Frame, area: Rect, style: Style) {
+        let title = match kind {
+            PickerKind::Bookmarks => "Bookmarks (Enter - open, d - delete, Esc - close)",
+            PickerKind::Recent => "Recent directories (Enter - open, Esc - close)",
+        };
+        let items: Vec<String> = self
+            .picker_items
+            .iter()
+            .map(|(label, path)| format!("{label:<16} {path}"))
+            .collect();
+        let list = List::new(items)
+            .style(style)
+            .highlight_style(Style::default().bg(Color::Green).fg(Color::White))
+            .block(Block::bordered().title(title));
+
+        let [_, picker_area, _] = Layout::vertical([
+            Constraint::Percentage(20),
+            Constraint::Percentage(60),
+            Constraint::Percentage(20),
+        ])
+        .areas(area);
+        frame.render_widget(Clear, picker_area);
+        frame.render_stateful_widget(list, picker_area, &mut self.picker_state);
+    }
+
+    fn confirm_action(&mut self) -> Result<(), io::Error> {
+        let action = std::mem::replace(&mut self.confirm_action, ConfirmAction::None);
+
+        let report = match action {
+            // Local copies and moves run in the background.
+            ConfirmAction::Batch(op @ (BatchOp::Copy(_) | BatchOp::Move(_)), targets)
+                if self.vfs.archive().is_none() && !self.vfs.read_only() =>
+            {
+                let (moving, dest) = match op {
+                    BatchOp::Move(dest) => (true, dest),
+                    BatchOp::Copy(dest) => (false, dest),
+                    _ => unreachable!(),
+                };
+                self.transfers.push(Transfer {
+                    moving,
+                    sources: targets.into_iter().map(|entry| entry.path).collect(),
+                    dest,
+                    follow_links: self.follow_links,
+                });
+                self.marked.clear();
+                self.visual_anchor = None;
+                return Ok(());
+            }
+            ConfirmAction::Batch(op, targets) => {
+                let open_path = path::Path::new(&self.open_path);
+                batch::run(
+                    &op,
+                    &targets,
+                    self.vfs.as_ref(),
+                    self.follow_links,
+                    open_path,
+                )
+            }
+            ConfirmAction::Rename(renames) => rename::apply(&renames),
+            ConfirmAction::GitDiscard(targets) => {
+                let Some(git) = &self.git else {
+                    return Ok(());
+                };
+                let paths: Vec<PathBuf> = targets.into_iter().map(|entry| entry.path).collect();
+                git.discard(&paths)
+            }
+            ConfirmAction::None => return Ok(()),
+        };
+        self.marked.clear();
+        self.visual_anchor = None;
+        self.read_path(self.open_path.clone());
+
+        if !report.failures.is_empty() {
+            let failed = report.failures.len();
+            let total = failed + report.succeeded;
+            self.batch_report = Some(report);
+            return Err(io::Error::other(format!(
+                "{failed} of {total} items failed"
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn cancel_action(&mut self) {
+        self.confirm_action = ConfirmAction::None
+    }
+}
+
+/// Creates a directory with an unpredictable name that only the current
+/// user can access, so other users can't plant or swap files in it.
+fn private_temp_dir() -> io::Result<PathBuf> {
+    let template = env::temp_dir().join("rustor-XXXXXX");
+    let mut template = CString::new(template.into_os_string().into_vec())?.into_bytes_with_nul();
+    // SAFETY: mkdtemp replaces the Xs of the NUL-terminated template in place.
+    if unsafe { libc::mkdtemp(template.as_mut_ptr().cast()) }.is_null() {
+        return Err(io::Error::last_os_error());
+    }
+    template.pop();
+    Ok(PathBuf::from(OsString::from_vec(template)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::vfs::{MemoryFs, ReadOnly};
+    use super::*;
+
+    fn names(app: &FileTreeApp) -> Vec<String> {
+        let mut names: Vec<String> = app.entries.iter().map(FileEntry::name).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_changes_through_vfs() {
+        let mut app = FileTreeApp::new();
+        app.vfs = Box::new(
+            MemoryFs::new()
+                .with_file("/mem/a.txt", b"a")
+                .with_file("/mem/b.txt", b"b"),
+        );
+        app.read_path("/mem".to_string());
+        assert_eq!(names(&app), ["a.txt", "b.txt"]);
+
+        app.input.set("/mem/new");
+        app.update(&FileTreeMsg::CreateDir);
+        assert!(app
+            .vfs
+            .metadata(path::Path::new("/mem/new"))
+            .unwrap()
+            .is_dir());
+        assert_eq!(names(&app), ["a.txt", "b.txt", "new"]);
+
+        let index = app.entries.iter().position(|entry| entry.name() == "a.txt");
+        app.select_state.select(index);
+        app.update(&FileTreeMsg::Delete);
+        app.update(&FileTreeMsg::Confirm);
+        assert!(app.vfs.metadata(path::Path::new("/mem/a.txt")).is_err());
+        assert_eq!(names(&app), ["b.txt", "new"]);
+    }
+
+    #[test]
+    fn test_read_only_refuses_changes() {
+        let mut app = FileTreeApp::new();
+        app.read_only = true;
+        app.vfs = Box::new(ReadOnly(Box::new(
+            MemoryFs::new().with_file("/mem/a.txt", b"a"),
+        )));
+        app.read_path("/mem".to_string());
+        app.select_state.select(Some(0));
+
+        app.input.set("/mem/new");
+        app.update(&FileTreeMsg::CreateDir);
+        for msg in [FileTreeMsg::Delete, FileTreeMsg::Copy, FileTreeMsg::Extract] {
+            app.update(&msg);
+            assert!(matches!(app.confirm_action, ConfirmAction::None));
+            assert!(app.prompt.is_none());
+        }
+        assert_eq!(names(&app), ["a.txt"]);
+    }
+}
@@ -0,0 +1,384 @@
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
+    widgets::{Block, Paragraph, Row, Table, TableState},
+    Frame,
+};
+
+use std::{
+    collections::HashMap,
+    ffi::CString,
+    fs, io,
+    mem::MaybeUninit,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::listing::format_size;
+
+/// How long opening or refreshing the view waits for the usage of every
+/// mount before showing what it has.
+const STAT_WAIT: Duration = Duration::from_millis(200);
+/// After this long a mount, like a stale NFS share, is listed as hung.
+const STAT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Space and inode counts of a mounted filesystem, as `df` reports them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Usage {
+    pub total: u64,
+    pub used: u64,
+    /// The space available to unprivileged users.
+    pub free: u64,
+    pub inodes: u64,
+    pub inodes_free: u64,
+}
+
+impl Usage {
+    // The statvfs fields are narrower on 32-bit targets. Some filesystems,
+    // like a few FUSE ones, report more free blocks than they have.
+    #[allow(clippy::unnecessary_cast)]
+    fn of(path: &Path) -> io::Result<Usage> {
+        let path = CString::new(path.as_os_str().as_bytes())?;
+        let mut stat = MaybeUninit::<libc::statvfs>::uninit();
+        // SAFETY: the path is NUL terminated and statvfs fills `stat` on
+        // success.
+        if unsafe { libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: statvfs succeeded.
+        let stat = unsafe { stat.assume_init() };
+
+        let block = stat.f_frsize as u64;
+        Ok(Usage {
+            total: stat.f_blocks as u64 * block,
+            used: (stat.f_blocks as u64).saturating_sub(stat.f_bfree as u64) * block,
+            free: stat.f_bavail as u64 * block,
+            inodes: stat.f_files as u64,
+            inodes_free: stat.f_ffree as u64,
+        })
+    }
+
+    /// The used share of the space users can have, like `df`'s Use%.
+    fn percent(&self) -> f64 {
+        match self.used + self.free {
+            0 => 0.0,
+            usable => self.used as f64 * 100.0 / usable as f64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mount {
+    pub device: String,
+    pub mount_point: PathBuf,
+    pub fs_type: String,
+    pub options: String,
+    pub usage: Option<Usage>,
+}
+
+/// Undoes the octal escapes the kernel uses for spaces and other special
+/// characters in `/proc/self/mountinfo`, like `\040`.
+fn unescape(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut result = vec![];
+    let mut index = 0;
+    while index < bytes.len() {
+        let escape = bytes.get(index + 1..index + 4);
+        match escape
+            .and_then(|digits| u8::from_str_radix(std::str::from_utf8(digits).ok()?, 8).ok())
+        {
+            Some(byte) if bytes[index] == b'\\' => {
+                result.push(byte);
+                index += 4;
+            }
+            _ => {
+                result.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&result).to_string()
+}
+
+/// Parses the lines of `/proc/self/mountinfo`:
+///
+/// `36 35 98:0 /root /mnt rw,noatime master:1 - ext3 /dev/sda1 rw,errors=continue`
+///
+/// The optional fields before the `-` vary, so the fields after it are
+/// found by the separator.
+pub fn parse_mountinfo(text: &str) -> Vec<Mount> {
+    text.lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(' ').collect();
+            let separator = fields.iter().position(|field| *field == "-")?;
+            let (fs_type, device, super_options) = match &fields[separator + 1..] {
+                [fs_type, device, super_options, ..] => (fs_type, device, super_options),
+                _ => return None,
+            };
+
+            let mut options: Vec<&str> = fields.get(5)?.split(',').collect();
+            for option in super_options.split(',') {
+                if !options.contains(&option) {
+                    options.push(option);
+                }
+            }
+            Some(Mount {
+                device: unescape(device),
+                mount_point: PathBuf::from(unescape(fields.get(4)?)),
+                fs_type: fs_type.to_string(),
+                options: options.join(","),
+                usage: None,
+            })
+        })
+        .collect()
+}
+
+/// The mounted filesystems, without their usage.
+pub fn read_mounts() -> io::Result<Vec<Mount>> {
+    let text = fs::read_to_string("/proc/self/mountinfo")?;
+    Ok(parse_mountinfo(&text))
+}
+
+/// The usage read for a mount point, `None` when statvfs failed.
+type UsageResult = (PathBuf, Option<Usage>);
+
+#[derive(Debug, Default)]
+pub struct MountView {
+    mounts: Vec<Mount>,
+    pub table_state: TableState,
+    /// Whether pseudo filesystems without space, like proc, are listed.
+    pub show_all: bool,
+    pub error: Option<String>,
+    /// Mount points whose usage is being read, with when that started.
+    /// statvfs can hang on an unreachable network filesystem, so each runs
+    /// on its own thread and a hung one is never asked again.
+    pending: HashMap<PathBuf, Instant>,
+    usage_channel: Option<(Sender<UsageResult>, Receiver<UsageResult>)>,
+}
+
+impl MountView {
+    pub fn refresh(&mut self) {
+        match read_mounts() {
+            Ok(mut mounts) => {
+                // Keep the last known usage until it is read again.
+                for mount in &mut mounts {
+                    mount.usage = self
+                        .mounts
+                        .iter()
+                        .find(|old| old.mount_point == mount.mount_point)
+                        .and_then(|old| old.usage);
+                }
+                self.mounts = mounts;
+                self.error = None;
+            }
+            Err(err) => self.error = Some(err.to_string()),
+        }
+        if self.table_state.selected().is_none() {
+            self.table_state.select(Some(0));
+        }
+
+        let (sender, _) = self.usage_channel.get_or_insert_with(mpsc::channel);
+        for mount in &self.mounts {
+            if self.pending.contains_key(&mount.mount_point) {
+                continue;
+            }
+            let path = mount.mount_point.clone();
+            self.pending.insert(path.clone(), Instant::now());
+            let sender = sender.clone();
+            thread::spawn(move || {
+                let usage = Usage::of(&path).ok();
+                let _ = sender.send((path, usage));
+            });
+        }
+
+        // Usually every answer arrives right away.
+        let deadline = Instant::now() + STAT_WAIT;
+        while !self.pending.is_empty() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+            self.poll();
+        }
+    }
+
+    /// Picks up the usage read so far.
+    pub fn poll(&mut self) {
+        let Some((_, receiver)) = &self.usage_channel else {
+            return;
+        };
+        while let Ok((path, usage)) = receiver.try_recv() {
+            self.pending.remove(&path);
+            for mount in &mut self.mounts {
+                if mount.mount_point == path {
+                    mount.usage = usage;
+                }
+            }
+        }
+    }
+
+    /// Pseudo filesystems are hidden unless `show_all` is on, but mounts
+    /// that don't answer are always listed.
+    fn visible(&self) -> Vec<&Mount> {
+        self.mounts
+            .iter()
+            .filter(|mount| {
+                self.show_all
+                    || mount.usage.is_some_and(|usage| usage.total > 0)
+                    || self.hung(mount)
+            })
+            .collect()
+    }
+
+    fn hung(&self, mount: &Mount) -> bool {
+        self.pending
+            .get(&mount.mount_point)
+            .is_some_and(|started| started.elapsed() >= STAT_TIMEOUT)
+    }
+
+    pub fn toggle_all(&mut self) {
+        self.show_all = !self.show_all;
+        self.table_state.select(Some(0));
+    }
+
+    pub fn highlighted(&self) -> Option<PathBuf> {
+        let index = self.table_state.selected()?;
+        self.visible()
+            .get(index)
+            .map(|mount| mount.mount_point.clone())
+    }
+}
+
+pub fn render(view: &mut MountView, frame: &mut Frame, area: Rect, style: Style) {
+    let [header_area, table_area] =
+        Layout::vertical([Constraint::Length(3), Constraint::Min(1)]).areas(area);
+
+    let visible = view.visible();
+    let status = match &view.error {
+        Some(err) => format!("Could not read mounts: {err}"),
+        None => "l - open, a - show all, r - refresh, Esc - close".to_string(),
+    };
+    let header = Paragraph::new(status)
+        .style(style)
+        .block(Block::bordered().title(format!(
+            "Mounted filesystems ({}{})",
+            visible.len(),
+            if view.show_all { ", all" } else { "" }
+        )));
+    frame.render_widget(header, header_area);
+
+    let rows: Vec<Row> = visible
+        .iter()
+        .map(|mount| {
+            let usage = match mount.usage {
+                Some(usage) => [
+                    format_size(usage.total),
+                    format_size(usage.used),
+                    format_size(usage.free),
+                    format!("{:.0}%", usage.percent()),
+                    format!(
+                        "{}/{}",
+                        usage.inodes.saturating_sub(usage.inodes_free),
+                        usage.inodes
+                    ),
+                ],
+                None if view.hung(mount) => [
+                    "hung".to_string(),
+                    Default::default(),
+                    Default::default(),
+                    Default::default(),
+                    Default::default(),
+                ],
+                None => Default::default(),
+            };
+            let full = mount.usage.is_some_and(|usage| usage.percent() >= 90.0);
+            let row = Row::new(
+                [
+                    mount.device.clone(),
+                    mount.mount_point.display().to_string(),
+                    mount.fs_type.clone(),
+                ]
+                .into_iter()
+                .chain(usage)
+                .chain([mount.options.clone()]),
+            );
+            if full {
+                row.fg(Color::Red)
+            } else {
+                row
+            }
+        })
+        .collect();
+
+    let widths = [
+        Constraint::Percentage(16),
+        Constraint::Percentage(20),
+        Constraint::Length(10),
+        Constraint::Length(7),
+        Constraint::Length(7),
+        Constraint::Length(7),
+        Constraint::Length(5),
+        Constraint::Length(19),
+        Constraint::Fill(1),
+    ];
+    let header = Row::new([
+        "Device",
+        "Mounted on",
+        "Type",
+        "Size",
+        "Used",
+        "Avail",
+        "Use%",
+        "Inodes",
+        "Options",
+    ])
+    .bold();
+    let table = Table::new(rows, widths)
+        .header(header)
+        .style(style)
+        .highlight_style(Style::default().bg(Color::Green).fg(Color::White))
+        .block(Block::bordered());
+    frame.render_stateful_widget(table, table_area, &mut view.table_state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mountinfo() {
+        let text = "\
+36 35 98:0 /mnt1 /mnt/my\\040disk rw,noatime master:1 - ext4 /dev/sda1 rw,errors=continue
+23 28 0:22 / /proc rw,relatime - proc proc rw
+broken line";
+        let mounts = parse_mountinfo(text);
+        assert_eq!(mounts.len(), 2);
+        assert_eq!(mounts[0].mount_point, PathBuf::from("/mnt/my disk"));
+        assert_eq!(mounts[0].device, "/dev/sda1");
+        assert_eq!(mounts[0].fs_type, "ext4");
+        assert_eq!(mounts[0].options, "rw,noatime,errors=continue");
+        assert_eq!(mounts[1].options, "rw,relatime");
+    }
+
+    #[test]
+    fn test_refresh() {
+        let mut view = MountView::default();
+        view.refresh();
+        let deadline = Instant::now() + STAT_TIMEOUT;
+        while view
+            .mounts
+            .iter()
+            .any(|mount| mount.mount_point == Path::new("/") && mount.usage.is_none())
+            && Instant::now() < deadline
+        {
+            thread::sleep(Duration::from_millis(10));
+            view.poll();
+        }
+        let root = view
+            .mounts
+            .iter()
+            .find(|mount| mount.mount_point == Path::new("/"))
+            .unwrap();
+        assert!(root.usage.is_some_and(|usage| usage.total > 0));
+    }
+}
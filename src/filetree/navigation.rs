@@ -0,0 +1,271 @@
+use directories::ProjectDirs;
+use log::error;
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const BOOKMARKS_FILE: &str = "bookmarks";
+const RECENT_FILE: &str = "recent";
+const MAX_RANK_SUM: f64 = 10_000.0;
+
+pub fn config_dir() -> Option<PathBuf> {
+    ProjectDirs::from("", "", "rustor").map(|dirs| dirs.config_dir().to_path_buf())
+}
+
+pub fn read_config(name: &str) -> String {
+    config_dir()
+        .and_then(|dir| fs::read_to_string(dir.join(name)).ok())
+        .unwrap_or_default()
+}
+
+fn write_config(name: &str, content: String) {
+    let Some(dir) = config_dir() else { return };
+    let result = fs::create_dir_all(&dir).and_then(|_| fs::write(dir.join(name), content));
+    if let Err(err) = result {
+        error!("Could not save {name} in {}: {err}", dir.display());
+    }
+}
+
+/// Escapes the characters that separate fields and records in the saved
+/// files, so any path can be stored on one line.
+fn escape(field: &str) -> String {
+    let mut escaped = String::with_capacity(field.len());
+    for c in field.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(field: &str) -> String {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Bookmarks {
+    pub entries: BTreeMap<String, String>,
+}
+
+impl Bookmarks {
+    pub fn load() -> Bookmarks {
+        Bookmarks::parse(&read_config(BOOKMARKS_FILE))
+    }
+
+    fn parse(text: &str) -> Bookmarks {
+        let entries = text
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .map(|(name, path)| (unescape(name), unescape(path)))
+            .collect();
+        Bookmarks { entries }
+    }
+
+    fn format(&self) -> String {
+        self.entries
+            .iter()
+            .map(|(name, path)| format!("{}\t{}\n", escape(name), escape(path)))
+            .collect()
+    }
+
+    fn save(&self) {
+        write_config(BOOKMARKS_FILE, self.format());
+    }
+
+    pub fn add(&mut self, name: &str, path: &str) {
+        self.entries.insert(name.to_string(), path.to_string());
+        self.save();
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.entries.remove(name);
+        self.save();
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    back: Vec<String>,
+    forward: Vec<String>,
+}
+
+impl History {
+    pub fn visit(&mut self, previous: String) {
+        if self.back.last() != Some(&previous) {
+            self.back.push(previous);
+        }
+        self.forward.clear();
+    }
+
+    pub fn back(&mut self, current: String) -> Option<String> {
+        let target = self.back.pop()?;
+        self.forward.push(current);
+        Some(target)
+    }
+
+    pub fn forward(&mut self, current: String) -> Option<String> {
+        let target = self.forward.pop()?;
+        self.back.push(current);
+        Some(target)
+    }
+}
+
+/// Directory ranking in the style of zoxide: every visit bumps the rank and
+/// the score is weighted by how recently the directory was last visited.
+#[derive(Debug, Clone, Default)]
+pub struct Frecency {
+    dirs: HashMap<String, (f64, u64)>,
+}
+
+impl Frecency {
+    pub fn load() -> Frecency {
+        Frecency::parse(&read_config(RECENT_FILE))
+    }
+
+    fn parse(text: &str) -> Frecency {
+        let dirs = text
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split('\t');
+                let path = unescape(fields.next()?);
+                let rank = fields.next()?.parse().ok()?;
+                let last = fields.next()?.parse().ok()?;
+                Some((path, (rank, last)))
+            })
+            .collect();
+        Frecency { dirs }
+    }
+
+    fn format(&self) -> String {
+        self.dirs
+            .iter()
+            .map(|(path, (rank, last))| format!("{}\t{rank}\t{last}\n", escape(path)))
+            .collect()
+    }
+
+    fn save(&self) {
+        write_config(RECENT_FILE, self.format());
+    }
+
+    pub fn visit(&mut self, path: &str) {
+        self.record(path, now());
+        self.save();
+    }
+
+    fn record(&mut self, path: &str, time: u64) {
+        let entry = self.dirs.entry(path.to_string()).or_insert((0.0, time));
+        entry.0 += 1.0;
+        entry.1 = time;
+
+        let total: f64 = self.dirs.values().map(|(rank, _)| rank).sum();
+        if total > MAX_RANK_SUM {
+            self.dirs.values_mut().for_each(|(rank, _)| *rank *= 0.9);
+            self.dirs.retain(|_, (rank, _)| *rank >= 1.0);
+        }
+    }
+
+    fn score(rank: f64, last: u64, time: u64) -> f64 {
+        let age = time.saturating_sub(last);
+        let weight = match age {
+            0..=3_599 => 4.0,
+            3_600..=86_399 => 2.0,
+            86_400..=604_799 => 0.5,
+            _ => 0.25,
+        };
+        rank * weight
+    }
+
+    pub fn ranked(&self) -> Vec<(String, f64)> {
+        let time = now();
+        let mut ranked: Vec<(String, f64)> = self
+            .dirs
+            .iter()
+            .map(|(path, (rank, last))| (path.clone(), Frecency::score(*rank, *last, time)))
+            .collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ranked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history() {
+        let mut history = History::default();
+        history.visit("/a".to_string());
+        history.visit("/b".to_string());
+
+        assert_eq!(history.back("/c".to_string()), Some("/b".to_string()));
+        assert_eq!(history.forward("/b".to_string()), Some("/c".to_string()));
+        assert_eq!(history.forward("/c".to_string()), None);
+    }
+
+    #[test]
+    fn test_frecency_prefers_recent_visits() {
+        let mut frecency = Frecency::default();
+        let time = now();
+        frecency.record("/old", time - 30 * 86_400);
+        frecency.record("/old", time - 30 * 86_400);
+        frecency.record("/new", time);
+
+        let ranked = frecency.ranked();
+        assert_eq!(ranked[0].0, "/new");
+        assert_eq!(ranked[1].0, "/old");
+    }
+
+    #[test]
+    fn test_save_special_paths() {
+        let odd = "/tmp/tab\there/new\nline/back\\slash\\t\r";
+        assert_eq!(unescape(&escape(odd)), odd);
+
+        let mut bookmarks = Bookmarks::default();
+        bookmarks
+            .entries
+            .insert("a\tname".to_string(), odd.to_string());
+        bookmarks
+            .entries
+            .insert("plain".to_string(), "/home".to_string());
+        let text = bookmarks.format();
+        assert_eq!(text.lines().count(), 2);
+        assert_eq!(Bookmarks::parse(&text).entries, bookmarks.entries);
+
+        let mut frecency = Frecency::default();
+        frecency.record(odd, 100);
+        frecency.record("/home", 200);
+        let text = frecency.format();
+        assert_eq!(text.lines().count(), 2);
+        assert_eq!(Frecency::parse(&text).dirs, frecency.dirs);
+    }
+}
@@ -0,0 +1,205 @@
+use globset::{Glob, GlobMatcher};
+use log::error;
+
+use std::{
+    env, fs,
+    io::{self, Read},
+    os::unix::fs::{FileTypeExt, OpenOptionsExt},
+    path::Path,
+    process::Command,
+};
+
+use super::navigation::read_config;
+
+const OPENERS_FILE: &str = "openers";
+const SNIFF_LENGTH: u64 = 8 * 1024;
+
+/// Rules used after the configured ones, so every file opens somehow.
+const DEFAULT_RULES: &str = "
+*.log less {}
+mime:text/* $EDITOR {}
+mime:inode/x-empty $EDITOR {}
+* xdg-open {}
+";
+
+#[derive(Debug, Clone)]
+struct Rule {
+    /// Whether `glob` is matched against the sniffed MIME type instead of
+    /// the file name.
+    mime: bool,
+    glob: GlobMatcher,
+    command: String,
+}
+
+impl Rule {
+    fn parse(line: &str) -> Option<Rule> {
+        let (pattern, command) = line.trim().split_once(char::is_whitespace)?;
+        let (mime, pattern) = match pattern.strip_prefix("mime:") {
+            Some(pattern) => (true, pattern),
+            None => (false, pattern),
+        };
+
+        match Glob::new(pattern) {
+            Ok(glob) => Some(Rule {
+                mime,
+                glob: glob.compile_matcher(),
+                command: command.trim().to_string(),
+            }),
+            Err(err) => {
+                error!("Invalid opener pattern {pattern:?}: {err}");
+                None
+            }
+        }
+    }
+}
+
+/// Picks the program a file is opened with. Rules are read from the
+/// `openers` config file as `pattern command` lines, where the pattern is a
+/// glob on the file name or `mime:` followed by a glob on the MIME type, and
+/// `{}` in the command stands for the file.
+#[derive(Debug, Clone, Default)]
+pub struct Openers {
+    rules: Vec<Rule>,
+}
+
+impl Openers {
+    pub fn load() -> Openers {
+        let config = read_config(OPENERS_FILE);
+        let rules = config
+            .lines()
+            .chain(DEFAULT_RULES.lines())
+            .filter(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+            .filter_map(Rule::parse)
+            .collect();
+        Openers { rules }
+    }
+
+    pub fn command_for(&self, path: &Path) -> Option<Command> {
+        let name = path.file_name()?;
+        let mime = sniff_mime(path);
+        let rule = self.rules.iter().find(|rule| {
+            if rule.mime {
+                rule.glob.is_match(&mime)
+            } else {
+                rule.glob.is_match(name)
+            }
+        })?;
+        Some(shell_command(&rule.command, path))
+    }
+}
+
+/// Runs `command` through the shell with the file passed as `$1`, so paths
+/// never need quoting.
+pub fn shell_command(command: &str, path: &Path) -> Command {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let command = command.replace("$EDITOR", &editor);
+    let script = if command.contains("{}") {
+        command.replace("{}", "\"$1\"")
+    } else {
+        format!("{command} \"$1\"")
+    };
+
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(script).arg("rustor").arg(path);
+    shell
+}
+
+/// Guesses the MIME type from magic numbers, falling back to `text/plain`
+/// for anything that looks like UTF-8 text. Only regular files are read,
+/// since reading a FIFO or a device could block forever.
+pub fn sniff_mime(path: &Path) -> String {
+    let file_type = match fs::metadata(path) {
+        Ok(metadata) => metadata.file_type(),
+        Err(_) => return "application/octet-stream".to_string(),
+    };
+    let special = if file_type.is_dir() {
+        Some("inode/directory")
+    } else if file_type.is_fifo() {
+        Some("inode/fifo")
+    } else if file_type.is_char_device() {
+        Some("inode/chardevice")
+    } else if file_type.is_block_device() {
+        Some("inode/blockdevice")
+    } else if file_type.is_socket() {
+        Some("inode/socket")
+    } else {
+        None
+    };
+    if let Some(mime) = special {
+        return mime.to_string();
+    }
+
+    let mut head = vec![];
+    // Not blocking guards against the file having been replaced meanwhile.
+    let read = fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(path)
+        .and_then(|file| match file.metadata()?.is_file() {
+            true => file.take(SNIFF_LENGTH).read_to_end(&mut head),
+            false => Err(io::Error::other("not a regular file")),
+        });
+    match read {
+        Ok(0) => return "inode/x-empty".to_string(),
+        Ok(_) => {}
+        Err(_) => return "application/octet-stream".to_string(),
+    }
+
+    if let Some(kind) = infer::get(&head) {
+        return kind.mime_type().to_string();
+    }
+
+    // A multi-byte character may be cut off at the end of the sniffed part.
+    let text = match std::str::from_utf8(&head) {
+        Ok(_) => true,
+        Err(err) => err.error_len().is_none(),
+    };
+    if text && !head.contains(&0) {
+        "text/plain".to_string()
+    } else {
+        "application/octet-stream".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rule() {
+        let rule = Rule::parse("mime:image/* feh {}").unwrap();
+        assert!(rule.mime);
+        assert!(rule.glob.is_match("image/png"));
+        assert_eq!(rule.command, "feh {}");
+
+        let rule = Rule::parse("*.log  less -R").unwrap();
+        assert!(!rule.mime);
+        assert!(rule.glob.is_match("server.log"));
+        assert_eq!(rule.command, "less -R");
+
+        assert!(Rule::parse("*.log").is_none());
+    }
+
+    #[test]
+    fn test_sniff_mime() {
+        let dir = env::temp_dir().join(format!("rustor-opener-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("text"), "hello\n").unwrap();
+        fs::write(dir.join("empty"), "").unwrap();
+        let fifo =
+            std::ffi::CString::new(dir.join("fifo").into_os_string().into_encoded_bytes()).unwrap();
+        // SAFETY: `fifo` is a valid NUL-terminated path.
+        assert_eq!(unsafe { libc::mkfifo(fifo.as_ptr(), 0o600) }, 0);
+
+        assert_eq!(sniff_mime(&dir.join("text")), "text/plain");
+        assert_eq!(sniff_mime(&dir.join("empty")), "inode/x-empty");
+        assert_eq!(sniff_mime(&dir), "inode/directory");
+        // Reading the FIFO would block without a writer.
+        assert_eq!(sniff_mime(&dir.join("fifo")), "inode/fifo");
+        assert_eq!(sniff_mime(Path::new("/dev/null")), "inode/chardevice");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,420 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    widgets::{Block, Clear, List, ListState},
+    Frame,
+};
+
+use std::{env, fs, path::Path};
+
+const HISTORY_LENGTH: usize = 100;
+const POPUP_HEIGHT: usize = 10;
+
+/// A readline-style edit of the path input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edit {
+    Insert(char),
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Home,
+    End,
+    WordLeft,
+    WordRight,
+    /// Deletes back to the start of the path component, like `Ctrl-W`.
+    KillWord,
+    KillToStart,
+    KillToEnd,
+    HistoryPrevious,
+    HistoryNext,
+}
+
+impl Edit {
+    /// The edit for a key, with the usual readline bindings.
+    pub fn from_key(key: KeyEvent) -> Option<Edit> {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        let edit = match key.code {
+            KeyCode::Char('a') if control => Edit::Home,
+            KeyCode::Char('e') if control => Edit::End,
+            KeyCode::Char('w') if control => Edit::KillWord,
+            KeyCode::Char('u') if control => Edit::KillToStart,
+            KeyCode::Char('k') if control => Edit::KillToEnd,
+            KeyCode::Char('b') if alt => Edit::WordLeft,
+            KeyCode::Char('f') if alt => Edit::WordRight,
+            KeyCode::Char(_) if control || alt => return None,
+            KeyCode::Char(c) => Edit::Insert(c),
+            KeyCode::Backspace => Edit::Backspace,
+            KeyCode::Delete => Edit::Delete,
+            KeyCode::Left if control => Edit::WordLeft,
+            KeyCode::Right if control => Edit::WordRight,
+            KeyCode::Left => Edit::Left,
+            KeyCode::Right => Edit::Right,
+            KeyCode::Home => Edit::Home,
+            KeyCode::End => Edit::End,
+            KeyCode::Up => Edit::HistoryPrevious,
+            KeyCode::Down => Edit::HistoryNext,
+            _ => return None,
+        };
+        Some(edit)
+    }
+}
+
+/// The candidates offered when Tab can't complete a unique name.
+#[derive(Debug, Clone)]
+pub struct Completion {
+    pub candidates: Vec<String>,
+    pub state: ListState,
+    /// The byte offset the completed name starts at.
+    start: usize,
+}
+
+/// The "Enter path" input with its cursor, history and completions.
+#[derive(Debug, Default)]
+pub struct PathInput {
+    pub text: String,
+    /// The cursor position in characters.
+    pub cursor: usize,
+    history: Vec<String>,
+    /// The history entry shown and the text typed before browsing history.
+    browsing: Option<(usize, String)>,
+    pub completion: Option<Completion>,
+}
+
+impl PathInput {
+    pub fn set(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.cursor = self.text.chars().count();
+        self.browsing = None;
+        self.completion = None;
+    }
+
+    /// The text with `~` and environment variables expanded.
+    pub fn expanded(&self) -> String {
+        expand(&self.text)
+    }
+
+    fn byte_index(&self, cursor: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(cursor)
+            .map_or(self.text.len(), |(index, _)| index)
+    }
+
+    fn chars(&self) -> Vec<char> {
+        self.text.chars().collect()
+    }
+
+    /// The start of the word before the cursor, where `/` and whitespace
+    /// separate words.
+    fn word_start(&self) -> usize {
+        let chars = self.chars();
+        let separator = |c: char| c == '/' || c.is_whitespace();
+        let mut cursor = self.cursor;
+        while cursor > 0 && separator(chars[cursor - 1]) {
+            cursor -= 1;
+        }
+        while cursor > 0 && !separator(chars[cursor - 1]) {
+            cursor -= 1;
+        }
+        cursor
+    }
+
+    fn word_end(&self) -> usize {
+        let chars = self.chars();
+        let separator = |c: char| c == '/' || c.is_whitespace();
+        let mut cursor = self.cursor;
+        while cursor < chars.len() && separator(chars[cursor]) {
+            cursor += 1;
+        }
+        while cursor < chars.len() && !separator(chars[cursor]) {
+            cursor += 1;
+        }
+        cursor
+    }
+
+    /// Removes the characters between two cursor positions.
+    fn remove(&mut self, from: usize, to: usize) {
+        let (start, end) = (self.byte_index(from), self.byte_index(to));
+        self.text.replace_range(start..end, "");
+        self.cursor = from;
+    }
+
+    /// Applies `edit` and returns whether the text changed.
+    pub fn edit(&mut self, edit: Edit) -> bool {
+        self.completion = None;
+        let length = self.text.chars().count();
+        let before = self.text.clone();
+
+        match edit {
+            Edit::Insert(c) => {
+                let index = self.byte_index(self.cursor);
+                self.text.insert(index, c);
+                self.cursor += 1;
+            }
+            Edit::Backspace if self.cursor > 0 => self.remove(self.cursor - 1, self.cursor),
+            Edit::Delete if self.cursor < length => self.remove(self.cursor, self.cursor + 1),
+            Edit::Backspace | Edit::Delete => {}
+            Edit::Left => self.cursor = self.cursor.saturating_sub(1),
+            Edit::Right => self.cursor = (self.cursor + 1).min(length),
+            Edit::Home => self.cursor = 0,
+            Edit::End => self.cursor = length,
+            Edit::WordLeft => self.cursor = self.word_start(),
+            Edit::WordRight => self.cursor = self.word_end(),
+            Edit::KillWord => self.remove(self.word_start(), self.cursor),
+            Edit::KillToStart => self.remove(0, self.cursor),
+            Edit::KillToEnd => self.remove(self.cursor, length),
+            Edit::HistoryPrevious => self.browse(true),
+            Edit::HistoryNext => self.browse(false),
+        }
+
+        if !matches!(edit, Edit::HistoryPrevious | Edit::HistoryNext) && self.text != before {
+            self.browsing = None;
+        }
+        self.text != before
+    }
+
+    fn browse(&mut self, older: bool) {
+        let index = match (self.browsing.as_ref(), older) {
+            (None, true) => self.history.len().checked_sub(1),
+            (None, false) => None,
+            (Some((index, _)), true) => Some(index.saturating_sub(1)),
+            (Some((index, _)), false) => Some(index + 1),
+        };
+
+        match index {
+            Some(index) if index < self.history.len() => {
+                let typed = match self.browsing.take() {
+                    Some((_, typed)) => typed,
+                    None => self.text.clone(),
+                };
+                self.text = self.history[index].clone();
+                self.browsing = Some((index, typed));
+            }
+            // Going past the newest entry restores what was typed.
+            Some(_) => {
+                if let Some((_, typed)) = self.browsing.take() {
+                    self.text = typed;
+                }
+            }
+            None => {}
+        }
+        self.cursor = self.text.chars().count();
+    }
+
+    /// Remembers the current text for browsing with the arrow keys.
+    pub fn push_history(&mut self) {
+        self.browsing = None;
+        if self.text.is_empty() || self.history.last() == Some(&self.text) {
+            return;
+        }
+        self.history.push(self.text.clone());
+        if self.history.len() > HISTORY_LENGTH {
+            self.history.remove(0);
+        }
+    }
+
+    /// Completes the path component before the cursor against the entries
+    /// of its directory, relative paths resolving against `base`. A unique
+    /// match is completed directly, otherwise the common prefix is inserted
+    /// and the candidates are shown. Pressing Tab again cycles through them.
+    /// Returns whether the text changed.
+    pub fn complete(&mut self, base: &Path, forward: bool) -> bool {
+        if let Some(completion) = &mut self.completion {
+            let count = completion.candidates.len();
+            let next = match (completion.state.selected(), forward) {
+                (None, true) => 0,
+                (None, false) => count - 1,
+                (Some(index), true) => (index + 1) % count,
+                (Some(index), false) => (index + count - 1) % count,
+            };
+            completion.state.select(Some(next));
+            let (start, candidate) = (completion.start, completion.candidates[next].clone());
+            let end = self.byte_index(self.cursor);
+            self.text.replace_range(start..end, &candidate);
+            self.cursor = self.text[..start + candidate.len()].chars().count();
+            return true;
+        }
+
+        // Complete the expanded text so the candidates can be listed.
+        let end = self.byte_index(self.cursor);
+        let (head, tail) = self.text.split_at(end);
+        let head = expand(head);
+        let tail = tail.to_string();
+        let start = head.rfind('/').map_or(0, |index| index + 1);
+        let (dir, name) = head.split_at(start);
+        let candidates = candidates(&base.join(if dir.is_empty() { "." } else { dir }), name);
+
+        let completed = match candidates.as_slice() {
+            [] => return false,
+            [only] => only.clone(),
+            _ => common_prefix(&candidates),
+        };
+        let changed = completed != name || head != self.text[..end];
+        if candidates.len() > 1 && completed == name {
+            self.completion = Some(Completion {
+                candidates,
+                state: ListState::default(),
+                start,
+            });
+        }
+
+        self.text = format!("{dir}{completed}{tail}");
+        self.cursor = format!("{dir}{completed}").chars().count();
+        changed
+    }
+
+    /// Accepts the highlighted candidate. Returns whether a popup was open.
+    pub fn accept_completion(&mut self) -> bool {
+        self.completion.take().is_some()
+    }
+}
+
+/// Names in `dir` starting with `prefix`, with a `/` after directories.
+/// Hidden entries are only offered when the prefix starts with a dot.
+fn candidates(dir: &Path, prefix: &str) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+    let mut names: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !name.starts_with(prefix) || name.starts_with('.') && !prefix.starts_with('.') {
+                return None;
+            }
+            // Follow symlinks so links to directories complete like them.
+            let is_dir = fs::metadata(entry.path()).is_ok_and(|metadata| metadata.is_dir());
+            Some(if is_dir { format!("{name}/") } else { name })
+        })
+        .collect();
+    names.sort();
+    names
+}
+
+fn common_prefix(names: &[String]) -> String {
+    let Some(first) = names.first() else {
+        return String::new();
+    };
+    let mut prefix = first.as_str();
+    for name in names {
+        while !name.starts_with(prefix) {
+            let mut chars = prefix.chars();
+            chars.next_back();
+            prefix = chars.as_str();
+        }
+    }
+    prefix.to_string()
+}
+
+/// Expands a leading `~` and `$VAR` or `${VAR}` like a shell. Unknown
+/// variables are kept as they are.
+pub fn expand(text: &str) -> String {
+    let home = env::var("HOME").unwrap_or_default();
+    let text = match text.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => format!("{home}{rest}"),
+        _ => text.to_string(),
+    };
+
+    let mut result = String::new();
+    let mut rest = text.as_str();
+    while let Some(start) = rest.find('$') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let (name, skip) = match after.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => ("", 0),
+            },
+            None => {
+                let end = after
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .unwrap_or(after.len());
+                (&after[..end], end)
+            }
+        };
+
+        match env::var(name).ok().filter(|_| !name.is_empty()) {
+            Some(value) => result.push_str(&value),
+            None => result.push_str(&rest[start..start + 1 + skip]),
+        }
+        rest = &after[skip..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Shows the completion candidates below the input.
+pub fn render_completion(input: &mut PathInput, frame: &mut Frame, input_area: Rect, style: Style) {
+    let Some(completion) = &mut input.completion else {
+        return;
+    };
+
+    let height = completion.candidates.len().min(POPUP_HEIGHT) as u16 + 2;
+    let width = completion
+        .candidates
+        .iter()
+        .map(|candidate| candidate.chars().count())
+        .max()
+        .unwrap_or_default()
+        .max(20) as u16
+        + 2;
+    let area = Rect {
+        x: input_area.x,
+        y: input_area.y + input_area.height,
+        width: width.min(input_area.width),
+        height,
+    }
+    .intersection(frame.area());
+
+    let list = List::new(completion.candidates.clone())
+        .style(style)
+        .highlight_style(Style::default().bg(Color::Green).fg(Color::White))
+        .block(Block::bordered().title("Tab - next, Shift-Tab - previous"));
+    frame.render_widget(Clear, area);
+    frame.render_stateful_widget(list, area, &mut completion.state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_editing() {
+        let mut input = PathInput::default();
+        input.set("/usr/local/bin");
+        input.edit(Edit::KillWord);
+        assert_eq!(input.text, "/usr/local/");
+        input.edit(Edit::WordLeft);
+        assert_eq!(input.cursor, 5);
+        input.edit(Edit::KillToStart);
+        assert_eq!((input.text.as_str(), input.cursor), ("local/", 0));
+
+        input.set("/a");
+        input.push_history();
+        input.set("/b");
+        input.push_history();
+        input.set("/c");
+        input.edit(Edit::HistoryPrevious);
+        input.edit(Edit::HistoryPrevious);
+        assert_eq!(input.text, "/a");
+        input.edit(Edit::HistoryNext);
+        input.edit(Edit::HistoryNext);
+        assert_eq!(input.text, "/c");
+    }
+
+    #[test]
+    fn test_expand() {
+        env::set_var("RUSTOR_TEST_DIR", "/srv");
+        assert_eq!(expand("$RUSTOR_TEST_DIR/x"), "/srv/x");
+        assert_eq!(expand("${RUSTOR_TEST_DIR}x"), "/srvx");
+        assert_eq!(expand("/$RUSTOR_UNSET_VAR/$"), "/$RUSTOR_UNSET_VAR/$");
+        assert_eq!(expand("~user"), "~user");
+        assert_eq!(
+            common_prefix(&["abc".to_string(), "abd/".to_string()]),
+            "ab"
+        );
+    }
+}
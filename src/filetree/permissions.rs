@@ -0,0 +1,469 @@
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style},
+    widgets::{Block, Clear, List, ListState, Paragraph},
+    Frame,
+};
+
+use std::{
+    fs, io,
+    os::unix::fs::{lchown, MetadataExt, PermissionsExt},
+    path::{Path, PathBuf},
+};
+
+use super::batch::BatchReport;
+use super::listing::{symbolic_mode, FileEntry};
+use super::users::UserDb;
+use crate::components::{optionlist::OptionListState, OptionList};
+
+const PREVIEW_LINES: usize = 200;
+
+const BITS: [(u32, &str); 12] = [
+    (0o4000, "setuid"),
+    (0o2000, "setgid"),
+    (0o1000, "sticky"),
+    (0o400, "user read"),
+    (0o200, "user write"),
+    (0o100, "user execute"),
+    (0o040, "group read"),
+    (0o020, "group write"),
+    (0o010, "group execute"),
+    (0o004, "other read"),
+    (0o002, "other write"),
+    (0o001, "other execute"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditorKind {
+    Chmod,
+    Chown,
+}
+
+/// A path the editor will change, with the metadata it had when the
+/// preview was built.
+#[derive(Debug, Clone)]
+struct Affected {
+    path: PathBuf,
+    mode: u32,
+    uid: u32,
+    gid: u32,
+    is_dir: bool,
+    /// Found by recursing into a target rather than targeted itself.
+    below: bool,
+}
+
+/// The chmod and chown dialogs over the targeted entries.
+#[derive(Debug)]
+pub struct PermissionsEditor {
+    pub kind: EditorKind,
+    targets: Vec<FileEntry>,
+    /// The mode the editor started from; the bits changed since are applied
+    /// to every affected path, keeping the rest of its mode.
+    initial: u32,
+    pub mode: u32,
+    pub octal: String,
+    pub bits: OptionListState,
+    /// `None` leaves the owner or group unchanged.
+    owners: Vec<(Option<u32>, String)>,
+    groups: Vec<(Option<u32>, String)>,
+    pub owner_state: ListState,
+    pub group_state: ListState,
+    pub group_focus: bool,
+    pub recursive: bool,
+    affected: Vec<Affected>,
+}
+
+impl PermissionsEditor {
+    pub fn new(kind: EditorKind, targets: Vec<FileEntry>, users: &UserDb) -> PermissionsEditor {
+        let first = &targets[0];
+        let choices = |names: &std::collections::BTreeMap<u32, String>| {
+            let mut choices = vec![(None, "(unchanged)".to_string())];
+            choices.extend(
+                names
+                    .iter()
+                    .map(|(id, name)| (Some(*id), format!("{name} ({id})"))),
+            );
+            choices
+        };
+        let owners = choices(&users.users);
+        let groups = choices(&users.groups);
+
+        let mut editor = PermissionsEditor {
+            kind,
+            initial: first.mode & 0o7777,
+            mode: first.mode & 0o7777,
+            octal: String::new(),
+            bits: OptionListState::new(BITS.len()),
+            owner_state: ListState::default().with_selected(Some(0)),
+            group_state: ListState::default().with_selected(Some(0)),
+            owners,
+            groups,
+            group_focus: false,
+            recursive: false,
+            affected: vec![],
+            targets,
+        };
+        editor.set_mode(editor.mode);
+        editor.collect_affected();
+        editor
+    }
+
+    fn set_mode(&mut self, mode: u32) {
+        self.mode = mode;
+        self.octal = format!("{mode:04o}");
+        for (index, (bit, _)) in BITS.iter().enumerate() {
+            self.bits.set_selected(index, mode & bit != 0);
+        }
+    }
+
+    pub fn toggle_bit(&mut self) {
+        if let Some(index) = self.bits.highlighted {
+            self.set_mode(self.mode ^ BITS[index].0);
+        }
+    }
+
+    pub fn push_octal(&mut self, digit: char) {
+        if self.octal.len() >= 4 {
+            return;
+        }
+        self.octal.push(digit);
+        if let Some(mode) = parse_octal(&self.octal) {
+            let octal = self.octal.clone();
+            self.set_mode(mode);
+            self.octal = octal;
+        }
+    }
+
+    pub fn pop_octal(&mut self) {
+        self.octal.pop();
+        let mode = parse_octal(&self.octal).unwrap_or(0);
+        let octal = self.octal.clone();
+        self.set_mode(mode);
+        self.octal = octal;
+    }
+
+    pub fn next(&mut self) {
+        match (self.kind, self.group_focus) {
+            (EditorKind::Chmod, _) => self.bits.highlight_next(),
+            (EditorKind::Chown, false) => self.owner_state.select_next(),
+            (EditorKind::Chown, true) => self.group_state.select_next(),
+        }
+    }
+
+    pub fn previous(&mut self) {
+        match (self.kind, self.group_focus) {
+            (EditorKind::Chmod, _) => self.bits.highlight_prev(),
+            (EditorKind::Chown, false) => self.owner_state.select_previous(),
+            (EditorKind::Chown, true) => self.group_state.select_previous(),
+        }
+    }
+
+    pub fn toggle_recursive(&mut self) {
+        self.recursive = !self.recursive;
+        self.collect_affected();
+    }
+
+    fn owner(&self) -> Option<u32> {
+        let index = self.owner_state.selected().unwrap_or(0);
+        self.owners.get(index).and_then(|(id, _)| *id)
+    }
+
+    fn group(&self) -> Option<u32> {
+        let index = self.group_state.selected().unwrap_or(0);
+        self.groups.get(index).and_then(|(id, _)| *id)
+    }
+
+    fn collect_affected(&mut self) {
+        let mut affected = vec![];
+        for target in &self.targets {
+            collect(
+                &target.path,
+                self.recursive,
+                false,
+                self.kind,
+                &mut affected,
+            );
+        }
+        self.affected = affected;
+    }
+
+    fn preview(&self, users: &UserDb) -> Vec<String> {
+        let mut lines: Vec<String> = self
+            .affected
+            .iter()
+            .take(PREVIEW_LINES)
+            .map(|entry| match self.kind {
+                EditorKind::Chmod => format!(
+                    "{} -> {} {}",
+                    symbolic_mode(entry.mode),
+                    symbolic_mode(entry.mode & !0o7777 | self.new_mode(entry)),
+                    entry.path.display()
+                ),
+                EditorKind::Chown => format!(
+                    "{}:{} -> {}:{} {}",
+                    users.user_name(entry.uid),
+                    users.group_name(entry.gid),
+                    users.user_name(self.owner().unwrap_or(entry.uid)),
+                    users.group_name(self.group().unwrap_or(entry.gid)),
+                    entry.path.display()
+                ),
+            })
+            .collect();
+        if self.affected.len() > PREVIEW_LINES {
+            lines.push(format!(
+                "... and {} more",
+                self.affected.len() - PREVIEW_LINES
+            ));
+        }
+        lines
+    }
+
+    /// The mode `entry` gets: the bits added or removed in the editor are
+    /// changed, the rest kept. Below the targets execute bits work like
+    /// chmod's `X`: files only gain them if they already are executable, and
+    /// directories keep them for as long as they stay readable, so a
+    /// recursive 644 doesn't lock anyone out of the subdirectories.
+    fn new_mode(&self, entry: &Affected) -> u32 {
+        let old = entry.mode & 0o7777;
+        let added = self.mode & !self.initial;
+        let removed = self.initial & !self.mode;
+        let mut mode = old & !removed | added;
+        if entry.below {
+            if entry.is_dir {
+                mode |= old & 0o111 & (mode >> 2);
+            } else if old & 0o111 == 0 {
+                mode &= !(added & 0o111);
+            }
+        }
+        mode
+    }
+
+    pub fn apply(&self) -> BatchReport {
+        let mut report = BatchReport {
+            op: match self.kind {
+                EditorKind::Chmod => format!("Chmod {:04o}", self.mode),
+                EditorKind::Chown => "Chown".to_string(),
+            },
+            succeeded: 0,
+            failures: vec![],
+        };
+
+        let (owner, group) = (self.owner(), self.group());
+        for entry in &self.affected {
+            let result = match self.kind {
+                EditorKind::Chmod => {
+                    let mode = self.new_mode(entry);
+                    fs::set_permissions(&entry.path, fs::Permissions::from_mode(mode))
+                }
+                EditorKind::Chown => lchown(&entry.path, owner, group),
+            };
+
+            match result {
+                Ok(()) => report.succeeded += 1,
+                Err(err) => report
+                    .failures
+                    .push((entry.path.clone(), describe_error(&err))),
+            }
+        }
+
+        report
+    }
+}
+
+/// Collects `path` and, when recursive, everything below it without
+/// following symlinks. Symlinks below the top level are skipped for chmod
+/// since changing their mode would change their target instead.
+fn collect(
+    path: &Path,
+    recursive: bool,
+    below: bool,
+    kind: EditorKind,
+    affected: &mut Vec<Affected>,
+) {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return;
+    };
+    affected.push(Affected {
+        path: path.to_path_buf(),
+        mode: metadata.mode(),
+        uid: metadata.uid(),
+        gid: metadata.gid(),
+        is_dir: metadata.is_dir(),
+        below,
+    });
+
+    if !recursive || !metadata.is_dir() {
+        return;
+    }
+    let Ok(children) = fs::read_dir(path) else {
+        return;
+    };
+    for child in children.flatten() {
+        let is_symlink = child.file_type().is_ok_and(|kind| kind.is_symlink());
+        if is_symlink && kind == EditorKind::Chmod {
+            continue;
+        }
+        collect(&child.path(), recursive, true, kind, affected);
+    }
+}
+
+fn describe_error(err: &io::Error) -> String {
+    match err.kind() {
+        io::ErrorKind::PermissionDenied => {
+            "permission denied (only the owner or root may change this)".to_string()
+        }
+        _ => err.to_string(),
+    }
+}
+
+fn parse_octal(text: &str) -> Option<u32> {
+    u32::from_str_radix(text, 8)
+        .ok()
+        .filter(|mode| *mode <= 0o7777)
+}
+
+pub fn render(
+    editor: &mut PermissionsEditor,
+    users: &UserDb,
+    frame: &mut Frame,
+    area: Rect,
+    style: Style,
+) {
+    let [_, dialog_area, _] = Layout::vertical([
+        Constraint::Percentage(10),
+        Constraint::Percentage(80),
+        Constraint::Percentage(10),
+    ])
+    .areas(area);
+    frame.render_widget(Clear, dialog_area);
+
+    let [choice_area, preview_area] =
+        Layout::horizontal([Constraint::Length(44), Constraint::Min(1)]).areas(dialog_area);
+    let highlight = Style::default().bg(Color::Green).fg(Color::White);
+    let recursive = if editor.recursive { "on" } else { "off" };
+
+    match editor.kind {
+        EditorKind::Chmod => {
+            let [bits_area, octal_area] = Layout::vertical([
+                Constraint::Length(BITS.len() as u16 + 2),
+                Constraint::Min(3),
+            ])
+            .areas(choice_area);
+            let bits = OptionList::new(
+                BITS.iter().map(|(_, name)| name.to_string()).collect(),
+                "[x]".to_string(),
+                "[ ]".to_string(),
+                "Chmod (space - toggle, 0-7 - octal)".to_string(),
+                highlight,
+                style,
+            );
+            frame.render_stateful_widget(bits, bits_area, &mut editor.bits);
+
+            let octal = Paragraph::new(format!(
+                "Octal: {}\nMode: {}\nRecursive (R): {recursive}",
+                editor.octal,
+                symbolic_mode(editor.mode)
+            ))
+            .style(style)
+            .block(Block::bordered());
+            frame.render_widget(octal, octal_area);
+        }
+        EditorKind::Chown => {
+            let [owner_area, group_area] =
+                Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                    .areas(choice_area);
+            let focused = |focus: bool| if focus { highlight } else { style };
+
+            let owners = List::new(editor.owners.iter().map(|(_, name)| name.clone()))
+                .style(style)
+                .highlight_style(focused(!editor.group_focus))
+                .block(Block::bordered().title("Owner"));
+            frame.render_stateful_widget(owners, owner_area, &mut editor.owner_state);
+
+            let groups = List::new(editor.groups.iter().map(|(_, name)| name.clone()))
+                .style(style)
+                .highlight_style(focused(editor.group_focus))
+                .block(Block::bordered().title(format!("Group (R - recursive: {recursive})")));
+            frame.render_stateful_widget(groups, group_area, &mut editor.group_state);
+        }
+    }
+
+    let lines = editor.preview(users);
+    let title = format!(
+        "Preview: {} items (Enter - apply, Esc - cancel{})",
+        editor.affected.len(),
+        match editor.kind {
+            EditorKind::Chmod => "",
+            EditorKind::Chown => ", h/l - owner/group",
+        }
+    );
+    let preview = Paragraph::new(lines.join("\n"))
+        .style(style)
+        .block(Block::bordered().title(title));
+    frame.render_widget(preview, preview_area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_octal() {
+        assert_eq!(parse_octal("755"), Some(0o755));
+        assert_eq!(parse_octal("4755"), Some(0o4755));
+        assert_eq!(parse_octal("17777"), None);
+        assert_eq!(parse_octal("8"), None);
+        assert_eq!(parse_octal(""), None);
+    }
+
+    #[test]
+    fn test_recursive_chmod() {
+        let dir = std::env::temp_dir().join(format!("rustor-chmod-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("plain"), "").unwrap();
+        fs::write(dir.join("script"), "").unwrap();
+        let chmod = |name: &str, mode| {
+            fs::set_permissions(dir.join(name), fs::Permissions::from_mode(mode)).unwrap()
+        };
+        let mode = |name: &str| fs::metadata(dir.join(name)).unwrap().mode() & 0o7777;
+        let reset = || {
+            chmod("", 0o755);
+            chmod("sub", 0o755);
+            chmod("plain", 0o600);
+            chmod("script", 0o755);
+        };
+        let run = |octal: u32| {
+            let target = FileEntry::from_metadata(dir.clone(), &fs::metadata(&dir).unwrap());
+            let mut editor =
+                PermissionsEditor::new(EditorKind::Chmod, vec![target], &UserDb::default());
+            editor.toggle_recursive();
+            editor.set_mode(octal);
+            let report = editor.apply();
+            assert!(report.failures.is_empty(), "{:?}", report.failures);
+        };
+
+        // Subdirectories stay traversable and only the target loses execute.
+        reset();
+        run(0o644);
+        assert_eq!(mode(""), 0o644);
+        assert_eq!(mode("sub"), 0o755);
+        assert_eq!(mode("plain"), 0o600);
+        assert_eq!(mode("script"), 0o644);
+
+        // Unreadable directories lose execute along with read.
+        chmod("", 0o755);
+        run(0o750);
+        assert_eq!(mode("sub"), 0o750);
+        assert_eq!(mode("script"), 0o640);
+
+        // Execute is only added to directories and executables.
+        reset();
+        chmod("", 0o700);
+        run(0o755);
+        assert_eq!(mode("sub"), 0o755);
+        assert_eq!(mode("plain"), 0o644);
+        assert_eq!(mode("script"), 0o755);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,301 @@
+use regex::Regex;
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+    process,
+};
+
+use super::batch::BatchReport;
+
+/// Pairs of current and new paths.
+pub type Renames = Vec<(PathBuf, PathBuf)>;
+
+/// A rename of many entries at once, as produced by a pattern or by editing
+/// the names in `$EDITOR`.
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// `s/regex/replacement/`, where the replacement may use `$1` groups.
+    Substitute(Regex, String),
+    Lower,
+    Upper,
+    /// Any other text, with `{name}`, `{ext}`, `{n}` and `{n:3}` replaced by
+    /// the stem, extension and (zero padded) position in the selection.
+    Template(String),
+}
+
+impl Pattern {
+    pub fn parse(text: &str) -> Result<Pattern, String> {
+        let text = text.trim();
+        match text {
+            "" => return Err("Pattern is empty".to_string()),
+            "lower" => return Ok(Pattern::Lower),
+            "upper" => return Ok(Pattern::Upper),
+            _ => {}
+        }
+
+        let Some(substitution) = text.strip_prefix("s/") else {
+            return Ok(Pattern::Template(text.to_string()));
+        };
+        let Some((regex, replacement)) = substitution
+            .strip_suffix('/')
+            .and_then(|rest| rest.split_once('/'))
+        else {
+            return Err("Expected s/regex/replacement/".to_string());
+        };
+        let regex = Regex::new(regex).map_err(|err| err.to_string())?;
+        Ok(Pattern::Substitute(regex, replacement.to_string()))
+    }
+
+    pub fn apply(&self, name: &str, index: usize) -> String {
+        match self {
+            Pattern::Substitute(regex, replacement) => regex
+                .replace_all(name, number(replacement, index + 1).as_str())
+                .to_string(),
+            Pattern::Lower => name.to_lowercase(),
+            Pattern::Upper => name.to_uppercase(),
+            Pattern::Template(template) => {
+                let path = Path::new(name);
+                let stem = path.file_stem().map(|stem| stem.to_string_lossy());
+                let ext = path.extension().map(|ext| ext.to_string_lossy());
+                number(template, index + 1)
+                    .replace("{name}", stem.as_deref().unwrap_or(name))
+                    .replace("{ext}", ext.as_deref().unwrap_or_default())
+            }
+        }
+    }
+}
+
+/// Replaces `{n}` and `{n:WIDTH}` with `number`.
+fn number(text: &str, number: usize) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{n") {
+        let Some(end) = rest[start..].find('}').map(|end| start + end) else {
+            break;
+        };
+        let spec = &rest[start + 2..end];
+        let width = match spec.strip_prefix(':') {
+            Some(width) => width.parse().ok(),
+            None if spec.is_empty() => Some(0),
+            None => None,
+        };
+
+        result.push_str(&rest[..start]);
+        match width {
+            Some(width) => result.push_str(&format!("{number:0width$}")),
+            None => result.push_str(&rest[start..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Pairs `sources` with their new `names`, dropping unchanged entries. Every
+/// problem found is returned so they can be fixed in one go.
+pub fn plan(sources: &[PathBuf], names: &[String]) -> Result<Renames, Vec<(PathBuf, String)>> {
+    if sources.len() != names.len() {
+        let dir = sources.first().and_then(|source| source.parent());
+        return Err(vec![(
+            dir.unwrap_or(Path::new("")).to_path_buf(),
+            format!("expected {} names, got {}", sources.len(), names.len()),
+        )]);
+    }
+
+    let mut problems = vec![];
+    let mut renames = vec![];
+    for (source, name) in sources.iter().zip(names) {
+        let name = name.trim();
+        // Names are edited as lossy UTF-8, so an unchanged line must not
+        // rename a name that isn't valid UTF-8 to its lossy form.
+        if source.file_name().unwrap_or_default().to_string_lossy() == name {
+            continue;
+        }
+        if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+            problems.push((source.clone(), format!("invalid name {name:?}")));
+            continue;
+        }
+        let dest = source.with_file_name(name);
+        if &dest != source {
+            renames.push((source.clone(), dest));
+        }
+    }
+
+    let moving: HashSet<&PathBuf> = renames.iter().map(|(source, _)| source).collect();
+    let mut claimed: HashMap<&PathBuf, &PathBuf> = HashMap::new();
+    for (source, dest) in &renames {
+        if let Some(other) = claimed.insert(dest, source) {
+            problems.push((
+                source.clone(),
+                format!(
+                    "{} is also the new name of {}",
+                    dest.display(),
+                    other.display()
+                ),
+            ));
+        } else if !moving.contains(dest) && fs::symlink_metadata(dest).is_ok() {
+            problems.push((source.clone(), format!("{} already exists", dest.display())));
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(renames)
+    } else {
+        Err(problems)
+    }
+}
+
+/// Applies a validated plan. Entries whose new name is taken by another
+/// entry of the plan (chains and cycles like swapping two names) are first
+/// moved to temporary names so no rename ever replaces a file.
+pub fn apply(renames: &[(PathBuf, PathBuf)]) -> BatchReport {
+    let mut report = BatchReport {
+        op: "Rename".to_string(),
+        succeeded: 0,
+        failures: vec![],
+    };
+    let sources: HashSet<&PathBuf> = renames.iter().map(|(source, _)| source).collect();
+
+    let mut direct = vec![];
+    let mut parked = vec![];
+    for (index, (source, dest)) in renames.iter().enumerate() {
+        if !sources.contains(dest) {
+            direct.push((source.clone(), source, dest));
+            continue;
+        }
+        let temp = source.with_file_name(format!(".rustor-rename-{}-{index}", process::id()));
+        match rename_new(source, &temp) {
+            Ok(()) => parked.push((temp, source, dest)),
+            Err(err) => report.failures.push((source.clone(), err.to_string())),
+        }
+    }
+
+    for (from, source, dest) in direct.into_iter().chain(parked) {
+        match rename_new(&from, dest) {
+            Ok(()) => report.succeeded += 1,
+            Err(err) => {
+                // Put parked entries back under their old name if possible.
+                if from != *source {
+                    let _ = rename_new(&from, source);
+                }
+                report.failures.push((source.clone(), err.to_string()));
+            }
+        }
+    }
+
+    report
+}
+
+/// Renames without replacing an existing entry, atomically where the
+/// filesystem supports it.
+#[cfg(target_os = "linux")]
+pub fn rename_new(from: &Path, to: &Path) -> io::Result<()> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+    let old = CString::new(from.as_os_str().as_bytes())?;
+    let new = CString::new(to.as_os_str().as_bytes())?;
+    // SAFETY: both paths are valid NUL-terminated strings.
+    let result = unsafe {
+        libc::renameat2(
+            libc::AT_FDCWD,
+            old.as_ptr(),
+            libc::AT_FDCWD,
+            new.as_ptr(),
+            libc::RENAME_NOREPLACE,
+        )
+    };
+    if result == 0 {
+        return Ok(());
+    }
+    let err = io::Error::last_os_error();
+    match err.raw_os_error() {
+        Some(libc::EEXIST) => Err(already_exists(to)),
+        // Some filesystems, like older NFS, don't support the flag.
+        Some(libc::EINVAL) | Some(libc::ENOSYS) => check_and_rename(from, to),
+        _ => Err(err),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn rename_new(from: &Path, to: &Path) -> io::Result<()> {
+    check_and_rename(from, to)
+}
+
+fn check_and_rename(from: &Path, to: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(to).is_ok() {
+        return Err(already_exists(to));
+    }
+    fs::rename(from, to)
+}
+
+fn already_exists(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        format!("{} already exists", path.display()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::ffi::OsStrExt;
+
+    #[test]
+    fn test_patterns() {
+        let pattern = Pattern::parse("s/(\\d+)/#$1/").unwrap();
+        assert_eq!(pattern.apply("img12.png", 0), "img#12.png");
+
+        let pattern = Pattern::parse("photo_{n:3}.{ext}").unwrap();
+        assert_eq!(pattern.apply("DSC1234.JPG", 4), "photo_005.JPG");
+
+        let pattern = Pattern::parse("{name}-{n}").unwrap();
+        assert_eq!(pattern.apply("notes.txt", 0), "notes-1");
+
+        assert_eq!(Pattern::parse("upper").unwrap().apply("a.txt", 0), "A.TXT");
+        assert!(Pattern::parse("s/(/x/").is_err());
+        assert!(Pattern::parse("s/a").is_err());
+    }
+
+    #[test]
+    fn test_plan_rejects_collisions() {
+        let dir = PathBuf::from("/nonexistent-rustor-test");
+        let sources = vec![dir.join("a"), dir.join("b"), dir.join("c")];
+
+        let names = vec!["b".to_string(), "a".to_string(), "c".to_string()];
+        let renames = plan(&sources, &names).unwrap();
+        assert_eq!(renames.len(), 2);
+
+        let names = vec!["x".to_string(), "x".to_string(), "c/d".to_string()];
+        assert_eq!(plan(&sources, &names).unwrap_err().len(), 2);
+
+        assert!(plan(&sources, &names[..1]).is_err());
+
+        let latin1 = vec![dir.join(std::ffi::OsStr::from_bytes(b"caf\xe9"))];
+        let lossy = latin1[0].file_name().unwrap().to_string_lossy().to_string();
+        assert!(plan(&latin1, &[lossy]).unwrap().is_empty());
+        let renames = plan(&latin1, &["cafe".to_string()]).unwrap();
+        assert_eq!(renames, [(latin1[0].clone(), dir.join("cafe"))]);
+    }
+
+    #[test]
+    fn test_apply_swaps_without_replacing() {
+        let dir = std::env::temp_dir().join(format!("rustor-rename-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a"), "a").unwrap();
+        fs::write(dir.join("b"), "b").unwrap();
+
+        let report = apply(&[
+            (dir.join("a"), dir.join("b")),
+            (dir.join("b"), dir.join("a")),
+        ]);
+        assert!(report.failures.is_empty(), "{:?}", report.failures);
+        assert_eq!(fs::read(dir.join("a")).unwrap(), b"b");
+
+        let err = rename_new(&dir.join("a"), &dir.join("b")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read(dir.join("b")).unwrap(), b"a");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,674 @@
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style},
+    symbols,
+    text::Line,
+    widgets::{Block, Clear, LineGauge, Paragraph},
+    Frame,
+};
+
+use std::{
+    collections::VecDeque,
+    ffi::CString,
+    fs, io,
+    os::unix::{
+        ffi::OsStrExt,
+        fs::{FileExt, MetadataExt, OpenOptionsExt},
+    },
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::batch::{self, BatchReport};
+use super::listing::format_size;
+use super::rename::rename_new;
+
+const CHUNK_LENGTH: usize = 1024 * 1024;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Copies or moves `sources` into the directory `dest` on a worker thread.
+#[derive(Debug, Clone)]
+pub struct Transfer {
+    pub moving: bool,
+    pub sources: Vec<PathBuf>,
+    pub dest: PathBuf,
+    /// Whether symlinks are replaced by copies of what they point to.
+    pub follow_links: bool,
+}
+
+impl Transfer {
+    fn title(&self) -> String {
+        format!(
+            "{} {} items to {}",
+            if self.moving { "Move" } else { "Copy" },
+            self.sources.len(),
+            self.dest.display()
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Progress {
+    pub file: PathBuf,
+    pub file_done: u64,
+    pub file_size: u64,
+    pub done: u64,
+    pub total: u64,
+    pub files_done: usize,
+    pub files: usize,
+}
+
+#[derive(Debug)]
+enum TransferEvent {
+    Progress(Progress),
+    Finished(BatchReport),
+}
+
+/// Lets the UI pause and cancel the worker between chunks.
+#[derive(Debug, Default)]
+struct Control {
+    paused: AtomicBool,
+    cancel: AtomicBool,
+}
+
+impl Control {
+    /// Blocks while paused, failing once the transfer is cancelled.
+    fn wait(&self) -> io::Result<()> {
+        while self.paused.load(Ordering::Relaxed) && !self.cancel.load(Ordering::Relaxed) {
+            thread::sleep(PROGRESS_INTERVAL);
+        }
+        if self.cancel.load(Ordering::Relaxed) {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled"));
+        }
+        Ok(())
+    }
+}
+
+/// One step of copying a tree, in the order the steps run.
+#[derive(Debug)]
+enum Step {
+    Dir(PathBuf, PathBuf),
+    File(PathBuf, PathBuf, u64),
+    Link(PathBuf, PathBuf),
+    /// A FIFO, socket or device node with its mode and device number.
+    Node(PathBuf, PathBuf, u32, u64),
+}
+
+/// Lists the steps to copy `src` to `dest`, refusing links that lead back
+/// into a directory being copied.
+fn plan(
+    src: &Path,
+    dest: &Path,
+    follow_links: bool,
+    ancestors: &mut Vec<(u64, u64)>,
+    steps: &mut Vec<Step>,
+) -> io::Result<()> {
+    let metadata = if follow_links {
+        fs::metadata(src)?
+    } else {
+        fs::symlink_metadata(src)?
+    };
+
+    if metadata.is_dir() {
+        let id = (metadata.dev(), metadata.ino());
+        if ancestors.contains(&id) {
+            return Err(io::Error::other(format!(
+                "{} links to one of its parents",
+                src.display()
+            )));
+        }
+        ancestors.push(id);
+        steps.push(Step::Dir(src.to_path_buf(), dest.to_path_buf()));
+        for child in fs::read_dir(src)? {
+            let child = child?;
+            plan(
+                &child.path(),
+                &dest.join(child.file_name()),
+                follow_links,
+                ancestors,
+                steps,
+            )?;
+        }
+        ancestors.pop();
+    } else if metadata.is_symlink() {
+        steps.push(Step::Link(src.to_path_buf(), dest.to_path_buf()));
+    } else if metadata.is_file() {
+        steps.push(Step::File(
+            src.to_path_buf(),
+            dest.to_path_buf(),
+            metadata.len(),
+        ));
+    } else {
+        // Reading a FIFO blocks until a writer shows up and a device may
+        // never end, so these are recreated instead of copied.
+        steps.push(Step::Node(
+            src.to_path_buf(),
+            dest.to_path_buf(),
+            metadata.mode(),
+            metadata.rdev(),
+        ));
+    }
+    Ok(())
+}
+
+/// Shares the file contents with `output` on filesystems that support
+/// reflinks, like btrfs and XFS.
+#[cfg(target_os = "linux")]
+fn reflink(input: &fs::File, output: &fs::File) -> bool {
+    use std::os::fd::AsRawFd;
+    // SAFETY: both descriptors stay open for the duration of the call.
+    unsafe { libc::ioctl(output.as_raw_fd(), libc::FICLONE, input.as_raw_fd()) == 0 }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn reflink(_input: &fs::File, _output: &fs::File) -> bool {
+    false
+}
+
+/// The next range of `file` at or after `offset` that holds data, so holes
+/// in sparse files are skipped instead of written out as zeros.
+#[cfg(target_os = "linux")]
+fn next_data(file: &fs::File, offset: u64, size: u64) -> io::Result<Option<(u64, u64)>> {
+    use std::os::fd::AsRawFd;
+    if offset >= size {
+        return Ok(None);
+    }
+
+    // SAFETY: lseek only moves the offset of a descriptor we own.
+    let start = unsafe { libc::lseek(file.as_raw_fd(), offset as libc::off_t, libc::SEEK_DATA) };
+    if start < 0 {
+        let err = io::Error::last_os_error();
+        return match err.raw_os_error() {
+            // Only a hole is left.
+            Some(libc::ENXIO) => Ok(None),
+            // The filesystem doesn't report holes.
+            Some(libc::EINVAL) | Some(libc::EOPNOTSUPP) => Ok(Some((offset, size))),
+            _ => Err(err),
+        };
+    }
+    // SAFETY: as above.
+    let end = unsafe { libc::lseek(file.as_raw_fd(), start, libc::SEEK_HOLE) };
+    let end = if end < 0 { size } else { end as u64 };
+    Ok(Some((start as u64, end.min(size))))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn next_data(_file: &fs::File, offset: u64, size: u64) -> io::Result<Option<(u64, u64)>> {
+    Ok((offset < size).then_some((offset, size)))
+}
+
+/// Creates a FIFO, socket or device node like the one at the source.
+/// Device nodes usually need root.
+fn make_node(dest: &Path, mode: u32, rdev: u64) -> io::Result<()> {
+    let path = CString::new(dest.as_os_str().as_bytes())?;
+    // SAFETY: `path` is a valid NUL-terminated string.
+    if unsafe { libc::mknod(path.as_ptr(), mode as libc::mode_t, rdev as libc::dev_t) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Copies a regular file, reporting how much of it is done.
+fn copy_file(
+    src: &Path,
+    dest: &Path,
+    control: &Control,
+    mut progress: impl FnMut(u64),
+) -> io::Result<()> {
+    // Opening without blocking guards against the file having been replaced
+    // by a FIFO since the transfer was planned.
+    let input = fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(src)?;
+    let metadata = input.metadata()?;
+    if !metadata.is_file() {
+        return Err(io::Error::other(format!(
+            "{} is no longer a regular file",
+            src.display()
+        )));
+    }
+    let size = metadata.len();
+    let output = fs::File::create_new(dest)?;
+
+    let result = (|| {
+        if !reflink(&input, &output) {
+            let mut buffer = vec![0; CHUNK_LENGTH];
+            let mut offset = 0;
+            while let Some((start, end)) = next_data(&input, offset, size)? {
+                let mut position = start;
+                while position < end {
+                    control.wait()?;
+                    let length = (end - position).min(CHUNK_LENGTH as u64) as usize;
+                    let read = input.read_at(&mut buffer[..length], position)?;
+                    if read == 0 {
+                        break;
+                    }
+                    output.write_all_at(&buffer[..read], position)?;
+                    position += read as u64;
+                    progress(position);
+                }
+                offset = end;
+            }
+            // Extending the file keeps a trailing hole sparse.
+            output.set_len(size)?;
+        }
+        progress(size);
+        output.set_permissions(metadata.permissions())
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(dest);
+    }
+    result
+}
+
+fn remove(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+/// Runs a whole transfer, sending progress as it goes. Moves are renames
+/// where possible and only copied across filesystems.
+fn run(transfer: &Transfer, control: &Control, send: impl Fn(Progress)) -> BatchReport {
+    let mut report = BatchReport {
+        op: transfer.title(),
+        succeeded: 0,
+        failures: vec![],
+    };
+
+    let mut copies = vec![];
+    for source in &transfer.sources {
+        let Some(name) = source.file_name() else {
+            continue;
+        };
+        let target = transfer.dest.join(name);
+        if fs::symlink_metadata(&target).is_ok() {
+            let err = format!("{} already exists", target.display());
+            report.failures.push((source.clone(), err));
+            continue;
+        }
+        // Moves take links along rather than what they point to.
+        let follow_links = transfer.follow_links && !transfer.moving;
+        if let Err(err) = batch::check_outside(source, &target, follow_links) {
+            report.failures.push((source.clone(), err.to_string()));
+            continue;
+        }
+        if transfer.moving {
+            // The target may have appeared since it was checked above.
+            match rename_new(source, &target) {
+                Ok(()) => {
+                    report.succeeded += 1;
+                    continue;
+                }
+                Err(err) if err.kind() == io::ErrorKind::CrossesDevices => {}
+                Err(err) => {
+                    report.failures.push((source.clone(), err.to_string()));
+                    continue;
+                }
+            }
+        }
+
+        let mut steps = vec![];
+        match plan(
+            source,
+            &target,
+            transfer.follow_links,
+            &mut vec![],
+            &mut steps,
+        ) {
+            Ok(()) => copies.push((source, steps)),
+            Err(err) => report.failures.push((source.clone(), err.to_string())),
+        }
+    }
+
+    let mut progress = Progress::default();
+    for (_, steps) in &copies {
+        for step in steps {
+            if let Step::File(_, _, size) = step {
+                progress.total += size;
+                progress.files += 1;
+            }
+        }
+    }
+    send(progress.clone());
+
+    let mut reported = Instant::now();
+    for (source, steps) in copies {
+        let failures = report.failures.len();
+        // Directory permissions are applied last so read-only directories
+        // can still be filled.
+        let mut dirs = vec![];
+
+        for step in steps {
+            let result = match &step {
+                Step::Dir(src, dest) => fs::metadata(src).and_then(|metadata| {
+                    fs::create_dir(dest)?;
+                    dirs.push((dest.clone(), metadata.permissions()));
+                    Ok(())
+                }),
+                Step::Link(src, dest) => {
+                    fs::read_link(src).and_then(|target| std::os::unix::fs::symlink(target, dest))
+                }
+                Step::Node(_, dest, mode, rdev) => make_node(dest, *mode, *rdev),
+                Step::File(src, dest, _) => {
+                    let done = progress.done;
+                    progress.file = src.clone();
+                    progress.file_size = fs::metadata(src).map_or(0, |metadata| metadata.len());
+                    let result = copy_file(src, dest, control, |file_done| {
+                        progress.file_done = file_done;
+                        progress.done = done + file_done;
+                        if reported.elapsed() >= PROGRESS_INTERVAL {
+                            send(progress.clone());
+                            reported = Instant::now();
+                        }
+                    });
+                    progress.files_done += 1;
+                    result
+                }
+            };
+
+            if let Err(err) = result {
+                let path = match step {
+                    Step::Dir(src, _)
+                    | Step::File(src, _, _)
+                    | Step::Link(src, _)
+                    | Step::Node(src, _, _, _) => src,
+                };
+                let cancelled = err.kind() == io::ErrorKind::Interrupted;
+                report.failures.push((path, err.to_string()));
+                if cancelled {
+                    return report;
+                }
+            }
+        }
+
+        for (dir, permissions) in dirs.into_iter().rev() {
+            if let Err(err) = fs::set_permissions(&dir, permissions) {
+                report.failures.push((dir, err.to_string()));
+            }
+        }
+
+        if report.failures.len() > failures {
+            continue;
+        }
+        // A move across filesystems only removes what was fully copied.
+        match transfer.moving.then(|| remove(source)) {
+            Some(Err(err)) => report.failures.push((source.clone(), err.to_string())),
+            _ => report.succeeded += 1,
+        }
+    }
+
+    send(progress);
+    report
+}
+
+#[derive(Debug)]
+struct ActiveTransfer {
+    title: String,
+    receiver: Receiver<TransferEvent>,
+    control: Arc<Control>,
+    progress: Progress,
+    /// Time spent transferring so far, without pauses.
+    elapsed: Duration,
+    /// When the transfer last started or resumed, `None` while paused.
+    resumed: Option<Instant>,
+}
+
+impl ActiveTransfer {
+    fn start(transfer: Transfer) -> ActiveTransfer {
+        let (sender, receiver) = mpsc::channel();
+        let control = Arc::new(Control::default());
+        let worker_control = control.clone();
+        let title = transfer.title();
+
+        thread::spawn(move || {
+            let report = run(&transfer, &worker_control, |progress| {
+                let _ = sender.send(TransferEvent::Progress(progress));
+            });
+            let _ = sender.send(TransferEvent::Finished(report));
+        });
+
+        ActiveTransfer {
+            title,
+            receiver,
+            control,
+            progress: Progress::default(),
+            elapsed: Duration::ZERO,
+            resumed: Some(Instant::now()),
+        }
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.elapsed
+            + self
+                .resumed
+                .map_or(Duration::ZERO, |resumed| resumed.elapsed())
+    }
+
+    /// Bytes per second while running.
+    fn throughput(&self) -> f64 {
+        self.progress.done as f64 / self.elapsed().as_secs_f64().max(0.001)
+    }
+}
+
+/// The copies and moves waiting or running, one at a time.
+#[derive(Debug, Default)]
+pub struct TransferQueue {
+    pending: VecDeque<Transfer>,
+    active: Option<ActiveTransfer>,
+}
+
+impl TransferQueue {
+    pub fn push(&mut self, transfer: Transfer) {
+        self.pending.push_back(transfer);
+        if self.active.is_none() {
+            self.start_next();
+        }
+    }
+
+    fn start_next(&mut self) {
+        self.active = self.pending.pop_front().map(ActiveTransfer::start);
+    }
+
+    /// Takes in progress and returns the report of a finished transfer,
+    /// starting the next one.
+    pub fn poll(&mut self) -> Option<BatchReport> {
+        let active = self.active.as_mut()?;
+        let mut finished = None;
+        while let Ok(event) = active.receiver.try_recv() {
+            match event {
+                TransferEvent::Progress(progress) => active.progress = progress,
+                TransferEvent::Finished(report) => finished = Some(report),
+            }
+        }
+        if finished.is_some() {
+            self.start_next();
+        }
+        finished
+    }
+
+    pub fn toggle_pause(&mut self) {
+        let Some(active) = &mut self.active else {
+            return;
+        };
+        match active.resumed.take() {
+            Some(resumed) => {
+                active.elapsed += resumed.elapsed();
+                active.control.paused.store(true, Ordering::Relaxed);
+            }
+            None => {
+                active.resumed = Some(Instant::now());
+                active.control.paused.store(false, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Cancels the running transfer, the queued ones start after it.
+    pub fn cancel(&mut self) {
+        if let Some(active) = &self.active {
+            active.control.cancel.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Shows the running transfer at the bottom of `area`.
+    pub fn render(&self, frame: &mut Frame, area: Rect, style: Style) {
+        let Some(active) = &self.active else {
+            return;
+        };
+        let progress = &active.progress;
+
+        let [_, panel_area] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(5)]).areas(area);
+        let mut title = active.title.clone();
+        if !self.pending.is_empty() {
+            title.push_str(&format!(" ({} queued)", self.pending.len()));
+        }
+        if active.resumed.is_none() {
+            title.push_str(" [paused]");
+        }
+        let block = Block::bordered()
+            .title(title)
+            .title_bottom("P - pause/resume, Z - cancel")
+            .style(style);
+        let inner = block.inner(panel_area);
+        frame.render_widget(Clear, panel_area);
+        frame.render_widget(block, panel_area);
+
+        let [file_area, total_area, stats_area] =
+            Layout::vertical([Constraint::Length(1); 3]).areas(inner);
+        let ratio = |done: u64, total: u64| match total {
+            0 => 1.0,
+            total => (done as f64 / total as f64).clamp(0.0, 1.0),
+        };
+        let name = progress
+            .file
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let file_gauge = LineGauge::default()
+            .label(format!("{name} "))
+            .filled_style(Style::default().fg(Color::Green))
+            .line_set(symbols::line::THICK)
+            .ratio(ratio(progress.file_done, progress.file_size));
+        frame.render_widget(file_gauge, file_area);
+
+        let total_gauge = LineGauge::default()
+            .label(format!(
+                "{} / {} ",
+                format_size(progress.done),
+                format_size(progress.total)
+            ))
+            .filled_style(Style::default().fg(Color::Cyan))
+            .line_set(symbols::line::THICK)
+            .ratio(ratio(progress.done, progress.total));
+        frame.render_widget(total_gauge, total_area);
+
+        let throughput = active.throughput();
+        let eta = if throughput > 0.0 {
+            let seconds = (progress.total.saturating_sub(progress.done) as f64 / throughput) as u64;
+            format!("{}:{:02}", seconds / 60, seconds % 60)
+        } else {
+            "-".to_string()
+        };
+        let stats = Line::from(format!(
+            "{}/{} files, {}/s, ETA {eta}",
+            progress.files_done,
+            progress.files,
+            format_size(throughput as u64),
+        ));
+        frame.render_widget(Paragraph::new(stats), stats_area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::FileTypeExt;
+
+    #[test]
+    fn test_sparse_copy() {
+        let dir = std::env::temp_dir().join(format!("rustor-transfer-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("src/sub")).unwrap();
+        fs::create_dir(dir.join("dest")).unwrap();
+
+        // A file with data after a 4 MiB hole and a trailing hole.
+        let sparse = fs::File::create(dir.join("src/sparse")).unwrap();
+        sparse.write_all_at(b"data", 4 * 1024 * 1024).unwrap();
+        sparse.set_len(8 * 1024 * 1024).unwrap();
+        fs::write(dir.join("src/sub/small"), b"small").unwrap();
+        make_node(&dir.join("src/sub/fifo"), libc::S_IFIFO | 0o600, 0).unwrap();
+
+        let transfer = Transfer {
+            moving: false,
+            sources: vec![dir.join("src")],
+            dest: dir.join("dest"),
+            follow_links: false,
+        };
+        let report = run(&transfer, &Control::default(), |_| {});
+        assert!(report.failures.is_empty(), "{:?}", report.failures);
+        assert_eq!(report.succeeded, 1);
+
+        let copy = fs::read(dir.join("dest/src/sparse")).unwrap();
+        assert_eq!(copy.len(), 8 * 1024 * 1024);
+        assert_eq!(&copy[4 * 1024 * 1024..4 * 1024 * 1024 + 4], b"data");
+        assert_eq!(fs::read(dir.join("dest/src/sub/small")).unwrap(), b"small");
+        // The FIFO is recreated rather than read, which would block forever.
+        let fifo = fs::symlink_metadata(dir.join("dest/src/sub/fifo")).unwrap();
+        assert!(fifo.file_type().is_fifo());
+
+        // Copying again fails instead of overwriting.
+        let report = run(&transfer, &Control::default(), |_| {});
+        assert_eq!(report.failures.len(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_copy_into_itself() {
+        let dir = std::env::temp_dir().join(format!("rustor-transfer-self-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("a/sub")).unwrap();
+        fs::write(dir.join("a/file"), b"data").unwrap();
+        fs::write(dir.join("b"), b"b").unwrap();
+
+        let mut queue = TransferQueue::default();
+        for (moving, follow_links) in [(false, false), (false, true), (true, false)] {
+            queue.push(Transfer {
+                moving,
+                sources: vec![dir.join("a"), dir.join("b")],
+                dest: dir.join("a/sub/../sub"),
+                follow_links,
+            });
+        }
+        let mut reports = vec![];
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while reports.len() < 3 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+            reports.extend(queue.poll());
+        }
+
+        assert_eq!(reports.len(), 3);
+        for report in &reports {
+            assert_eq!(report.failures[0].0, dir.join("a"));
+            assert!(report.failures[0].1.contains("is inside"));
+        }
+        // Only the first transfer copied `b`, the others found it there.
+        assert_eq!(reports[0].succeeded, 1);
+        assert_eq!(reports[0].failures.len(), 1);
+        assert!(!dir.join("a/sub/a").exists());
+        assert_eq!(fs::read(dir.join("a/sub/b")).unwrap(), b"b");
+        assert_eq!(fs::read(dir.join("b")).unwrap(), b"b");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,380 @@
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, BorderType, Paragraph},
+    Frame,
+};
+
+use std::time::{Duration, SystemTime};
+
+use super::diskusage::DuNode;
+use super::listing::format_size;
+
+/// How many directory levels are nested inside the current directory.
+const MAX_DEPTH: usize = 3;
+const DAY: u64 = 24 * 60 * 60;
+
+/// What the files in the treemap are colored by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorBy {
+    #[default]
+    Type,
+    Age,
+}
+
+impl ColorBy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColorBy::Type => "type",
+            ColorBy::Age => "age",
+        }
+    }
+
+    pub fn next(&self) -> ColorBy {
+        match self {
+            ColorBy::Type => ColorBy::Age,
+            ColorBy::Age => ColorBy::Type,
+        }
+    }
+}
+
+const TYPES: [(&str, Color, &[&str]); 6] = [
+    (
+        "image",
+        Color::Magenta,
+        &[
+            "png", "jpg", "jpeg", "gif", "bmp", "svg", "webp", "ico", "tif", "tiff",
+        ],
+    ),
+    (
+        "video",
+        Color::Red,
+        &["mp4", "mkv", "avi", "mov", "webm", "wmv", "flv"],
+    ),
+    (
+        "audio",
+        Color::LightRed,
+        &["mp3", "flac", "wav", "ogg", "opus", "m4a", "aac"],
+    ),
+    (
+        "archive",
+        Color::Yellow,
+        &[
+            "zip", "tar", "gz", "tgz", "xz", "zst", "bz2", "7z", "rar", "deb", "rpm", "iso", "img",
+        ],
+    ),
+    (
+        "code",
+        Color::Green,
+        &[
+            "rs", "c", "h", "cpp", "hpp", "py", "js", "ts", "go", "java", "sh", "toml", "json",
+            "yaml", "yml", "html", "css",
+        ],
+    ),
+    (
+        "document",
+        Color::Cyan,
+        &["txt", "md", "pdf", "doc", "docx", "odt", "csv", "log"],
+    ),
+];
+const OTHER_COLOR: Color = Color::Blue;
+
+const AGES: [(&str, u64, Color); 4] = [
+    ("day", DAY, Color::Red),
+    ("week", 7 * DAY, Color::Yellow),
+    ("month", 30 * DAY, Color::Green),
+    ("year", 365 * DAY, Color::Cyan),
+];
+const OLDER_COLOR: Color = Color::Blue;
+
+/// The colors of the current mode, each named in its color.
+fn legend(color_by: ColorBy) -> Line<'static> {
+    let colors: Vec<(&str, Color)> = match color_by {
+        ColorBy::Type => TYPES
+            .iter()
+            .map(|(name, color, _)| (*name, *color))
+            .chain([("other", OTHER_COLOR)])
+            .collect(),
+        ColorBy::Age => AGES
+            .iter()
+            .map(|(name, _, color)| (*name, *color))
+            .chain([("older", OLDER_COLOR)])
+            .collect(),
+    };
+    let mut spans = vec![Span::raw(format!("By {}: ", color_by.name()))];
+    for (name, color) in colors {
+        spans.push(Span::styled(name, Style::default().fg(color)));
+        spans.push(Span::raw(" "));
+    }
+    Line::from(spans)
+}
+
+fn file_color(node: &DuNode, color_by: ColorBy, now: SystemTime) -> Color {
+    match color_by {
+        ColorBy::Type => {
+            let extension = node
+                .path
+                .extension()
+                .map(|extension| extension.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            TYPES
+                .iter()
+                .find(|(_, _, extensions)| extensions.contains(&extension.as_str()))
+                .map_or(OTHER_COLOR, |(_, color, _)| *color)
+        }
+        ColorBy::Age => {
+            let age = node
+                .modified
+                .and_then(|modified| now.duration_since(modified).ok())
+                .unwrap_or(Duration::ZERO);
+            AGES.iter()
+                .find(|(_, limit, _)| age.as_secs() < *limit)
+                .map_or(OLDER_COLOR, |(_, _, color)| *color)
+        }
+    }
+}
+
+/// A tile of the treemap in fractional cells.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Tile {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Tile {
+    /// The cells covered by the tile. Shared edges round the same way, so
+    /// neighbouring tiles neither overlap nor leave gaps.
+    fn cells(&self, area: Rect) -> Rect {
+        let x0 = self.x.round() as u16;
+        let y0 = self.y.round() as u16;
+        let x1 = (self.x + self.width).round() as u16;
+        let y1 = (self.y + self.height).round() as u16;
+        Rect::new(
+            area.x + x0,
+            area.y + y0,
+            x1.saturating_sub(x0).min(area.width.saturating_sub(x0)),
+            y1.saturating_sub(y0).min(area.height.saturating_sub(y0)),
+        )
+    }
+}
+
+/// The worst aspect ratio of a row of areas laid along a side of `side`.
+fn worst_ratio(row: &[f64], side: f64) -> f64 {
+    let sum: f64 = row.iter().sum();
+    let max = row.iter().copied().fold(f64::MIN, f64::max);
+    let min = row.iter().copied().fold(f64::MAX, f64::min);
+    let side = side * side;
+    (side * max / (sum * sum)).max(sum * sum / (side * min))
+}
+
+/// Splits `tile` into tiles proportional to `sizes`, which must be sorted
+/// from largest to smallest, using the squarified algorithm of Bruls,
+/// Huizing and van Wijk: rows of tiles are grown along the shorter side for
+/// as long as that brings their aspect ratios closer to square.
+pub fn squarify(sizes: &[u64], tile: Tile) -> Vec<Tile> {
+    let mut tiles = vec![Tile::default(); sizes.len()];
+    let total: u64 = sizes.iter().sum();
+    if total == 0 || tile.width <= 0.0 || tile.height <= 0.0 {
+        return tiles;
+    }
+    let scale = tile.width * tile.height / total as f64;
+    let areas: Vec<f64> = sizes
+        .iter()
+        .take_while(|size| **size > 0)
+        .map(|size| *size as f64 * scale)
+        .collect();
+
+    let mut free = tile;
+    let mut start = 0;
+    while start < areas.len() {
+        let side = free.width.min(free.height);
+        let mut end = start + 1;
+        while end < areas.len()
+            && worst_ratio(&areas[start..=end], side) <= worst_ratio(&areas[start..end], side)
+        {
+            end += 1;
+        }
+
+        let row_area: f64 = areas[start..end].iter().sum();
+        let mut offset = 0.0;
+        if free.width >= free.height {
+            // A column along the left edge.
+            let width = row_area / free.height;
+            for (index, area) in (start..end).zip(&areas[start..end]) {
+                let height = area / width;
+                tiles[index] = Tile {
+                    x: free.x,
+                    y: free.y + offset,
+                    width,
+                    height,
+                };
+                offset += height;
+            }
+            free.x += width;
+            free.width -= width;
+        } else {
+            // A row along the top edge.
+            let height = row_area / free.width;
+            for (index, area) in (start..end).zip(&areas[start..end]) {
+                let width = area / height;
+                tiles[index] = Tile {
+                    x: free.x + offset,
+                    y: free.y,
+                    width,
+                    height,
+                };
+                offset += width;
+            }
+            free.y += height;
+            free.height -= height;
+        }
+        start = end;
+    }
+    tiles
+}
+
+struct Painter<'a, 'b> {
+    frame: &'a mut Frame<'b>,
+    on_disk: bool,
+    color_by: ColorBy,
+    now: SystemTime,
+    style: Style,
+}
+
+impl Painter<'_, '_> {
+    /// Lays out the children of `node` in `area`, nesting directories until
+    /// `depth` runs out or they get too small to hold anything.
+    fn paint_children(&mut self, node: &DuNode, area: Rect, depth: usize, selected: Option<usize>) {
+        let sizes: Vec<u64> = node
+            .children
+            .iter()
+            .map(|child| child.size(self.on_disk))
+            .collect();
+        let bounds = Tile {
+            x: 0.0,
+            y: 0.0,
+            width: area.width.into(),
+            height: area.height.into(),
+        };
+        for (index, (child, tile)) in node
+            .children
+            .iter()
+            .zip(squarify(&sizes, bounds))
+            .enumerate()
+        {
+            let cells = tile.cells(area);
+            if cells.width > 0 && cells.height > 0 {
+                self.paint(child, cells, depth, selected == Some(index));
+            }
+        }
+    }
+
+    fn paint(&mut self, node: &DuNode, area: Rect, depth: usize, highlighted: bool) {
+        let color = match node.is_dir {
+            true => Color::DarkGray,
+            false => file_color(node, self.color_by, self.now),
+        };
+        let fill = match node.is_dir {
+            true => self.style,
+            false => Style::default().bg(color).fg(Color::Black),
+        };
+        if area.width < 3 || area.height < 3 {
+            let fill = Style::default().bg(color);
+            self.frame.render_widget(Block::default().style(fill), area);
+            return;
+        }
+
+        let suffix = if node.is_dir { "/" } else { "" };
+        let mut border = Style::default().fg(color);
+        let mut block = Block::bordered().style(fill);
+        if !node.is_dir {
+            border = border.fg(Color::Black).bg(color);
+        }
+        if highlighted {
+            border = border.fg(Color::White).add_modifier(Modifier::BOLD);
+            block = block.border_type(BorderType::Thick);
+        }
+        let block = block
+            .border_style(border)
+            .title(format!("{}{suffix}", node.name()));
+        let inner = block.inner(area);
+        self.frame.render_widget(block, area);
+
+        if node.is_dir && depth > 0 && !node.children.is_empty() {
+            self.paint_children(node, inner, depth - 1, None);
+        } else {
+            self.frame.render_widget(
+                Paragraph::new(format_size(node.size(self.on_disk))).style(fill),
+                inner,
+            );
+        }
+    }
+}
+
+/// Draws the children of `node` as a squarified treemap, with the
+/// `selected` child outlined.
+pub fn render(
+    node: &DuNode,
+    selected: Option<usize>,
+    on_disk: bool,
+    color_by: ColorBy,
+    frame: &mut Frame,
+    area: Rect,
+    style: Style,
+) {
+    let block = Block::bordered().style(style).title(legend(color_by));
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let mut painter = Painter {
+        frame,
+        on_disk,
+        color_by,
+        now: SystemTime::now(),
+        style,
+    };
+    painter.paint_children(node, inner, MAX_DEPTH - 1, selected);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_squarify() {
+        // The example from the paper: a 6x4 rectangle.
+        let bounds = Tile {
+            x: 0.0,
+            y: 0.0,
+            width: 6.0,
+            height: 4.0,
+        };
+        let tiles = squarify(&[6, 6, 4, 3, 2, 2, 1, 0], bounds);
+        assert_eq!(
+            tiles[0],
+            Tile {
+                x: 0.0,
+                y: 0.0,
+                width: 3.0,
+                height: 2.0
+            }
+        );
+        assert_eq!(tiles[1].y, 2.0);
+        assert_eq!(tiles[2].x, 3.0);
+        assert_eq!(tiles[7], Tile::default());
+
+        let area: f64 = tiles.iter().map(|tile| tile.width * tile.height).sum();
+        assert!((area - 24.0).abs() < 1e-9);
+        for tile in &tiles[..7] {
+            let ratio = tile.width.max(tile.height) / tile.width.min(tile.height);
+            assert!(ratio < 3.0, "{tile:?}");
+        }
+
+        let area = Rect::new(0, 0, 6, 4);
+        let cells: u16 = tiles.iter().map(|tile| tile.cells(area).area()).sum();
+        assert_eq!(cells, 24);
+    }
+}
@@ -0,0 +1,103 @@
+use std::{collections::BTreeMap, ffi::CStr, fs, mem, ptr};
+
+#[derive(Debug, Clone, Default)]
+pub struct UserDb {
+    pub users: BTreeMap<u32, String>,
+    pub groups: BTreeMap<u32, String>,
+}
+
+impl UserDb {
+    pub fn load() -> UserDb {
+        UserDb {
+            users: read_id_file("/etc/passwd"),
+            groups: read_id_file("/etc/group"),
+        }
+    }
+
+    /// Whether `uid` belongs to a user. Ids missing from /etc/passwd are
+    /// looked up through NSS, which also knows LDAP and SSSD users, and the
+    /// names found are remembered.
+    pub fn resolve_user(&mut self, uid: u32) -> bool {
+        if self.users.contains_key(&uid) {
+            return true;
+        }
+        let name = nss_name(
+            // SAFETY: the lookup only writes to the entry and the buffer.
+            |passwd, buffer, result| unsafe {
+                libc::getpwuid_r(uid, passwd, buffer.as_mut_ptr(), buffer.len(), result)
+            },
+            |passwd: &libc::passwd| passwd.pw_name,
+        );
+        name.map(|name| self.users.insert(uid, name)).is_some()
+    }
+
+    /// Like `resolve_user` for groups missing from /etc/group.
+    pub fn resolve_group(&mut self, gid: u32) -> bool {
+        if self.groups.contains_key(&gid) {
+            return true;
+        }
+        let name = nss_name(
+            // SAFETY: the lookup only writes to the entry and the buffer.
+            |group, buffer, result| unsafe {
+                libc::getgrgid_r(gid, group, buffer.as_mut_ptr(), buffer.len(), result)
+            },
+            |group: &libc::group| group.gr_name,
+        );
+        name.map(|name| self.groups.insert(gid, name)).is_some()
+    }
+
+    pub fn user_name(&self, uid: u32) -> String {
+        self.users
+            .get(&uid)
+            .cloned()
+            .unwrap_or_else(|| uid.to_string())
+    }
+
+    pub fn group_name(&self, gid: u32) -> String {
+        self.groups
+            .get(&gid)
+            .cloned()
+            .unwrap_or_else(|| gid.to_string())
+    }
+}
+
+/// Looks up the name of a passwd or group entry with a reentrant NSS call,
+/// retrying with a bigger buffer while the entry doesn't fit.
+fn nss_name<T>(
+    lookup: impl Fn(&mut T, &mut [libc::c_char], &mut *mut T) -> libc::c_int,
+    name: impl Fn(&T) -> *const libc::c_char,
+) -> Option<String> {
+    // SAFETY: passwd and group are plain C structs, valid when zeroed.
+    let mut entry: T = unsafe { mem::zeroed() };
+    let mut buffer = vec![0; 1024];
+    loop {
+        let mut result = ptr::null_mut();
+        match lookup(&mut entry, &mut buffer, &mut result) {
+            // SAFETY: on success the name is a C string inside `buffer`.
+            0 if !result.is_null() => {
+                let name = unsafe { CStr::from_ptr(name(&entry)) };
+                return Some(name.to_string_lossy().into_owned());
+            }
+            libc::ERANGE if buffer.len() < 1024 * 1024 => buffer.resize(buffer.len() * 2, 0),
+            _ => return None,
+        }
+    }
+}
+
+/// Reads `name:password:id:...` lines as used by both passwd and group files.
+fn read_id_file(path: &str) -> BTreeMap<u32, String> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return BTreeMap::new();
+    };
+
+    content
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let id = fields.nth(1)?.parse().ok()?;
+            Some((id, name.to_string()))
+        })
+        .collect()
+}
@@ -0,0 +1,449 @@
+use std::{
+    fmt, fs,
+    io::{self, Read},
+    path::Path,
+};
+
+#[cfg(test)]
+use std::{collections::BTreeMap, path::PathBuf, sync::Mutex, time::SystemTime};
+
+use super::archive::{Archive, ArchiveFormat};
+use super::batch::copy_recursive;
+use super::listing::FileEntry;
+use super::rename::rename_new;
+
+#[cfg(test)]
+use super::listing::EntryKind;
+
+/// The directories the file tree can browse: the local filesystem or the
+/// inside of an archive, addressed as `/path/to/archive.tar/member`.
+/// Filesystems that can't be changed keep the default mutations, which fail.
+pub trait FileSystem: fmt::Debug {
+    /// The archive backing this filesystem, `None` for the local filesystem.
+    fn archive(&self) -> Option<&Path> {
+        None
+    }
+
+    /// Whether the read-only safety mode rejects every change.
+    fn read_only(&self) -> bool {
+        false
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<FileEntry>>;
+
+    /// The entry at `path`, without following a final symlink.
+    fn metadata(&self, path: &Path) -> io::Result<FileEntry>;
+
+    /// Reads up to `limit` bytes from the start of a file.
+    fn read(&self, path: &Path, limit: u64) -> io::Result<Vec<u8>>;
+
+    /// Copies a file or directory out to `dest` on the local filesystem.
+    fn copy_out(&self, path: &Path, dest: &Path) -> io::Result<()>;
+
+    /// Creates an empty file, failing if `path` exists.
+    fn create_file(&self, path: &Path) -> io::Result<()> {
+        Err(unsupported(self, path))
+    }
+
+    fn create_dir(&self, path: &Path) -> io::Result<()> {
+        Err(unsupported(self, path))
+    }
+
+    /// Removes a file, a link or a whole directory.
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        Err(unsupported(self, path))
+    }
+
+    /// Moves `from` to `to`, failing if `to` exists.
+    fn rename(&self, from: &Path, _to: &Path) -> io::Result<()> {
+        Err(unsupported(self, from))
+    }
+
+    /// Copies a file or directory within this filesystem, failing if `to`
+    /// exists.
+    fn copy(&self, from: &Path, _to: &Path) -> io::Result<()> {
+        Err(unsupported(self, from))
+    }
+}
+
+fn unsupported<F: FileSystem + ?Sized>(fs: &F, path: &Path) -> io::Error {
+    let message = match fs.archive() {
+        Some(archive) => format!("{} is read-only", archive.display()),
+        None => format!("{} is read-only", path.display()),
+    };
+    io::Error::new(io::ErrorKind::ReadOnlyFilesystem, message)
+}
+
+#[cfg(test)]
+fn already_exists(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        format!("{} already exists", path.display()),
+    )
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LocalFs;
+
+impl FileSystem for LocalFs {
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<FileEntry>> {
+        Ok(fs::read_dir(path)?
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                // Doesn't follow symlinks, so links are listed as links.
+                let metadata = fs::symlink_metadata(entry.path()).ok()?;
+                Some(FileEntry::from_metadata(entry.path(), &metadata))
+            })
+            .collect())
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FileEntry> {
+        let metadata = fs::symlink_metadata(path)?;
+        Ok(FileEntry::from_metadata(path.to_path_buf(), &metadata))
+    }
+
+    fn read(&self, path: &Path, limit: u64) -> io::Result<Vec<u8>> {
+        let mut content = vec![];
+        fs::File::open(path)?
+            .take(limit)
+            .read_to_end(&mut content)?;
+        Ok(content)
+    }
+
+    fn copy_out(&self, path: &Path, dest: &Path) -> io::Result<()> {
+        copy_recursive(path, dest, false)
+    }
+
+    fn create_file(&self, path: &Path) -> io::Result<()> {
+        fs::File::create_new(path).map(|_| ())
+    }
+
+    fn create_dir(&self, path: &Path) -> io::Result<()> {
+        fs::create_dir(path)
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        if fs::symlink_metadata(path)?.is_dir() {
+            fs::remove_dir_all(path)
+        } else {
+            fs::remove_file(path)
+        }
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        match rename_new(from, to) {
+            Err(err) if err.kind() == io::ErrorKind::CrossesDevices => {
+                copy_recursive(from, to, false)?;
+                self.remove(from)
+            }
+            result => result,
+        }
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<()> {
+        copy_recursive(from, to, false)
+    }
+}
+
+/// Wraps a filesystem for the read-only safety mode: browsing works as
+/// usual, but every change is refused, including copies out of it.
+#[derive(Debug)]
+pub struct ReadOnly(pub Box<dyn FileSystem>);
+
+impl FileSystem for ReadOnly {
+    fn archive(&self) -> Option<&Path> {
+        self.0.archive()
+    }
+
+    fn read_only(&self) -> bool {
+        true
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<FileEntry>> {
+        self.0.read_dir(path)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FileEntry> {
+        self.0.metadata(path)
+    }
+
+    fn read(&self, path: &Path, limit: u64) -> io::Result<Vec<u8>> {
+        self.0.read(path, limit)
+    }
+
+    fn copy_out(&self, path: &Path, _dest: &Path) -> io::Result<()> {
+        Err(unsupported(self, path))
+    }
+}
+
+/// A filesystem kept in memory, for tests that shouldn't touch the disk.
+/// Every entry has the same modification time so listings are deterministic.
+#[cfg(test)]
+#[derive(Debug)]
+pub struct MemoryFs {
+    /// The contents of each file, `None` for directories.
+    nodes: Mutex<BTreeMap<PathBuf, Option<Vec<u8>>>>,
+}
+
+#[cfg(test)]
+impl MemoryFs {
+    /// An empty filesystem with just the root directory.
+    pub fn new() -> MemoryFs {
+        MemoryFs {
+            nodes: Mutex::new(BTreeMap::from([(PathBuf::from("/"), None)])),
+        }
+    }
+
+    /// Adds a file with `content`, creating missing parent directories.
+    pub fn with_file(self, path: &str, content: &[u8]) -> MemoryFs {
+        {
+            let mut nodes = self.nodes.lock().unwrap();
+            for ancestor in Path::new(path).ancestors().skip(1) {
+                nodes.entry(ancestor.to_path_buf()).or_insert(None);
+            }
+            nodes.insert(PathBuf::from(path), Some(content.to_vec()));
+        }
+        self
+    }
+
+    fn entry(path: &Path, node: &Option<Vec<u8>>) -> FileEntry {
+        let (kind, size, mode) = match node {
+            Some(content) => (EntryKind::File, content.len() as u64, 0o644),
+            None => (EntryKind::Dir, 0, 0o755),
+        };
+        FileEntry::new(
+            path.to_path_buf(),
+            kind,
+            size,
+            mode,
+            Some(SystemTime::UNIX_EPOCH),
+        )
+    }
+
+    /// Checks that `path` is free and its parent is a directory.
+    fn check_new(nodes: &BTreeMap<PathBuf, Option<Vec<u8>>>, path: &Path) -> io::Result<()> {
+        if nodes.contains_key(path) {
+            return Err(already_exists(path));
+        }
+        match path.parent().map(|parent| nodes.get(parent)) {
+            Some(Some(None)) => Ok(()),
+            _ => Err(not_found(path.parent().unwrap_or(path))),
+        }
+    }
+
+    /// The entries at or below `path`, with their path relative to it.
+    fn subtree(
+        nodes: &BTreeMap<PathBuf, Option<Vec<u8>>>,
+        path: &Path,
+    ) -> io::Result<Vec<(PathBuf, Option<Vec<u8>>)>> {
+        if !nodes.contains_key(path) {
+            return Err(not_found(path));
+        }
+        Ok(nodes
+            .iter()
+            .filter_map(|(inner, node)| {
+                let relative = inner.strip_prefix(path).ok()?;
+                Some((relative.to_path_buf(), node.clone()))
+            })
+            .collect())
+    }
+
+    fn copy_subtree(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let mut nodes = self.nodes.lock().unwrap();
+        Self::check_new(&nodes, to)?;
+        if to.starts_with(from) {
+            return Err(io::Error::other(format!(
+                "{} is inside {}",
+                to.display(),
+                from.display()
+            )));
+        }
+        for (relative, node) in Self::subtree(&nodes, from)? {
+            nodes.insert(to.join(relative), node);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("{} not found", path.display()),
+    )
+}
+
+#[cfg(test)]
+impl FileSystem for MemoryFs {
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<FileEntry>> {
+        let nodes = self.nodes.lock().unwrap();
+        match nodes.get(path) {
+            Some(None) => Ok(nodes
+                .iter()
+                .filter(|(inner, _)| inner.parent() == Some(path))
+                .map(|(inner, node)| MemoryFs::entry(inner, node))
+                .collect()),
+            Some(Some(_)) => Err(io::Error::new(
+                io::ErrorKind::NotADirectory,
+                format!("{} is not a directory", path.display()),
+            )),
+            None => Err(not_found(path)),
+        }
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FileEntry> {
+        let nodes = self.nodes.lock().unwrap();
+        let node = nodes.get(path).ok_or_else(|| not_found(path))?;
+        Ok(MemoryFs::entry(path, node))
+    }
+
+    fn read(&self, path: &Path, limit: u64) -> io::Result<Vec<u8>> {
+        match self.nodes.lock().unwrap().get(path) {
+            Some(Some(content)) => Ok(content.iter().take(limit as usize).copied().collect()),
+            Some(None) => Err(io::Error::new(
+                io::ErrorKind::IsADirectory,
+                format!("{} is a directory", path.display()),
+            )),
+            None => Err(not_found(path)),
+        }
+    }
+
+    fn copy_out(&self, path: &Path, dest: &Path) -> io::Result<()> {
+        let subtree = MemoryFs::subtree(&self.nodes.lock().unwrap(), path)?;
+        if fs::symlink_metadata(dest).is_ok() {
+            return Err(already_exists(dest));
+        }
+        // Parents sort before their children, so directories come first.
+        for (relative, node) in subtree {
+            match node {
+                Some(content) => fs::write(dest.join(relative), content)?,
+                None => fs::create_dir(dest.join(relative))?,
+            }
+        }
+        Ok(())
+    }
+
+    fn create_file(&self, path: &Path) -> io::Result<()> {
+        let mut nodes = self.nodes.lock().unwrap();
+        MemoryFs::check_new(&nodes, path)?;
+        nodes.insert(path.to_path_buf(), Some(vec![]));
+        Ok(())
+    }
+
+    fn create_dir(&self, path: &Path) -> io::Result<()> {
+        let mut nodes = self.nodes.lock().unwrap();
+        MemoryFs::check_new(&nodes, path)?;
+        nodes.insert(path.to_path_buf(), None);
+        Ok(())
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        let mut nodes = self.nodes.lock().unwrap();
+        if path.parent().is_none() || !nodes.contains_key(path) {
+            return Err(not_found(path));
+        }
+        nodes.retain(|inner, _| !inner.starts_with(path));
+        Ok(())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        self.copy_subtree(from, to)?;
+        self.nodes
+            .lock()
+            .unwrap()
+            .retain(|inner, _| !inner.starts_with(from));
+        Ok(())
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<()> {
+        self.copy_subtree(from, to)
+    }
+}
+
+/// The archive `path` lies in, if any of its ancestors is an archive file.
+pub fn archive_root(path: &Path) -> Option<&Path> {
+    path.ancestors()
+        .find(|ancestor| ArchiveFormat::detect(ancestor).is_some() && ancestor.is_file())
+}
+
+/// Opens the filesystem `path` lives on, reusing `current` if it already is
+/// and is in the requested mode.
+pub fn open(
+    path: &Path,
+    current: &dyn FileSystem,
+    read_only: bool,
+) -> io::Result<Option<Box<dyn FileSystem>>> {
+    let root = archive_root(path);
+    if root == current.archive() && current.read_only() == read_only {
+        return Ok(None);
+    }
+
+    let fs: Box<dyn FileSystem> = match root {
+        Some(root) => Box::new(Archive::open(root)?),
+        None => Box::new(LocalFs),
+    };
+    Ok(Some(match read_only {
+        true => Box::new(ReadOnly(fs)),
+        false => fs,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_fs() {
+        let fs = MemoryFs::new().with_file("/docs/a.txt", b"hello");
+        fs.create_dir(Path::new("/docs/sub")).unwrap();
+        fs.create_file(Path::new("/docs/sub/b.txt")).unwrap();
+        assert!(fs.create_file(Path::new("/missing/c.txt")).is_err());
+        assert!(fs.create_dir(Path::new("/docs/a.txt")).is_err());
+
+        fs.copy(Path::new("/docs"), Path::new("/backup")).unwrap();
+        fs.rename(Path::new("/docs/sub"), Path::new("/moved"))
+            .unwrap();
+        fs.remove(Path::new("/docs/a.txt")).unwrap();
+        assert!(fs.read_dir(Path::new("/docs")).unwrap().is_empty());
+
+        let names = |dir: &str| -> Vec<String> {
+            let mut names: Vec<String> = fs
+                .read_dir(Path::new(dir))
+                .unwrap()
+                .iter()
+                .map(|entry| entry.name())
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(names("/"), ["backup", "docs", "moved"]);
+        assert_eq!(names("/backup"), ["a.txt", "sub"]);
+        assert_eq!(names("/moved"), ["b.txt"]);
+        assert_eq!(fs.read(Path::new("/backup/a.txt"), 4).unwrap(), b"hell");
+        assert!(fs.metadata(Path::new("/backup/sub")).unwrap().is_dir());
+    }
+
+    #[test]
+    fn test_read_only() {
+        let fs = ReadOnly(Box::new(MemoryFs::new().with_file("/a.txt", b"hello")));
+        let path = Path::new("/a.txt");
+        assert_eq!(fs.read(path, 64).unwrap(), b"hello");
+        assert_eq!(fs.read_dir(Path::new("/")).unwrap().len(), 1);
+
+        let refused = [
+            fs.create_file(Path::new("/b.txt")),
+            fs.create_dir(Path::new("/dir")),
+            fs.remove(path),
+            fs.rename(path, Path::new("/b.txt")),
+            fs.copy(path, Path::new("/b.txt")),
+            fs.copy_out(path, Path::new("/tmp/b.txt")),
+        ];
+        for result in refused {
+            assert_eq!(
+                result.unwrap_err().kind(),
+                io::ErrorKind::ReadOnlyFilesystem
+            );
+        }
+        assert_eq!(fs.read_dir(Path::new("/")).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,149 @@
+use notify::{
+    event::{AccessKind, AccessMode},
+    Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher,
+};
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+    time::{Duration, Instant},
+};
+
+const DEBOUNCE: Duration = Duration::from_millis(200);
+/// The longest changes are held back while events keep coming, so a
+/// directory that never goes quiet still refreshes.
+const MAX_LATENCY: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+pub enum Changes {
+    Paths(HashSet<PathBuf>),
+    Rescan,
+}
+
+/// Watches the open directory and batches bursts of inotify events until
+/// they have been quiet for `DEBOUNCE`, or for at most `MAX_LATENCY`.
+#[derive(Debug)]
+pub struct DirWatcher {
+    watcher: RecommendedWatcher,
+    receiver: Receiver<notify::Result<Event>>,
+    watched: Option<PathBuf>,
+    pending: HashSet<PathBuf>,
+    rescan: bool,
+    /// The first event not reported yet.
+    first_event: Option<Instant>,
+    last_event: Option<Instant>,
+}
+
+impl DirWatcher {
+    pub fn new() -> notify::Result<DirWatcher> {
+        let (sender, receiver) = mpsc::channel();
+        let watcher = notify::recommended_watcher(move |event| {
+            let _ = sender.send(event);
+        })?;
+
+        Ok(DirWatcher {
+            watcher,
+            receiver,
+            watched: None,
+            pending: HashSet::new(),
+            rescan: false,
+            first_event: None,
+            last_event: None,
+        })
+    }
+
+    pub fn watch(&mut self, path: &Path) -> notify::Result<()> {
+        if self.watched.as_deref() == Some(path) {
+            return Ok(());
+        }
+        if let Some(previous) = self.watched.take() {
+            let _ = self.watcher.unwatch(&previous);
+        }
+
+        self.pending.clear();
+        self.rescan = false;
+        self.first_event = None;
+        self.last_event = None;
+        self.watcher.watch(path, RecursiveMode::NonRecursive)?;
+        self.watched = Some(path.to_path_buf());
+        Ok(())
+    }
+
+    pub fn poll(&mut self) -> Option<Changes> {
+        while let Ok(event) = self.receiver.try_recv() {
+            match event {
+                // Opening and reading entries doesn't change the listing.
+                Ok(Event {
+                    kind: EventKind::Access(kind),
+                    ..
+                }) if kind != AccessKind::Close(AccessMode::Write) => continue,
+                Ok(event) if event.need_rescan() || event.paths.is_empty() => self.rescan = true,
+                Ok(event) => self.pending.extend(event.paths),
+                Err(_) => self.rescan = true,
+            }
+            self.last_event = Some(Instant::now());
+            self.first_event.get_or_insert_with(Instant::now);
+        }
+
+        let quiet = self
+            .last_event
+            .is_some_and(|last| last.elapsed() >= DEBOUNCE);
+        let overdue = self
+            .first_event
+            .is_some_and(|first| first.elapsed() >= MAX_LATENCY);
+        if !quiet && !overdue {
+            return None;
+        }
+
+        self.first_event = None;
+        self.last_event = None;
+        if std::mem::take(&mut self.rescan) {
+            self.pending.clear();
+            Some(Changes::Rescan)
+        } else if self.pending.is_empty() {
+            None
+        } else {
+            Some(Changes::Paths(std::mem::take(&mut self.pending)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, thread};
+
+    #[test]
+    fn test_poll() {
+        let dir = env::temp_dir().join(format!("rustor-watch-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let mut watcher = DirWatcher::new().unwrap();
+        watcher.watch(&dir).unwrap();
+
+        fs::write(dir.join("new"), "").unwrap();
+        let mut changes = None;
+        for _ in 0..100 {
+            thread::sleep(Duration::from_millis(50));
+            changes = watcher.poll();
+            if changes.is_some() {
+                break;
+            }
+        }
+        match changes {
+            Some(Changes::Paths(paths)) => assert!(paths.contains(&dir.join("new"))),
+            other => panic!("{other:?}"),
+        }
+
+        // Events that never stop are still reported once overdue.
+        let now = Instant::now();
+        watcher.pending.insert(dir.join("busy"));
+        watcher.last_event = Some(now);
+        watcher.first_event = Some(now - DEBOUNCE);
+        assert!(watcher.poll().is_none());
+        watcher.first_event = Some(now - MAX_LATENCY);
+        assert!(matches!(watcher.poll(), Some(Changes::Paths(_))));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
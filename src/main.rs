@@ -14,6 +14,7 @@ use ratatui::{
 use std::{
     fmt::Debug,
     io::{self, stdout},
+    process::Command,
     time::Duration,
 };
 
@@ -103,6 +104,7 @@ fn main() -> io::Result<()> {
     let mut model = Rustor::new(apps);
 
     while !model.exit {
+        tick(&mut model);
         terminal.draw(|f| view(&mut model, f))?;
 
         let mut current_msg = handle_event(&mut model)?;
@@ -110,6 +112,10 @@ fn main() -> io::Result<()> {
         if current_msg.is_some() {
             update(&mut model, current_msg.clone().unwrap());
         }
+
+        if let Some(command) = take_command(&mut model) {
+            run_command(&mut terminal, &mut model, command)?;
+        }
     }
 
     restore_terminal()?;
@@ -146,6 +152,47 @@ fn update(model: &mut Rustor, msg: Message) {
     }
 }
 
+fn tick(model: &mut Rustor) {
+    for app in model.apps.iter_mut() {
+        match app {
+            AppType::FileTreeApp(app) => app.tick(),
+            AppType::MainScreenApp(app) => app.tick(),
+            AppType::LoggingApp(app) => app.tick(),
+            AppType::NetScan(app) => app.tick(),
+        }
+    }
+}
+
+fn take_command(model: &mut Rustor) -> Option<Command> {
+    match &mut model.apps[model.selected_app] {
+        AppType::FileTreeApp(app) => app.take_command(),
+        AppType::MainScreenApp(app) => app.take_command(),
+        AppType::LoggingApp(app) => app.take_command(),
+        AppType::NetScan(app) => app.take_command(),
+    }
+}
+
+/// Hands the terminal to `command` until it exits, then redraws from scratch.
+fn run_command<B: Backend>(
+    terminal: &mut Terminal<B>,
+    model: &mut Rustor,
+    mut command: Command,
+) -> io::Result<()> {
+    restore_terminal()?;
+    let status = command.status();
+    enable_raw_mode()?;
+    stdout().execute(EnterAlternateScreen)?;
+    terminal.clear()?;
+
+    match &mut model.apps[model.selected_app] {
+        AppType::FileTreeApp(app) => app.resume(status),
+        AppType::MainScreenApp(app) => app.resume(status),
+        AppType::LoggingApp(app) => app.resume(status),
+        AppType::NetScan(app) => app.resume(status),
+    }
+    Ok(())
+}
+
 fn view(model: &mut Rustor, frame: &mut Frame) {
     let items: Vec<ListItem> = model
         .apps
@@ -208,8 +255,14 @@ fn handle_event(model: &mut Rustor) -> io::Result<Option<Message>> {
 
 fn handle_key(model: &mut Rustor, key: event::KeyEvent) -> Option<Message> {
     if model.app_focused {
+        let captured = match &model.apps[model.selected_app] {
+            AppType::FileTreeApp(app) => app.captures_key(&key),
+            AppType::MainScreenApp(app) => app.captures_key(&key),
+            AppType::LoggingApp(app) => app.captures_key(&key),
+            AppType::NetScan(app) => app.captures_key(&key),
+        };
         match key.code {
-            KeyCode::Tab => Some(Message::SwapFocus),
+            KeyCode::Tab if !captured => Some(Message::SwapFocus),
             _ => match &mut model.apps[model.selected_app] {
                 AppType::FileTreeApp(app) => {
                     app_update(app, key);